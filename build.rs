@@ -0,0 +1,46 @@
+//! Compiles the information identifying the build into the binary, which
+//! `quill version --json` prints.
+
+use std::{env, fs, path::Path, process::Command};
+
+// Reruns the build script when HEAD moves: `.git/HEAD` only changes when another branch is
+// checked out, while a commit updates the branch it refers to and the log of HEAD. Files which
+// do not exist are not watched, since cargo would then rerun the script on every build.
+fn watch_git_head() {
+    let mut watched = vec![".git/HEAD".to_string(), ".git/logs/HEAD".to_string()];
+    if let Ok(head) = fs::read_to_string(".git/HEAD") {
+        if let Some(reference) = head.trim().strip_prefix("ref: ") {
+            watched.push(format!(".git/{}", reference));
+            // Refs are moved to this file by `git pack-refs`, e.g. during `git gc`.
+            watched.push(".git/packed-refs".to_string());
+        }
+    }
+    for path in watched {
+        if Path::new(&path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
+
+fn main() {
+    // Builds from a source archive have no git repository, and can set the commit instead.
+    println!("cargo:rerun-if-env-changed=QUILL_GIT_COMMIT");
+    watch_git_head();
+    let commit = env::var("QUILL_GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(&["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|commit| commit.trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=QUILL_GIT_COMMIT={}",
+        commit.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "cargo:rustc-env=QUILL_TARGET={}",
+        env::var("TARGET").unwrap()
+    );
+}
@@ -0,0 +1,25 @@
+//! Reads a payment request, e.g. from a QR code, and prints the transfer it asks for.
+//!
+//! ```sh
+//! cargo run --example payment_request -- 'icp:345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752?amount=1.5&memo=42'
+//! ```
+
+use anyhow::Context;
+use quill::nns_types::{icpts::E8s, payment_request::PaymentRequest, Memo};
+
+fn main() -> anyhow::Result<()> {
+    let uri = std::env::args()
+        .nth(1)
+        .context("Usage: payment_request <icp: URI>")?;
+    let request = PaymentRequest::from_uri(&uri)?;
+    println!("To: {}", request.to);
+    match request.amount {
+        Some(amount) => println!("Amount: {} ICP ({})", amount, E8s::from(amount)),
+        None => println!("Amount: not requested"),
+    }
+    let memo = Memo(request.memo.unwrap_or_default());
+    println!("Memo: {}", memo.0);
+    // Amounts serialize as they are displayed.
+    println!("JSON: {}", serde_json::to_string(&request.amount)?);
+    Ok(())
+}
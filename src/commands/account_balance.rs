@@ -1,5 +1,5 @@
 use crate::{
-    commands::send::submit_unsigned_ingress,
+    commands::send::{submit_unsigned_ingress, QueryOpts},
     lib::{ledger_canister_id, AnyhowResult},
 };
 use candid::{CandidType, Encode};
@@ -23,6 +23,9 @@ pub struct AccountBalanceOpts {
     /// Will display the query, but not send it.
     #[clap(long)]
     dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
 }
 
 // We currently only support a subset of the functionality.
@@ -36,6 +39,7 @@ pub async fn exec(opts: AccountBalanceOpts, fetch_root_key: bool) -> AnyhowResul
         args,
         opts.yes,
         opts.dry_run,
+        opts.query.fee_free,
         fetch_root_key,
     )
     .await
@@ -0,0 +1,385 @@
+use crate::{
+    commands::send::{submit_unsigned_ingress, submit_unsigned_ingress_raw, QueryOpts},
+    lib::{
+        index_canister_id,
+        timestamp::{display_utc, format_timestamp, parse_timestamp},
+        AnyhowResult,
+    },
+};
+use anyhow::{bail, Context};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use clap::{ArgEnum, Parser};
+use rayon::prelude::*;
+
+#[derive(CandidType)]
+pub struct GetAccountIdentifierTransactionsArgs {
+    pub max_results: u64,
+    pub start: Option<u64>,
+    pub account_identifier: String,
+}
+
+/// Queries the transactions of a ledger account from the index canister.
+#[derive(Parser)]
+pub struct AccountTransactionsOpts {
+    /// The id of the account to query.
+    account_id: String,
+
+    /// The maximum number of transactions to fetch, newest first. With --format, they are
+    /// fetched in pages of 1000, which are decoded in parallel.
+    #[clap(long, default_value = "100")]
+    max_results: u64,
+
+    /// Prints the transactions in chronological order in the given format, with the balance of
+    /// the account after each transaction.
+    #[clap(long, arg_enum, conflicts_with_all(&["dry-run", "fee-free"]))]
+    format: Option<TransactionFormat>,
+
+    /// Only prints the transactions made at or after this instant, e.g. 2023-01-01 or
+    /// 2023-01-01T12:00:00Z.
+    #[clap(long, requires("format"))]
+    since: Option<String>,
+
+    /// Skips confirmation and sends the message directly.
+    #[clap(long)]
+    yes: bool,
+
+    /// Will display the query, but not send it.
+    #[clap(long)]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+/// The formats of the transactions printed by `account-transactions --format`.
+#[derive(Debug, Clone, Copy, ArgEnum)]
+pub enum TransactionFormat {
+    Csv,
+}
+
+/// The number of transactions requested per call for `--format`, so that long histories are
+/// fetched in several replies.
+const PAGE_SIZE: u64 = 1_000;
+
+pub async fn exec(opts: AccountTransactionsOpts, fetch_root_key: bool) -> AnyhowResult {
+    if let Some(TransactionFormat::Csv) = opts.format {
+        let since = opts.since.as_deref().map(parse_timestamp).transpose()?;
+        let pages = fetch_pages(&opts.account_id, opts.max_results, since, fetch_root_key).await?;
+        print!(
+            "{}",
+            render_csv(&pages, &opts.account_id, since, display_utc())?
+        );
+        return Ok(());
+    }
+    let args = Encode!(&GetAccountIdentifierTransactionsArgs {
+        max_results: opts.max_results,
+        start: None,
+        account_identifier: opts.account_id.clone(),
+    })?;
+    submit_unsigned_ingress(
+        index_canister_id(),
+        "get_account_identifier_transactions",
+        args,
+        opts.yes,
+        opts.dry_run,
+        opts.query.fee_free,
+        fetch_root_key,
+    )
+    .await
+}
+
+// Only the fields needed for the CSV export are decoded; the others are skipped.
+#[derive(CandidType, Deserialize)]
+struct Tokens {
+    e8s: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+struct TimeStamp {
+    timestamp_nanos: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+enum Operation {
+    Approve {
+        from: String,
+        fee: Tokens,
+    },
+    Burn {
+        from: String,
+        amount: Tokens,
+    },
+    Mint {
+        to: String,
+        amount: Tokens,
+    },
+    Transfer {
+        from: String,
+        to: String,
+        amount: Tokens,
+        fee: Tokens,
+    },
+}
+
+#[derive(CandidType, Deserialize)]
+struct Transaction {
+    memo: u64,
+    operation: Operation,
+    timestamp: Option<TimeStamp>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct TransactionWithId {
+    id: u64,
+    transaction: Transaction,
+}
+
+#[derive(CandidType, Deserialize)]
+struct GetAccountIdentifierTransactionsResponse {
+    balance: u64,
+    transactions: Vec<TransactionWithId>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct GetAccountIdentifierTransactionsError {
+    message: String,
+}
+
+#[derive(CandidType, Deserialize)]
+enum GetAccountIdentifierTransactionsResult {
+    Ok(GetAccountIdentifierTransactionsResponse),
+    Err(GetAccountIdentifierTransactionsError),
+}
+
+// The ids and timestamps of a page of transactions, which are enough to request the next page.
+// Decoding them skips the other fields, so pages are fetched without waiting for the full
+// decoding, which is done in parallel once all pages are fetched.
+#[derive(CandidType, Deserialize)]
+struct TransactionTime {
+    timestamp: Option<TimeStamp>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct TransactionHeader {
+    id: u64,
+    transaction: TransactionTime,
+}
+
+#[derive(CandidType, Deserialize)]
+struct PageHeader {
+    transactions: Vec<TransactionHeader>,
+}
+
+#[derive(CandidType, Deserialize)]
+enum PageHeaderResult {
+    Ok(PageHeader),
+    Err(GetAccountIdentifierTransactionsError),
+}
+
+// Fetches up to `max_results` transactions, newest first, in pages of at most `PAGE_SIZE`, and
+// returns the raw replies. Stops at the first transaction made before `since`.
+async fn fetch_pages(
+    account: &str,
+    max_results: u64,
+    since: Option<u64>,
+    fetch_root_key: bool,
+) -> AnyhowResult<Vec<Vec<u8>>> {
+    let mut pages = Vec::new();
+    let mut start = None;
+    let mut remaining = max_results;
+    while remaining > 0 {
+        let args = Encode!(&GetAccountIdentifierTransactionsArgs {
+            max_results: remaining.min(PAGE_SIZE),
+            start,
+            account_identifier: account.to_string(),
+        })?;
+        let reply = submit_unsigned_ingress_raw(
+            index_canister_id(),
+            "get_account_identifier_transactions",
+            args,
+            fetch_root_key,
+        )
+        .await?;
+        let header = match Decode!(&reply, PageHeaderResult)
+            .context("Cannot decode get_account_identifier_transactions reply")?
+        {
+            PageHeaderResult::Ok(header) => header,
+            PageHeaderResult::Err(err) => {
+                bail!("The index canister returned an error: {}", err.message)
+            }
+        };
+        // Even an empty page is kept, since it has the balance of the account.
+        pages.push(reply);
+        let last = match header.transactions.last() {
+            Some(last) => last,
+            None => break,
+        };
+        let last_seconds = last
+            .transaction
+            .timestamp
+            .as_ref()
+            .map(|t| t.timestamp_nanos / 1_000_000_000);
+        if since.is_some() && last_seconds < since {
+            break;
+        }
+        remaining = remaining.saturating_sub(header.transactions.len() as u64);
+        // The next page starts after the oldest transaction of this one.
+        start = Some(last.id);
+    }
+    Ok(pages)
+}
+
+// A transaction of the CSV export, with the change of the balance of the account it made.
+struct Row {
+    id: u64,
+    seconds: Option<u64>,
+    kind: &'static str,
+    from: String,
+    to: String,
+    amount: u64,
+    fee: u64,
+    memo: u64,
+    change: i128,
+}
+
+// Decodes a page of transactions, newest first, and returns the balance of the account with
+// the rows.
+fn decode_page(reply: &[u8], account: &str) -> AnyhowResult<(u64, Vec<Row>)> {
+    let response = match Decode!(reply, GetAccountIdentifierTransactionsResult)
+        .context("Cannot decode get_account_identifier_transactions reply")?
+    {
+        GetAccountIdentifierTransactionsResult::Ok(response) => response,
+        GetAccountIdentifierTransactionsResult::Err(err) => {
+            bail!("The index canister returned an error: {}", err.message)
+        }
+    };
+    let rows = response
+        .transactions
+        .into_iter()
+        .map(|tx| {
+            let (kind, from, to, amount, fee) = match tx.transaction.operation {
+                Operation::Approve { from, fee } => ("approve", from, String::new(), 0, fee.e8s),
+                Operation::Burn { from, amount } => ("burn", from, String::new(), amount.e8s, 0),
+                Operation::Mint { to, amount } => ("mint", String::new(), to, amount.e8s, 0),
+                Operation::Transfer {
+                    from,
+                    to,
+                    amount,
+                    fee,
+                } => ("transfer", from, to, amount.e8s, fee.e8s),
+            };
+            let mut change = 0;
+            if from == account {
+                change -= i128::from(amount) + i128::from(fee);
+            }
+            if to == account {
+                change += i128::from(amount);
+            }
+            Row {
+                id: tx.id,
+                seconds: tx
+                    .transaction
+                    .timestamp
+                    .map(|t| t.timestamp_nanos / 1_000_000_000),
+                kind,
+                from,
+                to,
+                amount,
+                fee,
+                memo: tx.transaction.memo,
+                change,
+            }
+        })
+        .collect();
+    Ok((response.balance, rows))
+}
+
+// Renders the transactions oldest first. The pages are decoded in parallel, and kept in order.
+// The index canister returns the current balance and the transactions newest first, so the
+// running balance is computed backwards from it. Timestamps are in UTC if `utc` is set, and in
+// local time otherwise.
+fn render_csv(
+    pages: &[Vec<u8>],
+    account: &str,
+    since: Option<u64>,
+    utc: bool,
+) -> AnyhowResult<String> {
+    let pages = pages
+        .par_iter()
+        .map(|reply| decode_page(reply, account))
+        .collect::<AnyhowResult<Vec<_>>>()?;
+    let mut balance = pages.first().map_or(0, |(balance, _)| i128::from(*balance));
+    let mut lines = Vec::new();
+    for row in pages.into_iter().flat_map(|(_, rows)| rows) {
+        if since.is_none() || row.seconds >= since {
+            let timestamp = row
+                .seconds
+                .map_or_else(String::new, |seconds| format_timestamp(seconds, utc));
+            lines.push(format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                row.id,
+                timestamp,
+                row.kind,
+                row.from,
+                row.to,
+                row.amount,
+                row.fee,
+                row.memo,
+                balance
+            ));
+        }
+        balance -= row.change;
+    }
+    let mut csv = "id,timestamp,type,from,to,amount_e8s,fee_e8s,memo,balance_e8s\n".to_string();
+    csv.extend(lines.into_iter().rev());
+    Ok(csv)
+}
+
+#[test]
+fn test_render_csv() {
+    let page = |transactions| {
+        Encode!(&GetAccountIdentifierTransactionsResult::Ok(
+            GetAccountIdentifierTransactionsResponse {
+                balance: 70,
+                transactions,
+            }
+        ))
+        .unwrap()
+    };
+    let newest = page(vec![TransactionWithId {
+        id: 2,
+        transaction: Transaction {
+            memo: 1,
+            operation: Operation::Transfer {
+                from: "me".to_string(),
+                to: "you".to_string(),
+                amount: Tokens { e8s: 20 },
+                fee: Tokens { e8s: 10 },
+            },
+            timestamp: None,
+        },
+    }]);
+    let oldest = page(vec![TransactionWithId {
+        id: 1,
+        transaction: Transaction {
+            memo: 0,
+            operation: Operation::Mint {
+                to: "me".to_string(),
+                amount: Tokens { e8s: 100 },
+            },
+            timestamp: Some(TimeStamp {
+                timestamp_nanos: 1_671_545_000_000_000_000,
+            }),
+        },
+    }]);
+    match Decode!(&oldest, PageHeaderResult).unwrap() {
+        PageHeaderResult::Ok(header) => assert_eq!(header.transactions[0].id, 1),
+        PageHeaderResult::Err(_) => panic!("the page is not an error"),
+    }
+    assert_eq!(
+        render_csv(&[newest, oldest], "me", None, true).unwrap(),
+        "id,timestamp,type,from,to,amount_e8s,fee_e8s,memo,balance_e8s\n\
+         1,2022-12-20 14:03:20 UTC,mint,,me,100,0,0,100\n\
+         2,,transfer,me,you,20,10,1,70\n"
+    );
+}
@@ -0,0 +1,144 @@
+//! Utilities for reviewing the content of signed messages.
+
+use crate::lib::{
+    request_id::{bytes_to_sign, hash_value},
+    signing::{decode_content, parse_bundle, Bundle, Ingress},
+    AnyhowResult,
+};
+use anyhow::{anyhow, bail, Context};
+use candid::Principal;
+use clap::Parser;
+use serde_cbor::Value;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/// Utilities for reviewing the content of signed messages.
+#[derive(Parser)]
+pub struct ArgOpts {
+    #[clap(subcommand)]
+    command: ArgCommand,
+}
+
+#[derive(Parser)]
+pub enum ArgCommand {
+    Hash(HashOpts),
+}
+
+/// Computes the representation-independent hash (the request id) of the content of signed
+/// messages, and the bytes that were signed, to compare with other implementations.
+#[derive(Parser)]
+pub struct HashOpts {
+    /// Path to the output of a signing command (use "-" for STDIN), or to a hex-encoded CBOR
+    /// envelope or content map.
+    file_name: PathBuf,
+}
+
+pub fn exec(opts: ArgOpts) -> AnyhowResult {
+    match opts.command {
+        ArgCommand::Hash(opts) => hash(opts),
+    }
+}
+
+fn hash(opts: HashOpts) -> AnyhowResult {
+    let input = crate::read_file(&opts.file_name, "message")?;
+    for (label, content, expected) in contents(input.trim())? {
+        let content = content_map(&content)?;
+        let request_id = hash_value(&content)?;
+        if let Some(expected) = expected {
+            if !expected.eq_ignore_ascii_case(&hex::encode(request_id)) {
+                bail!(
+                    "The request id {} in the bundle does not match the content, whose hash is {}",
+                    expected,
+                    hex::encode(request_id)
+                );
+            }
+        }
+        println!("{} {}", label, describe(&content));
+        println!("  request id:    {}", hex::encode(request_id));
+        println!(
+            "  bytes to sign: {}",
+            hex::encode(bytes_to_sign(&request_id))
+        );
+    }
+    Ok(())
+}
+
+// Returns the kind, the hex-encoded envelope and the recorded request id of every message.
+fn contents(input: &str) -> AnyhowResult<Vec<(&'static str, String, Option<String>)>> {
+    if !input.starts_with('{') && !input.starts_with('[') {
+        return Ok(vec![("content", input.to_string(), None)]);
+    }
+    let ingress = |msg: Ingress| ("ingress", msg.content, msg.request_id);
+    Ok(match parse_bundle(input, false)? {
+        Bundle::Ingress(msg) => vec![ingress(msg)],
+        Bundle::Messages(msgs) => msgs.into_iter().map(ingress).collect(),
+        Bundle::WithRequestStatus(msgs) => msgs
+            .into_iter()
+            .flat_map(|msg| {
+                vec![
+                    ingress(msg.ingress),
+                    ("request status", msg.request_status.content, None),
+                ]
+            })
+            .collect(),
+    })
+}
+
+// Returns the content map of an envelope, or the map itself if it is not an envelope.
+fn content_map(content: &str) -> AnyhowResult<Value> {
+    let mut value: Value = serde_cbor::from_slice(&decode_content(content)?)
+        .context("Invalid cbor data in the content of the message.")?;
+    if let Value::Tag(_, inner) = value {
+        value = *inner;
+    }
+    match value {
+        Value::Map(mut map) => match map.remove(&Value::Text("content".to_string())) {
+            Some(content @ Value::Map(_)) => Ok(content),
+            Some(_) => Err(anyhow!("Invalid cbor content")),
+            None => Ok(Value::Map(map)),
+        },
+        _ => Err(anyhow!("The message content is not a CBOR map")),
+    }
+}
+
+// Describes the request type and target of a content map.
+fn describe(content: &Value) -> String {
+    let map = match content {
+        Value::Map(map) => map,
+        _ => return String::new(),
+    };
+    let field = |name: &str| map.get(&Value::Text(name.to_string()));
+    let mut description = match field("request_type") {
+        Some(Value::Text(request_type)) => request_type.clone(),
+        _ => "unknown request type".to_string(),
+    };
+    if let Some(Value::Bytes(canister_id)) = field("canister_id") {
+        if let Ok(canister_id) = Principal::try_from(canister_id) {
+            description.push_str(&format!(" to {}", canister_id));
+        }
+    }
+    if let Some(Value::Text(method_name)) = field("method_name") {
+        description.push_str(&format!(", method {}", method_name));
+    }
+    format!("({})", description)
+}
+
+#[test]
+fn test_content_map() {
+    // The specification example, as an envelope with the self-describing tag and as a bare
+    // content map.
+    let content = "a463617267474449444c00fd2a6b63616e69737465725f69644800000000000004d26b6d6574686f645f6e616d656568656c6c6f6c726571756573745f747970656463616c6c";
+    let envelope = format!("d9d9f7a167636f6e74656e74{}", content);
+    for input in &[content.to_string(), envelope] {
+        let map = content_map(input).unwrap();
+        assert_eq!(
+            hex::encode(hash_value(&map).unwrap()),
+            "8781291c347db32a9d8c10eb62b710fce5a93be676474c42babc74c51858f94b"
+        );
+        assert_eq!(
+            describe(&map),
+            "(call to ngj2t-fiaaa-aaaaa-aatja, method hello)"
+        );
+    }
+    assert!(content_map("01").is_err());
+}
@@ -0,0 +1,94 @@
+//! Checks the derivation of staking accounts, e.g. one computed by another wallet, before ICP is
+//! sent to them.
+
+use crate::commands::{
+    get_ids,
+    neuron_stake::{convert_name_to_nonce, neuron_name_validator},
+};
+use crate::lib::{
+    nns_types::{account_id::parse_account_id, Subaccount},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, bail};
+use candid::Principal;
+use clap::Parser;
+use ic_nns_constants::GOVERNANCE_CANISTER_ID;
+use ledger_canister::AccountIdentifier;
+
+/// Checks that ICP sent to an account can be claimed as a neuron by a principal, i.e. that the
+/// account is the staking account of the principal for a memo.
+#[derive(Parser)]
+pub struct CheckStakingAccountOpts {
+    /// The account id to check.
+    account_id: String,
+
+    /// The principal which should be able to claim the neuron, the principal of the key by
+    /// default.
+    #[clap(long)]
+    principal: Option<Principal>,
+
+    /// The memo of the staking transfer, i.e. the nonce of the neuron.
+    #[clap(long)]
+    memo: Option<u64>,
+
+    /// The name of the neuron (up to 8 ASCII characters), as given to `neuron-stake`.
+    #[clap(long, validator(neuron_name_validator), conflicts_with("memo"))]
+    name: Option<String>,
+}
+
+pub fn exec(auth: &AuthInfo, opts: CheckStakingAccountOpts) -> AnyhowResult {
+    let account_id = parse_account_id(&opts.account_id)?;
+    let principal = match opts.principal {
+        Some(principal) => principal,
+        None if matches!(auth, AuthInfo::NoAuth) => {
+            bail!("Either --principal or a private key is required")
+        }
+        None => get_ids(auth)?.0,
+    };
+    let memo = match (opts.memo, &opts.name) {
+        (Some(memo), _) => memo,
+        (_, Some(name)) => convert_name_to_nonce(name),
+        _ => return Err(anyhow!("Either a memo or a name should be specified")),
+    };
+    println!("{}", check_staking_account(&account_id, &principal, memo)?);
+    Ok(())
+}
+
+// Compares the account to the staking account derived the way governance does.
+fn check_staking_account(
+    account_id: &AccountIdentifier,
+    principal: &Principal,
+    memo: u64,
+) -> AnyhowResult<String> {
+    let subaccount = Subaccount::from_nonce(principal, memo);
+    let expected = AccountIdentifier::new(GOVERNANCE_CANISTER_ID.get(), Some(subaccount.into()));
+    if expected != *account_id {
+        bail!(
+            "{} is NOT the staking account of principal {} for memo {}, which is {}. ICP sent \
+             to {} cannot be claimed as a neuron by this principal with this memo.",
+            account_id,
+            principal,
+            memo,
+            expected,
+            account_id
+        );
+    }
+    Ok(format!(
+        "{} is the staking account of principal {} for memo {}: funds sent there can only be \
+         claimed by this principal.",
+        account_id, principal, memo
+    ))
+}
+
+#[test]
+fn test_check_staking_account() {
+    let principal =
+        Principal::from_text("fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae")
+            .unwrap();
+    let account_id =
+        parse_account_id("a0ea9002c2bc3d442050f4431f3732c91dbec13eff79f414b15255d60c4a324c")
+            .unwrap();
+    assert!(check_staking_account(&account_id, &principal, 777).is_ok());
+    assert!(check_staking_account(&account_id, &principal, 778).is_err());
+    assert!(check_staking_account(&account_id, &Principal::anonymous(), 777).is_err());
+}
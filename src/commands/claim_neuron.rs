@@ -0,0 +1,56 @@
+//! Claims a neuron staked by a transfer made outside of quill, e.g. from another wallet or an
+//! exchange, to the staking account of a controller.
+
+use crate::commands::{
+    get_ids,
+    neuron_stake::{convert_name_to_nonce, neuron_name_validator, ClaimOrRefreshNeuronFromAccount},
+};
+use crate::lib::{
+    governance_canister_id,
+    nns_types::Memo,
+    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::anyhow;
+use candid::{Encode, Principal};
+use clap::Parser;
+
+/// Signs the claim of the neuron staked by a transfer to the staking account of a controller for
+/// a memo, or the refresh of its stake after a top-up.
+#[derive(Parser)]
+pub struct ClaimNeuronOpts {
+    /// The controller of the neuron, the principal of the key by default. The transfer must have
+    /// been made to the staking account of this principal.
+    #[clap(long)]
+    controller: Option<Principal>,
+
+    /// The memo of the staking transfer, i.e. the nonce of the neuron.
+    #[clap(long)]
+    memo: Option<u64>,
+
+    /// The name of the neuron (up to 8 ASCII characters), as given to `neuron-stake`.
+    #[clap(long, validator(neuron_name_validator), conflicts_with("memo"))]
+    name: Option<String>,
+}
+
+pub fn exec(auth: &AuthInfo, opts: ClaimNeuronOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    let controller = match opts.controller {
+        Some(controller) => controller,
+        None => get_ids(auth)?.0,
+    };
+    let memo = match (opts.memo, &opts.name) {
+        (Some(memo), _) => memo,
+        (_, Some(name)) => convert_name_to_nonce(name),
+        _ => return Err(anyhow!("Either a memo or a name should be specified")),
+    };
+    let args = Encode!(&ClaimOrRefreshNeuronFromAccount {
+        memo: Memo(memo),
+        controller: Some(controller),
+    })?;
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        governance_canister_id(),
+        "claim_or_refresh_neuron_from_account",
+        args,
+    )?])
+}
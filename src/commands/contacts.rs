@@ -0,0 +1,78 @@
+use crate::lib::{
+    contacts::{load_contacts, store_contacts, validate_contact},
+    AnyhowResult,
+};
+use anyhow::bail;
+use clap::Parser;
+
+/// Manages the address book of named recipients, which commands taking a recipient accept as
+/// `@<name>`.
+#[derive(Parser)]
+pub struct ContactsOpts {
+    #[clap(subcommand)]
+    command: ContactsCommand,
+}
+
+#[derive(Parser)]
+pub enum ContactsCommand {
+    /// Adds a contact with an account id or a principal.
+    Add(AddOpts),
+    /// Lists the contacts.
+    List,
+    /// Removes a contact.
+    Remove(RemoveOpts),
+}
+
+#[derive(Parser)]
+pub struct AddOpts {
+    /// The name of the contact (letters, digits, '-' and '_').
+    name: String,
+
+    /// The account id or the principal of the contact.
+    address: String,
+
+    /// Replaces the address of an existing contact.
+    #[clap(long)]
+    replace: bool,
+}
+
+#[derive(Parser)]
+pub struct RemoveOpts {
+    /// The name of the contact.
+    name: String,
+}
+
+pub fn exec(opts: ContactsOpts) -> AnyhowResult {
+    let mut contacts = load_contacts()?;
+    match opts.command {
+        ContactsCommand::Add(opts) => {
+            let address = validate_contact(&opts.name, &opts.address)?;
+            if contacts.contains_key(&opts.name) && !opts.replace {
+                bail!(
+                    "There is already a contact named {}, use --replace to change its address",
+                    opts.name
+                );
+            }
+            contacts.insert(opts.name, address);
+            store_contacts(&contacts)?;
+        }
+        ContactsCommand::List => {
+            let width = contacts.keys().map(|name| name.len() + 1).max();
+            for (name, address) in &contacts {
+                println!(
+                    "{:width$}  {}",
+                    format!("@{}", name),
+                    address,
+                    width = width.unwrap_or_default()
+                );
+            }
+        }
+        ContactsCommand::Remove(opts) => {
+            if contacts.remove(&opts.name).is_none() {
+                bail!("There is no contact named {}", opts.name);
+            }
+            store_contacts(&contacts)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use crate::lib::{
+    config::load_custom_canister,
+    get_candid_type,
+    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, bail, Context};
+use candid::IDLArgs;
+use clap::Parser;
+use std::collections::BTreeMap;
+
+/// Signs a call to a custom canister described in the configuration directory.
+#[derive(Parser)]
+pub struct CustomOpts {
+    /// The name of the canister, i.e. of its TOML file in the `canisters` subdirectory of the
+    /// configuration directory.
+    name: String,
+
+    /// The method to call.
+    method: String,
+
+    /// The arguments of the call in candid text format, e.g. '(record { amount = 10 })'.
+    #[clap(long, conflicts_with("set"))]
+    args: Option<String>,
+
+    /// Replaces {KEY} in the argument template of the method with VALUE (can be repeated). VALUE
+    /// is a candid value, or any text where the placeholder is within a text literal.
+    #[clap(long, multiple_occurrences(true), value_name("KEY=VALUE"))]
+    set: Vec<String>,
+}
+
+pub fn exec(auth: &AuthInfo, opts: CustomOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    let canister = load_custom_canister(&opts.name)?;
+    if !canister.methods.is_empty() && !canister.methods.contains(&opts.method) {
+        bail!(
+            "Method {} of {} is not allowed, it must be one of: {}",
+            opts.method,
+            opts.name,
+            canister.methods.join(", ")
+        );
+    }
+    let args = match opts.args {
+        Some(args) => args,
+        None => {
+            let mut values = BTreeMap::new();
+            for assignment in &opts.set {
+                let (key, value) = assignment
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Expected KEY=VALUE, found {}", assignment))?;
+                values.insert(key.to_string(), value.to_string());
+            }
+            let template = canister
+                .templates
+                .get(&opts.method)
+                .map_or("()", String::as_str);
+            render_template(template, &values).with_context(|| {
+                format!("Cannot fill in the argument template of {}", opts.method)
+            })?
+        }
+    };
+    let (env, func) = get_candid_type(canister.candid()?, &opts.method)
+        .ok_or_else(|| anyhow!("Method {} not found in {}", opts.method, opts.name))?;
+    let args = args
+        .parse::<IDLArgs>()
+        .context("Cannot parse the arguments")?
+        .to_bytes_with_types(&env, &func.args)
+        .context("The arguments do not match the method's candid type")?;
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        canister.canister_id()?,
+        &opts.method,
+        args,
+    )?])
+}
+
+// Replaces the {KEY} placeholders of an argument template with their values. Within a text
+// literal, a value is escaped as text; elsewhere it must be a single candid value, which is
+// rendered again so that it cannot change the structure of the arguments. The arguments are
+// then checked against the method's candid type by the caller.
+fn render_template(template: &str, values: &BTreeMap<String, String>) -> AnyhowResult<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut used = Vec::new();
+    let mut in_text = false;
+    let mut escaped = false;
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if c == '{' && !escaped {
+            if let Some(key) = placeholder(rest) {
+                let value = values.get(key).ok_or_else(|| {
+                    anyhow!("No value for {{{}}}, give it with --set {}=VALUE", key, key)
+                })?;
+                if in_text {
+                    rendered.extend(value.escape_default());
+                } else {
+                    rendered.push_str(&render_value(key, value)?);
+                }
+                used.push(key);
+                rest = &rest[key.len() + 2..];
+                continue;
+            }
+        }
+        if in_text {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_text = false;
+            }
+        } else if c == '"' {
+            in_text = true;
+        }
+        rendered.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if let Some(key) = values.keys().find(|key| !used.contains(&key.as_str())) {
+        bail!("The template has no placeholder {{{}}}", key);
+    }
+    Ok(rendered)
+}
+
+// Returns KEY if the text starts with a {KEY} placeholder.
+fn placeholder(text: &str) -> Option<&str> {
+    let end = text.find('}')?;
+    let key = &text[1..end];
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then(|| key)
+}
+
+// Renders a value given with --set as a single candid value.
+fn render_value(key: &str, value: &str) -> AnyhowResult<String> {
+    let args = format!("({})", value)
+        .parse::<IDLArgs>()
+        .with_context(|| format!("Invalid candid value for {{{}}}: {}", key, value))?;
+    match args.args.as_slice() {
+        [value] => Ok(value.to_string()),
+        _ => bail!(
+            "Expected a single candid value for {{{}}}, found {}",
+            key,
+            value
+        ),
+    }
+}
+
+#[test]
+fn test_render_template() {
+    let values = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let template = r#"(record { to = "{to}"; amount = record { e8s = {e8s} : nat64 } })"#;
+    assert_eq!(
+        render_template(template, &values(&[("to", "ab\"c"), ("e8s", "12300")])).unwrap(),
+        r#"(record { to = "ab\"c"; amount = record { e8s = 12300 : nat64 } })"#
+    );
+    // Values cannot add fields or arguments.
+    assert!(render_template(template, &values(&[("to", "a"), ("e8s", "1 }; x = 2")])).is_err());
+    assert!(render_template(template, &values(&[("to", "a"), ("e8s", "1, 2")])).is_err());
+    // Every placeholder needs a value, and every value a placeholder.
+    assert!(render_template(template, &values(&[("to", "a")])).is_err());
+    assert!(render_template(
+        template,
+        &values(&[("to", "a"), ("e8s", "1"), ("memo", "2")])
+    )
+    .is_err());
+    assert_eq!(
+        render_template("(record {})", &values(&[])).unwrap(),
+        "(record {})"
+    );
+}
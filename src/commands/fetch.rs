@@ -0,0 +1,263 @@
+//! Fetches the state needed to prepare and check messages offline into a single snapshot: the
+//! balances of accounts, the neurons of the key, the open proposals and the fees.
+
+use crate::commands::{get_ids, get_proposal_info::proposal_status_name, list_neurons};
+use crate::lib::{
+    contacts::resolve_account_id,
+    get_account_id, get_agent, get_network, governance_canister_id, is_quiet, ledger_canister_id,
+    nns_types::{account_id::parse_account_id, topic::topic_name},
+    timestamp::now_seconds,
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{bail, Context};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use clap::{ArgGroup, Parser};
+use ic_agent::Agent;
+use ic_nns_common::pb::v1::ProposalId;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Fetches balances, neurons, open proposals and fees into a snapshot, which offline commands
+/// such as `neuron-manage --neuron-file` check messages against.
+#[derive(Parser)]
+#[clap(group(
+    ArgGroup::new("contents")
+        .args(&["all", "balances", "neurons", "proposals", "fees"])
+        .multiple(true)
+        .required(true)
+))]
+pub struct FetchOpts {
+    /// Fetches everything: the balances, the neurons, the open proposals and the fees.
+    #[clap(long)]
+    all: bool,
+
+    /// Fetches the balances of the accounts.
+    #[clap(long)]
+    balances: bool,
+
+    /// Fetches the neurons controlled by the key or of which it is a hot key.
+    #[clap(long)]
+    neurons: bool,
+
+    /// Fetches the open proposals.
+    #[clap(long)]
+    proposals: bool,
+
+    /// Fetches the fees and the minimum stake of the governance canister.
+    #[clap(long)]
+    fees: bool,
+
+    /// The account whose balance is fetched (can be repeated), the account of the key by
+    /// default. Also accepts a principal or a contact as `@<name>`.
+    #[clap(long = "account", multiple_occurrences(true))]
+    accounts: Vec<String>,
+
+    /// Writes the snapshot to this file instead of STDOUT.
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// The state fetched at a given time.
+#[derive(Serialize)]
+pub struct Snapshot {
+    /// When the snapshot was fetched, in seconds since the Unix epoch.
+    pub fetched_at_seconds: u64,
+    pub network: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balances: Option<Vec<Balance>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub neurons: Option<Vec<list_neurons::NeuronReportRow>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proposals: Option<Vec<ProposalSummary>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fees: Option<Fees>,
+}
+
+#[derive(Serialize)]
+pub struct Balance {
+    pub account: String,
+    pub balance_e8s: u64,
+}
+
+#[derive(Serialize)]
+pub struct ProposalSummary {
+    pub id: u64,
+    pub topic: String,
+    pub status: &'static str,
+    pub title: Option<String>,
+    pub deadline_timestamp_seconds: Option<u64>,
+}
+
+#[derive(Serialize, CandidType, Deserialize)]
+pub struct Fees {
+    pub transaction_fee_e8s: u64,
+    pub reject_cost_e8s: u64,
+    pub neuron_minimum_stake_e8s: u64,
+    pub neuron_management_fee_per_proposal_e8s: u64,
+}
+
+#[derive(CandidType)]
+struct AccountBalanceArgs {
+    account: String,
+}
+
+#[derive(CandidType, Deserialize)]
+struct Tokens {
+    e8s: u64,
+}
+
+// Only the fields needed for the snapshot are decoded.
+#[derive(CandidType, Deserialize)]
+struct Proposal {
+    title: Option<String>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ProposalInfo {
+    id: Option<ProposalId>,
+    topic: i32,
+    status: i32,
+    deadline_timestamp_seconds: Option<u64>,
+    proposal: Option<Proposal>,
+}
+
+pub async fn exec(auth: &AuthInfo, opts: FetchOpts) -> AnyhowResult {
+    let agent = get_agent(auth)?;
+    let mut snapshot = Snapshot {
+        fetched_at_seconds: now_seconds()?,
+        network: get_network(),
+        balances: None,
+        neurons: None,
+        proposals: None,
+        fees: None,
+    };
+    if opts.all || opts.balances {
+        let accounts = resolve_accounts(auth, &opts.accounts)?;
+        snapshot.balances = Some(fetch_balances(&agent, accounts).await?);
+    }
+    if opts.all || opts.neurons {
+        if matches!(auth, AuthInfo::NoAuth) {
+            bail!("Fetching the neurons requires the key of their controller or of a hot key");
+        }
+        snapshot.neurons = Some(fetch_neurons(&agent).await?);
+    }
+    if opts.all || opts.proposals {
+        snapshot.proposals = Some(fetch_proposals(&agent).await?);
+    }
+    if opts.all || opts.fees {
+        let reply = query(
+            &agent,
+            governance_canister_id(),
+            "get_network_economics_parameters",
+            Encode!()?,
+        )
+        .await?;
+        snapshot.fees = Some(
+            Decode!(&reply, Fees)
+                .context("Cannot decode get_network_economics_parameters reply")?,
+        );
+    }
+
+    let json = serde_json::to_string_pretty(&snapshot)?;
+    match &opts.output {
+        Some(path) => {
+            std::fs::write(path, json)
+                .with_context(|| format!("Cannot write {}", path.display()))?;
+            if !is_quiet() {
+                eprintln!("Wrote the snapshot to {}", path.display());
+            }
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+/// Resolves the accounts given as account ids, principals or contacts to their hex ids, the
+/// account of the key if none is given.
+pub fn resolve_accounts(auth: &AuthInfo, accounts: &[String]) -> AnyhowResult<Vec<String>> {
+    if accounts.is_empty() {
+        return Ok(vec![get_ids(auth)?.1.to_hex()]);
+    }
+    accounts
+        .iter()
+        .map(|account| resolve_account(account))
+        .collect()
+}
+
+/// Queries the balances of the accounts, given as hex ids.
+pub async fn fetch_balances(agent: &Agent, accounts: Vec<String>) -> AnyhowResult<Vec<Balance>> {
+    let mut balances = Vec::new();
+    for account in accounts {
+        let reply = query(
+            agent,
+            ledger_canister_id(),
+            "account_balance_dfx",
+            Encode!(&AccountBalanceArgs {
+                account: account.clone()
+            })?,
+        )
+        .await?;
+        let balance_e8s = Decode!(&reply, Tokens)?.e8s;
+        balances.push(Balance {
+            account,
+            balance_e8s,
+        });
+    }
+    Ok(balances)
+}
+
+/// Queries the neurons controlled by the identity of the agent or of which it is a hot key.
+pub async fn fetch_neurons(agent: &Agent) -> AnyhowResult<Vec<list_neurons::NeuronReportRow>> {
+    let args = Encode!(&list_neurons::ListNeurons {
+        neuron_ids: Vec::new(),
+        include_neurons_readable_by_caller: true,
+    })?;
+    let reply = query(agent, governance_canister_id(), "list_neurons", args).await?;
+    list_neurons::report_rows(&reply)
+}
+
+/// Queries the open proposals.
+pub async fn fetch_proposals(agent: &Agent) -> AnyhowResult<Vec<ProposalSummary>> {
+    let reply = query(
+        agent,
+        governance_canister_id(),
+        "get_pending_proposals",
+        Encode!()?,
+    )
+    .await?;
+    let proposals =
+        Decode!(&reply, Vec<ProposalInfo>).context("Cannot decode get_pending_proposals reply")?;
+    Ok(proposals
+        .into_iter()
+        .map(|proposal| ProposalSummary {
+            id: proposal.id.map_or(0, |id| id.id),
+            topic: topic_name(proposal.topic),
+            status: proposal_status_name(proposal.status),
+            title: proposal.proposal.and_then(|p| p.title),
+            deadline_timestamp_seconds: proposal.deadline_timestamp_seconds,
+        })
+        .collect())
+}
+
+// Resolves an account given as an account id, a principal or a contact to its hex id.
+fn resolve_account(account: &str) -> AnyhowResult<String> {
+    let account = resolve_account_id(account)?;
+    match Principal::from_text(&account) {
+        Ok(principal) => Ok(get_account_id(principal)?.to_hex()),
+        Err(_) => Ok(parse_account_id(&account)?.to_hex()),
+    }
+}
+
+async fn query(
+    agent: &Agent,
+    canister_id: Principal,
+    method_name: &str,
+    args: Vec<u8>,
+) -> AnyhowResult<Vec<u8>> {
+    agent
+        .query(&canister_id, method_name)
+        .with_arg(args)
+        .call()
+        .await
+        .with_context(|| format!("Cannot query {}", method_name))
+}
@@ -0,0 +1,235 @@
+//! The full data of a neuron, which only its controller and hot keys can query: its followees,
+//! recent ballots, hot keys and maturity.
+
+use crate::commands::list_neurons::known_neuron_label;
+use crate::lib::{
+    governance_canister_id,
+    nns_types::{
+        icpts::{E8s, Icp},
+        neuron_id::parse_neuron_id,
+        topic::topic_name,
+    },
+    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    timestamp::format_timestamp_seconds,
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, Context};
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use clap::Parser;
+use ic_nns_common::pb::v1::NeuronId;
+use ic_nns_governance::pb::v1::GovernanceError;
+use std::collections::HashMap;
+
+/// Signs the query for the full data of a neuron, which `send` prints in a readable form.
+#[derive(Parser)]
+pub struct GetFullNeuronOpts {
+    /// The id of the neuron, controlled by the signing principal or of which it is a hot key.
+    #[clap(parse(try_from_str = parse_neuron_id))]
+    neuron_id: u64,
+}
+
+pub fn exec(auth: &AuthInfo, opts: GetFullNeuronOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        governance_canister_id(),
+        "get_full_neuron",
+        Encode!(&opts.neuron_id)?,
+    )?])
+}
+
+// Only the fields of the report are decoded.
+#[derive(CandidType, Deserialize)]
+struct BallotInfo {
+    vote: i32,
+    proposal_id: Option<NeuronId>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct Followees {
+    followees: Vec<NeuronId>,
+}
+
+#[derive(CandidType, Deserialize)]
+enum DissolveState {
+    DissolveDelaySeconds(u64),
+    WhenDissolvedTimestampSeconds(u64),
+}
+
+#[derive(CandidType, Deserialize)]
+struct KnownNeuronData {
+    name: String,
+}
+
+#[derive(CandidType, Deserialize)]
+struct Neuron {
+    id: Option<NeuronId>,
+    controller: Option<Principal>,
+    hot_keys: Vec<Principal>,
+    cached_neuron_stake_e8s: u64,
+    neuron_fees_e8s: u64,
+    maturity_e8s_equivalent: u64,
+    staked_maturity_e8s_equivalent: Option<u64>,
+    auto_stake_maturity: Option<bool>,
+    aging_since_timestamp_seconds: u64,
+    dissolve_state: Option<DissolveState>,
+    followees: Vec<(i32, Followees)>,
+    recent_ballots: Vec<BallotInfo>,
+    known_neuron_data: Option<KnownNeuronData>,
+}
+
+#[derive(CandidType, Deserialize)]
+enum FullNeuronResult {
+    Ok(Neuron),
+    Err(GovernanceError),
+}
+
+/// Renders the reply of `get_full_neuron` readably, with the names of known neurons.
+pub fn render_full_neuron(reply: &[u8], names: &HashMap<u64, String>) -> AnyhowResult<String> {
+    let neuron =
+        match Decode!(reply, FullNeuronResult).context("Cannot decode get_full_neuron reply")? {
+            FullNeuronResult::Ok(neuron) => neuron,
+            FullNeuronResult::Err(err) => return Err(anyhow!(err.error_message)),
+        };
+    let icp = |e8s: u64| Icp::from(E8s::new(e8s));
+    let mut text = format!(
+        "Neuron {}{}\n",
+        neuron
+            .id
+            .as_ref()
+            .map_or_else(|| "?".to_string(), |id| id.id.to_string()),
+        neuron
+            .known_neuron_data
+            .as_ref()
+            .map_or_else(String::new, |data| format!(" ({})", data.name))
+    );
+    text.push_str(&format!(
+        "Controller:          {}\n",
+        neuron
+            .controller
+            .map_or_else(|| "?".to_string(), |p| p.to_text())
+    ));
+    let hot_keys: Vec<_> = neuron.hot_keys.iter().map(Principal::to_text).collect();
+    text.push_str(&format!(
+        "Hot keys:            {}\n",
+        if hot_keys.is_empty() {
+            "none".to_string()
+        } else {
+            hot_keys.join(", ")
+        }
+    ));
+    text.push_str(&format!(
+        "Stake:               {} ICP\n",
+        icp(neuron
+            .cached_neuron_stake_e8s
+            .saturating_sub(neuron.neuron_fees_e8s))
+    ));
+    text.push_str(&format!(
+        "Maturity:            {} ICP (staked: {} ICP)\n",
+        icp(neuron.maturity_e8s_equivalent),
+        icp(neuron.staked_maturity_e8s_equivalent.unwrap_or(0))
+    ));
+    text.push_str(&format!(
+        "Auto-stake maturity: {}\n",
+        if neuron.auto_stake_maturity.unwrap_or(false) {
+            "yes"
+        } else {
+            "no"
+        }
+    ));
+    text.push_str(&format!(
+        "Dissolve state:      {}\n",
+        match neuron.dissolve_state {
+            Some(DissolveState::DissolveDelaySeconds(seconds)) => format!(
+                "not dissolving, dissolve delay of {} days",
+                seconds / (24 * 60 * 60)
+            ),
+            Some(DissolveState::WhenDissolvedTimestampSeconds(seconds)) => {
+                format!("dissolving until {}", format_timestamp_seconds(seconds))
+            }
+            None => "unspecified".to_string(),
+        }
+    ));
+    // Dissolving neurons do not age.
+    if neuron.aging_since_timestamp_seconds != u64::MAX {
+        text.push_str(&format!(
+            "Aging since:         {}\n",
+            format_timestamp_seconds(neuron.aging_since_timestamp_seconds)
+        ));
+    }
+    text.push_str("Followees:\n");
+    if neuron.followees.iter().all(|(_, f)| f.followees.is_empty()) {
+        text.push_str("  none\n");
+    }
+    for (topic, followees) in &neuron.followees {
+        if followees.followees.is_empty() {
+            continue;
+        }
+        let labels: Vec<_> = followees
+            .followees
+            .iter()
+            .map(|id| known_neuron_label(id.id, names))
+            .collect();
+        text.push_str(&format!(
+            "  {}: {}\n",
+            topic_name(*topic),
+            labels.join(", ")
+        ));
+    }
+    text.push_str("Recent ballots:\n");
+    if neuron.recent_ballots.is_empty() {
+        text.push_str("  none\n");
+    }
+    for ballot in &neuron.recent_ballots {
+        text.push_str(&format!(
+            "  Proposal {}: {}\n",
+            ballot
+                .proposal_id
+                .as_ref()
+                .map_or_else(|| "?".to_string(), |id| id.id.to_string()),
+            match ballot.vote {
+                1 => "Yes",
+                2 => "No",
+                _ => "Unspecified",
+            }
+        ));
+    }
+    Ok(text)
+}
+
+#[test]
+fn test_render_full_neuron() {
+    let neuron = Neuron {
+        id: Some(NeuronId { id: 42 }),
+        controller: Some(Principal::anonymous()),
+        hot_keys: Vec::new(),
+        cached_neuron_stake_e8s: 110_000_000,
+        neuron_fees_e8s: 10_000_000,
+        maturity_e8s_equivalent: 5_000_000,
+        staked_maturity_e8s_equivalent: None,
+        auto_stake_maturity: Some(true),
+        aging_since_timestamp_seconds: u64::MAX,
+        dissolve_state: Some(DissolveState::DissolveDelaySeconds(180 * 24 * 60 * 60)),
+        followees: vec![(
+            4,
+            Followees {
+                followees: vec![NeuronId { id: 27 }],
+            },
+        )],
+        recent_ballots: vec![BallotInfo {
+            vote: 1,
+            proposal_id: Some(NeuronId { id: 100 }),
+        }],
+        known_neuron_data: None,
+    };
+    let reply = Encode!(&FullNeuronResult::Ok(neuron)).unwrap();
+    let names = HashMap::from([(27, "DFINITY Foundation".to_string())]);
+    let text = render_full_neuron(&reply, &names).unwrap();
+    assert!(
+        text.starts_with("Neuron 42\nController:          2vxsx-fae\nHot keys:            none\n")
+    );
+    assert!(text.contains("Stake:               1.00000000 ICP\n"));
+    assert!(text.contains("dissolve delay of 180 days\n"));
+    assert!(!text.contains("Aging since"));
+    assert!(text.contains(&format!("  {}: DFINITY Foundation (27)\n", topic_name(4))));
+    assert!(text.ends_with("Recent ballots:\n  Proposal 100: Yes\n"));
+}
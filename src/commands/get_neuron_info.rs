@@ -1,12 +1,22 @@
 use crate::{
-    commands::send::submit_unsigned_ingress,
-    lib::{governance_canister_id, AnyhowResult},
+    commands::send::{submit_unsigned_ingress, QueryOpts},
+    lib::{
+        get_agent, governance_canister_id,
+        nns_types::{
+            icpts::{E8s, Icp},
+            neuron_id::parse_neuron_id,
+        },
+        AnyhowResult, AuthInfo,
+    },
 };
-use candid::Encode;
+use anyhow::{anyhow, Context};
+use candid::{CandidType, Decode, Deserialize, Encode};
 use clap::Parser;
+use ic_nns_governance::pb::v1::GovernanceError;
 
 #[derive(Parser)]
 pub struct GetNeuronInfoOpts {
+    #[clap(parse(try_from_str = parse_neuron_id))]
     pub ident: u64,
 
     /// Skips confirmation and sends the message directly.
@@ -16,18 +26,117 @@ pub struct GetNeuronInfoOpts {
     /// Will display the query, but not send it.
     #[clap(long)]
     dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
+
+    /// Prints the stake, state, dissolve delay, age and voting power of the neuron in a readable
+    /// form rather than the candid reply. Uses a query call, whose reply is not certified.
+    #[clap(long, conflicts_with("dry-run"))]
+    summary: bool,
+}
+
+// Only the fields of the summary are decoded.
+#[derive(CandidType, Deserialize)]
+struct KnownNeuronData {
+    name: String,
+}
+
+#[derive(CandidType, Deserialize)]
+struct NeuronInfo {
+    stake_e8s: u64,
+    state: i32,
+    dissolve_delay_seconds: u64,
+    age_seconds: u64,
+    voting_power: u64,
+    known_neuron_data: Option<KnownNeuronData>,
+}
+
+#[derive(CandidType, Deserialize)]
+enum NeuronInfoResult {
+    Ok(NeuronInfo),
+    Err(GovernanceError),
 }
 
 // We currently only support a subset of the functionality.
 pub async fn exec(opts: GetNeuronInfoOpts, fetch_root_key: bool) -> AnyhowResult {
     let args = Encode!(&opts.ident)?;
+    if opts.summary {
+        let reply = get_agent(&AuthInfo::NoAuth)?
+            .query(&governance_canister_id(), "get_neuron_info")
+            .with_arg(args)
+            .call()
+            .await?;
+        let info = match Decode!(&reply, NeuronInfoResult)
+            .context("Cannot decode get_neuron_info reply")?
+        {
+            NeuronInfoResult::Ok(info) => info,
+            NeuronInfoResult::Err(err) => return Err(anyhow!(err.error_message)),
+        };
+        print!("{}", render_summary(opts.ident, &info));
+        return Ok(());
+    }
     submit_unsigned_ingress(
         governance_canister_id(),
         "get_neuron_info",
         args,
         opts.yes,
         opts.dry_run,
+        opts.query.fee_free,
         fetch_root_key,
     )
     .await
 }
+
+const ONE_DAY_SECONDS: u64 = 24 * 60 * 60;
+
+fn render_summary(neuron_id: u64, info: &NeuronInfo) -> String {
+    let name = match &info.known_neuron_data {
+        Some(data) => format!(" ({})", data.name),
+        None => String::new(),
+    };
+    let state = match info.state {
+        1 => "Not dissolving",
+        2 => "Dissolving",
+        3 => "Dissolved",
+        _ => "Unspecified",
+    };
+    format!(
+        "Neuron {}{}\n\
+         Stake:          {} ICP\n\
+         State:          {}\n\
+         Dissolve delay: {} days\n\
+         Age:            {} days\n\
+         Voting power:   {:.2}\n",
+        neuron_id,
+        name,
+        Icp::from(E8s::new(info.stake_e8s)),
+        state,
+        info.dissolve_delay_seconds / ONE_DAY_SECONDS,
+        info.age_seconds / ONE_DAY_SECONDS,
+        info.voting_power as f64 / 1e8
+    )
+}
+
+#[test]
+fn test_render_summary() {
+    let info = NeuronInfo {
+        stake_e8s: 1_250_000_000,
+        state: 2,
+        dissolve_delay_seconds: 182 * ONE_DAY_SECONDS + 1,
+        age_seconds: 0,
+        voting_power: 1_523_456_789,
+        known_neuron_data: Some(KnownNeuronData {
+            name: "Example".to_string(),
+        }),
+    };
+    assert_eq!(
+        render_summary(42, &info),
+        "Neuron 42 (Example)\n\
+         Stake:          12.50000000 ICP\n\
+         State:          Dissolving\n\
+         Dissolve delay: 182 days\n\
+         Age:            0 days\n\
+         Voting power:   15.23\n"
+    );
+}
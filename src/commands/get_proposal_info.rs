@@ -1,9 +1,14 @@
 use crate::{
-    commands::send::submit_unsigned_ingress,
-    lib::{governance_canister_id, AnyhowResult},
+    commands::send::{submit_unsigned_ingress, QueryOpts},
+    lib::{
+        get_agent, get_idl_args, governance_canister_id, nns_types::topic::topic_name,
+        timestamp::format_timestamp_seconds, AnyhowResult, ArgPart, AuthInfo,
+    },
 };
-use candid::Encode;
+use anyhow::{bail, Context};
+use candid::{parser::value::IDLValue, CandidType, Decode, Deserialize, Encode};
 use clap::Parser;
+use ic_nns_common::pb::v1::ProposalId;
 
 #[derive(Parser)]
 pub struct GetProposalInfoOpts {
@@ -16,18 +21,193 @@ pub struct GetProposalInfoOpts {
     /// Will display the query, but not send it.
     #[clap(long)]
     dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
+
+    /// Prints the title, status, tally, deadline and action of the proposal in a readable form
+    /// rather than the candid reply. Uses a query call, whose reply is not certified.
+    #[clap(long, conflicts_with("dry-run"))]
+    summary: bool,
+}
+
+// Only the fields of the summary are decoded; the action is rendered from the candid reply.
+#[derive(CandidType, Deserialize)]
+pub struct Tally {
+    yes: u64,
+    no: u64,
+    total: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct Proposal {
+    title: Option<String>,
+    url: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposalInfo {
+    id: Option<ProposalId>,
+    status: i32,
+    topic: i32,
+    latest_tally: Option<Tally>,
+    deadline_timestamp_seconds: Option<u64>,
+    proposal_timestamp_seconds: u64,
+    proposal: Option<Proposal>,
 }
 
 // We currently only support a subset of the functionality.
 pub async fn exec(opts: GetProposalInfoOpts, fetch_root_key: bool) -> AnyhowResult {
     let args = Encode!(&opts.ident)?;
+    if opts.summary {
+        let reply = get_agent(&AuthInfo::NoAuth)?
+            .query(&governance_canister_id(), "get_proposal_info")
+            .with_arg(args)
+            .call()
+            .await?;
+        let info = match Decode!(&reply, Option<ProposalInfo>)
+            .context("Cannot decode get_proposal_info reply")?
+        {
+            Some(info) => info,
+            None => bail!("No proposal {}", opts.ident),
+        };
+        let action = get_idl_args(
+            &reply,
+            governance_canister_id(),
+            "get_proposal_info",
+            ArgPart::Rets,
+        )?
+        .args
+        .iter()
+        .find_map(|value| find_field(value, "action"))
+        .map(|action| action.to_string());
+        print!("{}", render_summary(opts.ident, &info, action.as_deref()));
+        return Ok(());
+    }
     submit_unsigned_ingress(
         governance_canister_id(),
         "get_proposal_info",
         args,
         opts.yes,
         opts.dry_run,
+        opts.query.fee_free,
         fetch_root_key,
     )
     .await
 }
+
+pub fn proposal_status_name(status: i32) -> &'static str {
+    match status {
+        1 => "Open",
+        2 => "Rejected",
+        3 => "Adopted",
+        4 => "Executed",
+        5 => "Failed",
+        _ => "Unspecified",
+    }
+}
+
+// Returns the value of the first field with the name, searching records depth-first.
+fn find_field<'a>(value: &'a IDLValue, name: &str) -> Option<&'a IDLValue> {
+    match value {
+        IDLValue::Record(fields) => fields.iter().find_map(|field| {
+            if field.id.get_id() == candid::idl_hash(name) {
+                Some(&field.val)
+            } else {
+                find_field(&field.val, name)
+            }
+        }),
+        IDLValue::Opt(value) => find_field(value, name),
+        IDLValue::Variant(variant) => find_field(&(variant.0).val, name),
+        _ => None,
+    }
+}
+
+/// Renders a proposal on a single line, for lists.
+pub fn render_line(info: &ProposalInfo) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        info.id.as_ref().map_or(0, |id| id.id),
+        proposal_status_name(info.status),
+        topic_name(info.topic),
+        info.deadline_timestamp_seconds
+            .map_or_else(String::new, format_timestamp_seconds),
+        info.proposal
+            .as_ref()
+            .and_then(|proposal| proposal.title.as_deref())
+            .unwrap_or("")
+    )
+}
+
+fn render_summary(proposal_id: u64, info: &ProposalInfo, action: Option<&str>) -> String {
+    let mut text = format!("Proposal {}\n", proposal_id);
+    if let Some(proposal) = &info.proposal {
+        text.push_str(&format!(
+            "Title:    {}\n",
+            proposal.title.as_deref().unwrap_or("")
+        ));
+        if !proposal.url.is_empty() {
+            text.push_str(&format!("URL:      {}\n", proposal.url));
+        }
+    }
+    text.push_str(&format!(
+        "Topic:    {}\nStatus:   {}\nCreated:  {}\n",
+        topic_name(info.topic),
+        proposal_status_name(info.status),
+        format_timestamp_seconds(info.proposal_timestamp_seconds)
+    ));
+    if let Some(deadline) = info.deadline_timestamp_seconds {
+        text.push_str(&format!(
+            "Deadline: {}\n",
+            format_timestamp_seconds(deadline)
+        ));
+    }
+    if let Some(tally) = &info.latest_tally {
+        let percent = |votes: u64| {
+            if tally.total == 0 {
+                0.0
+            } else {
+                votes as f64 * 100.0 / tally.total as f64
+            }
+        };
+        text.push_str(&format!(
+            "Tally:    {:.2}% yes, {:.2}% no, of {:.2} voting power\n",
+            percent(tally.yes),
+            percent(tally.no),
+            tally.total as f64 / 1e8
+        ));
+    }
+    if let Some(action) = action {
+        text.push_str(&format!("Action:   {}\n", action));
+    }
+    text
+}
+
+#[test]
+fn test_render_summary() {
+    let info = ProposalInfo {
+        id: Some(ProposalId { id: 7 }),
+        status: 1,
+        topic: 4,
+        latest_tally: Some(Tally {
+            yes: 300_000_000,
+            no: 100_000_000,
+            total: 1_000_000_000,
+        }),
+        deadline_timestamp_seconds: None,
+        proposal_timestamp_seconds: 0,
+        proposal: Some(Proposal {
+            title: Some("Adopt the roadmap".to_string()),
+            url: String::new(),
+        }),
+    };
+    let summary = render_summary(7, &info, Some("opt variant { Motion }"));
+    assert!(summary.starts_with("Proposal 7\nTitle:    Adopt the roadmap\nTopic:    Governance\n"));
+    assert!(summary.contains("Status:   Open\n"));
+    assert!(summary.contains("Tally:    30.00% yes, 10.00% no, of 10.00 voting power\n"));
+    assert!(summary.ends_with("Action:   opt variant { Motion }\n"));
+    assert_eq!(
+        render_line(&info),
+        "7\tOpen\tGovernance\t\tAdopt the roadmap"
+    );
+}
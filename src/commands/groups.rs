@@ -0,0 +1,128 @@
+//! The commands grouped by what they apply to, e.g. `quill neuron manage` for `quill
+//! neuron-manage`. The flat commands remain available, and the grouped commands run them.
+
+#[cfg(feature = "online")]
+use super::{
+    account_balance, account_transactions, get_neuron_info, get_proposal_info, list_proposals,
+    voting_history,
+};
+use super::{
+    check_staking_account, claim_neuron, get_full_neuron, list_neurons, neuron_manage,
+    neuron_stake, transfer, Command, Empty,
+};
+use crate::BaseOpts;
+use clap::Parser;
+
+/// Commands for NNS neurons.
+#[derive(Parser)]
+pub struct NeuronOpts {
+    #[clap(subcommand)]
+    command: NeuronCommand,
+}
+
+#[derive(Parser)]
+pub enum NeuronCommand {
+    /// Same as `neuron-stake`.
+    Stake(BaseOpts<neuron_stake::StakeOpts>),
+    /// Same as `check-staking-account`.
+    CheckStakingAccount(BaseOpts<check_staking_account::CheckStakingAccountOpts>),
+    /// Same as `claim-neuron`.
+    Claim(BaseOpts<claim_neuron::ClaimNeuronOpts>),
+    /// Same as `neuron-manage`.
+    Manage(BaseOpts<neuron_manage::ManageOpts>),
+    /// Same as `list-neurons`.
+    List(BaseOpts<list_neurons::ListNeuronsOpts>),
+    /// Same as `get-full-neuron`.
+    Full(BaseOpts<get_full_neuron::GetFullNeuronOpts>),
+    /// Same as `get-neuron-info`.
+    #[cfg(feature = "online")]
+    Info(BaseOpts<get_neuron_info::GetNeuronInfoOpts>),
+    /// Same as `voting-history`.
+    #[cfg(feature = "online")]
+    VotingHistory(BaseOpts<voting_history::VotingHistoryOpts>),
+}
+
+impl From<NeuronOpts> for Command {
+    fn from(opts: NeuronOpts) -> Self {
+        match opts.command {
+            NeuronCommand::Stake(opts) => Command::NeuronStake(opts),
+            NeuronCommand::CheckStakingAccount(opts) => Command::CheckStakingAccount(opts),
+            NeuronCommand::Claim(opts) => Command::ClaimNeuron(opts),
+            NeuronCommand::Manage(opts) => Command::NeuronManage(opts),
+            NeuronCommand::List(opts) => Command::ListNeurons(opts),
+            NeuronCommand::Full(opts) => Command::GetFullNeuron(opts),
+            #[cfg(feature = "online")]
+            NeuronCommand::Info(opts) => Command::GetNeuronInfo(opts),
+            #[cfg(feature = "online")]
+            NeuronCommand::VotingHistory(opts) => Command::VotingHistory(opts),
+        }
+    }
+}
+
+/// Commands for ICP ledger accounts.
+#[derive(Parser)]
+pub struct LedgerOpts {
+    #[clap(subcommand)]
+    command: LedgerCommand,
+}
+
+#[derive(Parser)]
+pub enum LedgerCommand {
+    /// Same as `transfer`.
+    Transfer(BaseOpts<transfer::TransferOpts>),
+    /// Same as `account-balance`.
+    #[cfg(feature = "online")]
+    Balance(BaseOpts<account_balance::AccountBalanceOpts>),
+    /// Same as `account-transactions`.
+    #[cfg(feature = "online")]
+    Transactions(BaseOpts<account_transactions::AccountTransactionsOpts>),
+}
+
+impl From<LedgerOpts> for Command {
+    fn from(opts: LedgerOpts) -> Self {
+        match opts.command {
+            LedgerCommand::Transfer(opts) => Command::Transfer(opts),
+            #[cfg(feature = "online")]
+            LedgerCommand::Balance(opts) => Command::AccountBalance(opts),
+            #[cfg(feature = "online")]
+            LedgerCommand::Transactions(opts) => Command::AccountTransactions(opts),
+        }
+    }
+}
+
+/// Commands for NNS proposals.
+#[derive(Parser)]
+pub struct GovernanceOpts {
+    #[clap(subcommand)]
+    command: GovernanceCommand,
+}
+
+#[derive(Parser)]
+pub enum GovernanceCommand {
+    /// Same as `list-neuron-ids`.
+    NeuronIds(BaseOpts<Empty>),
+    /// Same as `neuron-manage --register-vote`.
+    Vote(BaseOpts<neuron_manage::VoteOpts>),
+    /// Same as `get-proposal-info`.
+    #[cfg(feature = "online")]
+    Proposal(BaseOpts<get_proposal_info::GetProposalInfoOpts>),
+    /// Same as `list-proposals`.
+    #[cfg(feature = "online")]
+    Proposals(BaseOpts<list_proposals::ListProposalsOpts>),
+}
+
+impl From<GovernanceOpts> for Command {
+    fn from(opts: GovernanceOpts) -> Self {
+        match opts.command {
+            GovernanceCommand::NeuronIds(opts) => Command::ListNeuronIds(opts),
+            GovernanceCommand::Vote(opts) => Command::NeuronManage(BaseOpts {
+                command_opts: opts.command_opts.into(),
+                global_opts: opts.global_opts,
+            }),
+            #[cfg(feature = "online")]
+            GovernanceCommand::Proposal(opts) => Command::GetProposalInfo(opts),
+            #[cfg(feature = "online")]
+            GovernanceCommand::Proposals(opts) => Command::ListProposals(opts),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use crate::lib::{
+    governance_canister_id,
+    signing::{sign_query, Ingress},
+    AnyhowResult, AuthInfo,
+};
+use candid::Encode;
+
+/// Signs the query for the ids of the neurons readable by the signing principal, i.e. those it
+/// controls or is a hot key of, without the neurons themselves.
+pub fn exec(auth: &AuthInfo) -> AnyhowResult<Vec<Ingress>> {
+    Ok(vec![sign_query(
+        auth,
+        governance_canister_id(),
+        "get_neuron_ids",
+        Encode!()?,
+    )?])
+}
@@ -1,10 +1,15 @@
 use crate::lib::{
-    governance_canister_id,
-    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    get_agent, governance_canister_id,
+    nns_types::{neuron_id::parse_neuron_id, topic::topic_name},
+    signing::{sign_query, Ingress},
     AnyhowResult, AuthInfo,
 };
-use candid::{CandidType, Encode};
-use clap::Parser;
+use anyhow::Context;
+use candid::{CandidType, Decode, Deserialize, Encode, Principal};
+use clap::{ArgEnum, Parser};
+use ic_nns_common::pb::v1::NeuronId;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
 
 #[derive(CandidType)]
 pub struct ListNeurons {
@@ -12,27 +17,334 @@ pub struct ListNeurons {
     pub include_neurons_readable_by_caller: bool,
 }
 
-/// Signs a neuron configuration change.
+/// Signs the query for the neurons readable by the signing principal, i.e. those it controls or
+/// is a hot key of.
 #[derive(Parser)]
 pub struct ListNeuronsOpts {
     /// The optional ids of the specific neuron to query. Note that these ids
     /// may only be those that occur in the usual output from `list-neurons`,
     /// i.e., they should be ids of the user's own neurons. The purpose of
     /// this option is to narrow the query, and not to allow querying of
-    /// arbtirary neuron ids.
+    /// arbitrary neuron ids.
+    #[clap(parse(try_from_str = parse_neuron_id))]
     neuron_id: Vec<u64>,
 }
 
 // We currently only support a subset of the functionality.
-pub fn exec(auth: &AuthInfo, opts: ListNeuronsOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+pub fn exec(auth: &AuthInfo, opts: ListNeuronsOpts) -> AnyhowResult<Vec<Ingress>> {
     let args = Encode!(&ListNeurons {
         neuron_ids: opts.neuron_id.clone(),
         include_neurons_readable_by_caller: opts.neuron_id.is_empty(),
     })?;
-    Ok(vec![sign_ingress_with_request_status_query(
+    Ok(vec![sign_query(
         auth,
         governance_canister_id(),
         "list_neurons",
         args,
     )?])
 }
+
+/// The formats of the neuron report printed by `send --neuron-report`.
+#[derive(Debug, Clone, Copy, ArgEnum)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+// Only the fields needed for the report are decoded; the others are skipped.
+#[derive(CandidType, Deserialize)]
+struct NeuronInfo {
+    state: i32,
+    stake_e8s: u64,
+    dissolve_delay_seconds: u64,
+    age_seconds: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+struct Neuron {
+    id: Option<NeuronId>,
+    maturity_e8s_equivalent: u64,
+    hot_keys: Vec<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ListNeuronsResponse {
+    neuron_infos: Vec<(u64, NeuronInfo)>,
+    full_neurons: Vec<Neuron>,
+}
+
+/// A normalized row of the neuron report.
+#[derive(Serialize)]
+pub struct NeuronReportRow {
+    pub id: u64,
+    pub stake_e8s: u64,
+    pub maturity_e8s: u64,
+    pub state: &'static str,
+    pub dissolve_delay_seconds: u64,
+    pub age_seconds: u64,
+    pub hot_keys: Vec<String>,
+}
+
+// The JSON neuron report, in the same shape as the neurons of a `fetch` snapshot so that
+// `neuron-manage --neuron-file` can tell how old the stakes are.
+#[derive(Serialize)]
+struct NeuronReport {
+    fetched_at_seconds: u64,
+    neurons: Vec<NeuronReportRow>,
+}
+
+/// Renders the reply of `list_neurons` as a normalized report. The JSON report records when the
+/// reply was fetched, in seconds since the Unix epoch.
+pub fn render_report(
+    reply: &[u8],
+    format: ReportFormat,
+    fetched_at_seconds: u64,
+) -> AnyhowResult<String> {
+    let rows = report_rows(reply)?;
+    match format {
+        ReportFormat::Json => Ok(serde_json::to_string_pretty(&NeuronReport {
+            fetched_at_seconds,
+            neurons: rows,
+        })?),
+        ReportFormat::Csv => {
+            let mut csv =
+                "id,stake_e8s,maturity_e8s,state,dissolve_delay_seconds,age_seconds,hot_keys\n"
+                    .to_string();
+            for row in rows {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{}\n",
+                    row.id,
+                    row.stake_e8s,
+                    row.maturity_e8s,
+                    row.state,
+                    row.dissolve_delay_seconds,
+                    row.age_seconds,
+                    row.hot_keys.join(" ")
+                ));
+            }
+            Ok(csv)
+        }
+    }
+}
+
+/// Returns the rows of the normalized report of the reply of `list_neurons`.
+pub fn report_rows(reply: &[u8]) -> AnyhowResult<Vec<NeuronReportRow>> {
+    let response =
+        Decode!(reply, ListNeuronsResponse).context("Cannot decode list_neurons reply")?;
+    Ok(response
+        .neuron_infos
+        .into_iter()
+        .map(|(id, info)| {
+            let full = response
+                .full_neurons
+                .iter()
+                .find(|neuron| neuron.id.as_ref().map(|n| n.id) == Some(id));
+            NeuronReportRow {
+                id,
+                stake_e8s: info.stake_e8s,
+                maturity_e8s: full.map_or(0, |n| n.maturity_e8s_equivalent),
+                state: match info.state {
+                    1 => "NotDissolving",
+                    2 => "Dissolving",
+                    3 => "Dissolved",
+                    _ => "Unspecified",
+                },
+                dissolve_delay_seconds: info.dissolve_delay_seconds,
+                age_seconds: info.age_seconds,
+                hot_keys: full.map_or_else(Vec::new, |n| {
+                    n.hot_keys.iter().map(Principal::to_text).collect()
+                }),
+            }
+        })
+        .collect())
+}
+
+#[derive(CandidType, Deserialize)]
+struct Followees {
+    followees: Vec<NeuronId>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct NeuronFollowing {
+    id: Option<NeuronId>,
+    followees: Vec<(i32, Followees)>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ListNeuronsFollowing {
+    full_neurons: Vec<NeuronFollowing>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct KnownNeuronData {
+    name: String,
+}
+
+#[derive(CandidType, Deserialize)]
+struct KnownNeuron {
+    id: Option<NeuronId>,
+    known_neuron_data: Option<KnownNeuronData>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ListKnownNeuronsResponse {
+    known_neurons: Vec<KnownNeuron>,
+}
+
+/// Returns the names of the known neurons registered with governance, by neuron id.
+pub async fn fetch_known_neuron_names() -> AnyhowResult<HashMap<u64, String>> {
+    let reply = get_agent(&AuthInfo::NoAuth)?
+        .query(&governance_canister_id(), "list_known_neurons")
+        .with_arg(Encode!()?)
+        .call()
+        .await?;
+    let response = Decode!(&reply, ListKnownNeuronsResponse)
+        .context("Cannot decode list_known_neurons reply")?;
+    Ok(response
+        .known_neurons
+        .into_iter()
+        .filter_map(|neuron| Some((neuron.id?.id, neuron.known_neuron_data?.name)))
+        .collect())
+}
+
+/// Returns the id of a neuron, preceded by its name if it is a known neuron.
+pub fn known_neuron_label(id: u64, names: &HashMap<u64, String>) -> String {
+    match names.get(&id) {
+        Some(name) => format!("{} ({})", name, id),
+        None => id.to_string(),
+    }
+}
+
+/// Renders the followees of the neurons in a `list_neurons` reply as a table with a row per
+/// topic and a column per neuron. Followees are shown by name if they are known neurons.
+pub fn render_following(reply: &[u8], names: &HashMap<u64, String>) -> AnyhowResult<String> {
+    let response =
+        Decode!(reply, ListNeuronsFollowing).context("Cannot decode list_neurons reply")?;
+    let topics: BTreeSet<i32> = response
+        .full_neurons
+        .iter()
+        .flat_map(|neuron| neuron.followees.iter().map(|(topic, _)| *topic))
+        .collect();
+
+    let mut header = vec!["Topic".to_string()];
+    header.extend(response.full_neurons.iter().map(|neuron| {
+        neuron
+            .id
+            .as_ref()
+            .map_or_else(|| "?".to_string(), |id| id.id.to_string())
+    }));
+    let mut rows = vec![header];
+    for topic in topics {
+        let mut row = vec![topic_name(topic)];
+        row.extend(response.full_neurons.iter().map(|neuron| {
+            match neuron.followees.iter().find(|(t, _)| *t == topic) {
+                Some((_, f)) if !f.followees.is_empty() => f
+                    .followees
+                    .iter()
+                    .map(|id| known_neuron_label(id.id, names))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                _ => "-".to_string(),
+            }
+        }));
+        rows.push(row);
+    }
+
+    let mut table = format_table(&rows);
+    if rows.len() == 1 {
+        table.push_str("No neuron follows other neurons.\n");
+    }
+    Ok(table)
+}
+
+/// Formats rows as a table with aligned columns, whose first row is the header.
+pub fn format_table(rows: &[Vec<String>]) -> String {
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut table = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let cells: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join(" | ").trim_end());
+        table.push('\n');
+        if i == 0 {
+            let rule: Vec<_> = widths.iter().map(|width| "-".repeat(*width)).collect();
+            table.push_str(&rule.join("-+-"));
+            table.push('\n');
+        }
+    }
+    table
+}
+
+#[test]
+fn test_render_following() {
+    let reply = Encode!(&ListNeuronsFollowing {
+        full_neurons: vec![
+            NeuronFollowing {
+                id: Some(NeuronId { id: 7 }),
+                followees: vec![
+                    (
+                        0,
+                        Followees {
+                            followees: vec![NeuronId { id: 27 }, NeuronId { id: 28 }]
+                        }
+                    ),
+                    (4, Followees { followees: vec![] }),
+                ],
+            },
+            NeuronFollowing {
+                id: Some(NeuronId { id: 8 }),
+                followees: vec![(
+                    4,
+                    Followees {
+                        followees: vec![NeuronId { id: 27 }]
+                    }
+                )],
+            },
+        ],
+    })
+    .unwrap();
+    let names = vec![(27, "DFINITY".to_string())].into_iter().collect();
+    assert_eq!(
+        render_following(&reply, &names).unwrap(),
+        "Topic       | 7                | 8\n\
+         ------------+------------------+-------------\n\
+         Unspecified | DFINITY (27), 28 | -\n\
+         Governance  | -                | DFINITY (27)\n"
+    );
+}
+
+#[test]
+fn test_render_report() {
+    let reply = Encode!(&ListNeuronsResponse {
+        neuron_infos: vec![(
+            7,
+            NeuronInfo {
+                state: 2,
+                stake_e8s: 100_000_000,
+                dissolve_delay_seconds: 3600,
+                age_seconds: 0,
+            }
+        )],
+        full_neurons: vec![Neuron {
+            id: Some(NeuronId { id: 7 }),
+            maturity_e8s_equivalent: 42,
+            hot_keys: vec![Principal::anonymous()],
+        }],
+    })
+    .unwrap();
+    assert_eq!(
+        render_report(&reply, ReportFormat::Csv, 0).unwrap(),
+        "id,stake_e8s,maturity_e8s,state,dissolve_delay_seconds,age_seconds,hot_keys\n\
+         7,100000000,42,Dissolving,3600,0,2vxsx-fae\n"
+    );
+}
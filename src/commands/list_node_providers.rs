@@ -0,0 +1,33 @@
+use crate::{
+    commands::send::{submit_unsigned_ingress, QueryOpts},
+    lib::{governance_canister_id, AnyhowResult},
+};
+use candid::Encode;
+use clap::Parser;
+
+#[derive(Parser)]
+pub struct ListNodeProvidersOpts {
+    /// Skips confirmation and sends the message directly.
+    #[clap(long)]
+    yes: bool,
+
+    /// Will display the query, but not send it.
+    #[clap(long)]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub async fn exec(opts: ListNodeProvidersOpts, fetch_root_key: bool) -> AnyhowResult {
+    submit_unsigned_ingress(
+        governance_canister_id(),
+        "list_node_providers",
+        Encode!()?,
+        opts.yes,
+        opts.dry_run,
+        opts.query.fee_free,
+        fetch_root_key,
+    )
+    .await
+}
@@ -1,16 +1,30 @@
 use crate::{
-    commands::send::submit_unsigned_ingress,
-    lib::{governance_canister_id, AnyhowResult},
+    commands::{
+        get_proposal_info::{render_line, ProposalInfo},
+        send::{submit_unsigned_ingress, QueryOpts},
+    },
+    lib::{get_agent, governance_canister_id, AnyhowResult, AuthInfo},
 };
-use candid::Encode;
+use anyhow::Context;
+use candid::{CandidType, Decode, Deserialize, Encode};
 use clap::Parser;
+use ic_nns_common::pb::v1::ProposalId;
 use ic_nns_governance::pb::v1::ListProposalInfo;
 
 #[derive(Parser)]
 pub struct ListProposalsOpts {
+    /// Only displays `<LIMIT>` proposals.
     #[clap(long)]
     pub limit: Option<u32>,
 
+    /// Only displays the proposals older than this one, to page through the proposals.
+    #[clap(long)]
+    before: Option<u64>,
+
+    /// Only displays the open proposals, which can still be voted on.
+    #[clap(long, conflicts_with_all(&["limit", "before"]))]
+    pending: bool,
+
     /// Skips confirmation and sends the message directly.
     #[clap(long)]
     yes: bool,
@@ -18,23 +32,61 @@ pub struct ListProposalsOpts {
     /// Will display the query, but not send it.
     #[clap(long)]
     dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
+
+    /// Prints one line per proposal with its id, status, topic, deadline and title rather than
+    /// the candid reply. Uses a query call, whose reply is not certified.
+    #[clap(long, conflicts_with("dry-run"))]
+    summary: bool,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ListProposalInfoResponse {
+    proposal_info: Vec<ProposalInfo>,
 }
 
 // We currently only support a subset of the functionality.
 pub async fn exec(opts: ListProposalsOpts, fetch_root_key: bool) -> AnyhowResult {
-    let args = Encode!(&ListProposalInfo {
-        limit: opts.limit.unwrap_or(100),
-        before_proposal: None,
-        exclude_topic: vec![2 /*TOPIC_EXCHANGE_RATE*/, 9 /*TOPIC_KYC*/],
-        include_reward_status: Vec::new(),
-        include_status: Vec::new(),
-    })?;
+    let (method_name, args) = if opts.pending {
+        ("get_pending_proposals", Encode!()?)
+    } else {
+        (
+            "list_proposals",
+            Encode!(&ListProposalInfo {
+                limit: opts.limit.unwrap_or(100),
+                before_proposal: opts.before.map(|id| ProposalId { id }),
+                exclude_topic: vec![2 /*TOPIC_EXCHANGE_RATE*/, 9 /*TOPIC_KYC*/],
+                include_reward_status: Vec::new(),
+                include_status: Vec::new(),
+            })?,
+        )
+    };
+    if opts.summary {
+        let reply = get_agent(&AuthInfo::NoAuth)?
+            .query(&governance_canister_id(), method_name)
+            .with_arg(args)
+            .call()
+            .await?;
+        let proposals = if opts.pending {
+            Decode!(&reply, Vec<ProposalInfo>)
+        } else {
+            Decode!(&reply, ListProposalInfoResponse).map(|response| response.proposal_info)
+        }
+        .with_context(|| format!("Cannot decode {} reply", method_name))?;
+        for proposal in &proposals {
+            println!("{}", render_line(proposal));
+        }
+        return Ok(());
+    }
     submit_unsigned_ingress(
         governance_canister_id(),
-        "list_proposals",
+        method_name,
         args,
         opts.yes,
         opts.dry_run,
+        opts.query.fee_free,
         fetch_root_key,
     )
     .await
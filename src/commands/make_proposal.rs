@@ -0,0 +1,182 @@
+use crate::lib::{
+    get_candid_type, get_local_candid, governance_canister_id,
+    nns_types::neuron_id::parse_neuron_id,
+    proposal::{check_motion_text, check_proposal_text, print_preview, read_summary},
+    read_from_file,
+    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, Context};
+use candid::{
+    parser::value::{IDLField, IDLValue},
+    Encode, IDLArgs,
+};
+use clap::Parser;
+use ic_nns_common::pb::v1::NeuronId;
+use ic_nns_governance::pb::v1::{
+    manage_neuron::Command, proposal::Action, ManageNeuron, Motion, Proposal,
+};
+use std::path::PathBuf;
+
+/// Signs a proposal made by a neuron: either a motion, which has no effect beyond recording the
+/// opinion of the community, or any proposal written in candid.
+#[derive(Parser)]
+pub struct MakeProposalOpts {
+    /// The id of the neuron making the proposal.
+    #[clap(parse(try_from_str = parse_neuron_id))]
+    proposer_neuron_id: u64,
+
+    /// The title of the proposal.
+    #[clap(long, required_unless_present("proposal-file"))]
+    title: Option<String>,
+
+    /// The summary of the proposal.
+    #[clap(long, required_unless_present_any(&["proposal-file", "summary-file"]))]
+    summary: Option<String>,
+
+    /// Path to a file with the summary of the proposal, e.g. a Markdown document.
+    #[clap(long, conflicts_with("summary"))]
+    summary_file: Option<PathBuf>,
+
+    /// A URL with more information on the proposal.
+    #[clap(long, default_value = "")]
+    url: String,
+
+    /// The text of the motion.
+    #[clap(long, required_unless_present("proposal-file"))]
+    motion_text: Option<String>,
+
+    /// Path to a file with the proposal in candid text format, a value of the `Proposal` type of
+    /// the governance canister, e.g. `record { title = opt "..."; summary = "..."; url = "";
+    /// action = opt variant { ... } }`.
+    #[clap(
+        long,
+        conflicts_with_all(&["title", "summary", "summary-file", "url", "motion-text"])
+    )]
+    proposal_file: Option<PathBuf>,
+}
+
+pub fn exec(auth: &AuthInfo, opts: MakeProposalOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    let args = match &opts.proposal_file {
+        Some(path) => encode_proposal_file(opts.proposer_neuron_id, &read_from_file(path)?)?,
+        None => {
+            let title = opts.title.unwrap_or_default();
+            let summary = read_summary(opts.summary, opts.summary_file.as_deref())?;
+            let motion_text = opts.motion_text.unwrap_or_default();
+            check_proposal_text(&title, &summary, &opts.url)?;
+            check_motion_text(&motion_text)?;
+            print_preview(&title, &summary, &opts.url);
+            Encode!(&ManageNeuron {
+                id: Some(NeuronId {
+                    id: opts.proposer_neuron_id
+                }),
+                command: Some(Command::MakeProposal(Box::new(Proposal {
+                    title: Some(title),
+                    summary,
+                    url: opts.url,
+                    action: Some(Action::Motion(Motion { motion_text })),
+                }))),
+                neuron_id_or_subaccount: None,
+            })?
+        }
+    };
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        governance_canister_id(),
+        "manage_neuron",
+        args,
+    )?])
+}
+
+// Encodes the `manage_neuron` argument making the proposal with the types of the embedded
+// governance candid, so that any action it knows of can be proposed.
+fn encode_proposal_file(proposer_neuron_id: u64, proposal: &str) -> AnyhowResult<Vec<u8>> {
+    let (env, func) = get_candid_type(get_local_candid(governance_canister_id())?, "manage_neuron")
+        .ok_or_else(|| anyhow!("manage_neuron not found in the governance candid"))?;
+    // The proposal is on lines of its own, so that a trailing comment cannot swallow the rest.
+    let args = format!(
+        "(record {{ id = opt record {{ id = {} : nat64 }}; command = opt variant {{ MakeProposal \
+         =\n{}\n}}; neuron_id_or_subaccount = null }})",
+        proposer_neuron_id, proposal
+    );
+    let args = args
+        .parse::<IDLArgs>()
+        .context("Cannot parse the proposal")?;
+    let bytes = args
+        .to_bytes_with_types(&env, &func.args)
+        .context("The proposal does not match the Proposal type of the governance canister")?;
+    if let Some(proposal) = proposal_fields(&args) {
+        let text = |name| match field(proposal, name) {
+            Some(IDLValue::Text(text)) => text.as_str(),
+            Some(IDLValue::Opt(text)) => match text.as_ref() {
+                IDLValue::Text(text) => text.as_str(),
+                _ => "",
+            },
+            _ => "",
+        };
+        check_proposal_text(text("title"), text("summary"), text("url"))?;
+        print_preview(text("title"), text("summary"), text("url"));
+    }
+    Ok(bytes)
+}
+
+// Returns the fields of the proposal in the `manage_neuron` argument.
+fn proposal_fields(args: &IDLArgs) -> Option<&[IDLField]> {
+    let fields = match args.args.first()? {
+        IDLValue::Record(fields) => fields,
+        _ => return None,
+    };
+    let variant = match field(fields, "command")? {
+        IDLValue::Opt(command) => match command.as_ref() {
+            IDLValue::Variant(variant) => variant,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match &(variant.0).val {
+        IDLValue::Record(proposal) => Some(proposal),
+        _ => None,
+    }
+}
+
+fn field<'a>(fields: &'a [IDLField], name: &str) -> Option<&'a IDLValue> {
+    fields
+        .iter()
+        .find(|field| field.id.get_id() == candid::idl_hash(name))
+        .map(|field| &field.val)
+}
+
+#[test]
+fn test_encode_proposal_file() {
+    use candid::Decode;
+    let args = encode_proposal_file(
+        42,
+        r#"record {
+            title = opt "Adopt the roadmap";
+            summary = "";
+            url = "";
+            action = opt variant { Motion = record { motion_text = "Adopt it" } };
+        } // Motions have no effect."#,
+    )
+    .unwrap();
+    let manage_neuron = Decode!(&args, ManageNeuron).unwrap();
+    assert_eq!(manage_neuron.id, Some(NeuronId { id: 42 }));
+    match manage_neuron.command {
+        Some(Command::MakeProposal(proposal)) => {
+            assert_eq!(proposal.title.as_deref(), Some("Adopt the roadmap"));
+            assert_eq!(
+                proposal.action,
+                Some(Action::Motion(Motion {
+                    motion_text: "Adopt it".to_string()
+                }))
+            );
+        }
+        _ => panic!("Expected a MakeProposal command"),
+    }
+    assert!(encode_proposal_file(42, "record { title = 7 }").is_err());
+    assert!(encode_proposal_file(
+        42,
+        r#"record { title = opt "Hi"; summary = ""; url = ""; action = null }"#
+    )
+    .is_err());
+}
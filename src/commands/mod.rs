@@ -1,27 +1,72 @@
 //! This module implements the command-line API.
 
-use crate::{get_auth, lib::AnyhowResult, BaseOpts};
-use anyhow::Context;
+use crate::{
+    get_auth,
+    lib::{self, is_quiet, AnyhowResult},
+    BaseOpts, GlobalOpts,
+};
+use anyhow::{bail, Context};
 use clap::{Args, Parser};
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "online")]
 use tokio::runtime::Runtime;
 
+#[cfg(feature = "online")]
 mod account_balance;
+#[cfg(feature = "online")]
+mod account_transactions;
+mod arg;
+mod check_staking_account;
+mod claim_neuron;
 mod claim_neurons;
+mod contacts;
+mod custom;
+#[cfg(feature = "online")]
+mod fetch;
 mod generate;
+mod get_full_neuron;
+#[cfg(feature = "online")]
 mod get_neuron_info;
+#[cfg(feature = "online")]
 mod get_proposal_info;
+mod groups;
+mod list_neuron_ids;
 mod list_neurons;
+#[cfg(feature = "online")]
+mod list_node_providers;
+#[cfg(feature = "online")]
 mod list_proposals;
+mod make_proposal;
 mod neuron_manage;
 mod neuron_stake;
+#[cfg(feature = "online")]
+mod node_provider_rewards;
+#[cfg(feature = "online")]
+mod preconditions;
 mod public;
 mod qrcode;
+mod register_known_neuron;
 mod replace_node_provide_id;
+mod request_payment;
+#[cfg(feature = "online")]
 mod request_status;
+mod self_test;
+#[cfg(feature = "online")]
 mod send;
+mod session;
+mod sign_policy;
+mod sns;
+mod summary;
 mod transfer;
+#[cfg(feature = "tui")]
+mod tui;
 mod update_node_provider;
+#[cfg(feature = "online")]
+mod verify_receipt;
+mod version;
+#[cfg(feature = "online")]
+mod voting_history;
 
 pub use public::get_ids;
 
@@ -29,74 +74,155 @@ pub use public::get_ids;
 pub enum Command {
     /// Prints the principal id and the account id.
     PublicIds(BaseOpts<public::PublicOpts>),
+    #[cfg(feature = "online")]
     Send(BaseOpts<send::SendOpts>),
     Transfer(BaseOpts<transfer::TransferOpts>),
     /// Claim seed neurons from the Genesis Token Canister.
     ClaimNeurons(BaseOpts<Empty>),
     NeuronStake(BaseOpts<neuron_stake::StakeOpts>),
+    CheckStakingAccount(BaseOpts<check_staking_account::CheckStakingAccountOpts>),
+    ClaimNeuron(BaseOpts<claim_neuron::ClaimNeuronOpts>),
     NeuronManage(BaseOpts<neuron_manage::ManageOpts>),
-    /// Signs the query for all neurons belonging to the signing principal.
+    /// Signs the query for all neurons controlled by the signing principal or of which it is a
+    /// hot key.
     ListNeurons(BaseOpts<list_neurons::ListNeuronsOpts>),
+    GetFullNeuron(BaseOpts<get_full_neuron::GetFullNeuronOpts>),
+    /// Signs the query for the ids of the neurons controlled by the signing principal or of which
+    /// it is a hot key.
+    ListNeuronIds(BaseOpts<Empty>),
+    #[cfg(feature = "online")]
     ListProposals(BaseOpts<list_proposals::ListProposalsOpts>),
+    #[cfg(feature = "online")]
     GetProposalInfo(BaseOpts<get_proposal_info::GetProposalInfoOpts>),
+    #[cfg(feature = "online")]
     GetNeuronInfo(BaseOpts<get_neuron_info::GetNeuronInfoOpts>),
+    #[cfg(feature = "online")]
+    VotingHistory(BaseOpts<voting_history::VotingHistoryOpts>),
     /// Queries a ledger account balance.
+    #[cfg(feature = "online")]
     AccountBalance(BaseOpts<account_balance::AccountBalanceOpts>),
+    /// Queries the transactions of a ledger account from the index canister.
+    #[cfg(feature = "online")]
+    AccountTransactions(BaseOpts<account_transactions::AccountTransactionsOpts>),
     /// Update node provider details
     UpdateNodeProvider(BaseOpts<update_node_provider::UpdateNodeProviderOpts>),
     ReplaceNodeProviderId(BaseOpts<replace_node_provide_id::ReplaceNodeProviderIdOpts>),
+    RegisterKnownNeuron(BaseOpts<register_known_neuron::RegisterKnownNeuronOpts>),
+    MakeProposal(BaseOpts<make_proposal::MakeProposalOpts>),
+    /// Queries the rewards distributed to node providers.
+    #[cfg(feature = "online")]
+    NodeProviderRewards(BaseOpts<node_provider_rewards::NodeProviderRewardsOpts>),
+    /// Queries the list of node providers.
+    #[cfg(feature = "online")]
+    ListNodeProviders(BaseOpts<list_node_providers::ListNodeProvidersOpts>),
+    /// Signs a call to a custom canister described in the configuration directory.
+    Custom(BaseOpts<custom::CustomOpts>),
+    RequestPayment(BaseOpts<request_payment::RequestPaymentOpts>),
+    SignPolicy(BaseOpts<sign_policy::SignPolicyOpts>),
+    Session(BaseOpts<session::SessionOpts>),
+    #[cfg(feature = "online")]
+    Fetch(BaseOpts<fetch::FetchOpts>),
+    #[cfg(feature = "online")]
+    VerifyReceipt(BaseOpts<verify_receipt::VerifyReceiptOpts>),
+    #[cfg(feature = "tui")]
+    Tui(BaseOpts<tui::TuiOpts>),
+    Sns(sns::SnsOpts),
     /// Generate a mnemonic seed phrase and generate or recover PEM.
     Generate(generate::GenerateOpts),
     /// Print QR Scanner dapp QR code: scan to start dapp to submit QR results.
     ScannerQRCode,
     /// Print QR code for data e.g. principal id.
     QRCode(BaseOpts<qrcode::QRCodeOpts>),
+    /// Verifies the embedded candid interfaces and signing routines against known test vectors.
+    SelfTest,
+    Version(version::VersionOpts),
+    Arg(arg::ArgOpts),
+    Contacts(contacts::ContactsOpts),
+    Neuron(groups::NeuronOpts),
+    Ledger(groups::LedgerOpts),
+    Governance(groups::GovernanceOpts),
 }
 
 #[derive(Args)]
 pub struct Empty;
 
 pub fn dispatch(cmd: Command) -> AnyhowResult {
+    #[cfg(feature = "online")]
     let runtime = Runtime::new().expect("Unable to create a runtime");
     match cmd {
         Command::PublicIds(opts) => public::exec(&get_auth(opts.global_opts)?, opts.command_opts)?,
         Command::Transfer(opts) => {
-            let qr = opts.global_opts.qr;
+            let output = Output::new(&opts.global_opts);
             let out = transfer::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
-            print_vec(qr, &out)?;
+            output.print_vec(&out)?;
         }
         Command::NeuronStake(opts) => {
-            let qr = opts.global_opts.qr;
+            let output = Output::new(&opts.global_opts);
             let out = neuron_stake::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
-            print_vec(qr, &out)?;
+            output.print_vec(&out)?;
+        }
+        Command::CheckStakingAccount(opts) => {
+            check_staking_account::exec(&get_auth(opts.global_opts)?, opts.command_opts)?
+        }
+        Command::ClaimNeuron(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = claim_neuron::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
         }
         Command::NeuronManage(opts) => {
-            let qr = opts.global_opts.qr;
+            let output = Output::new(&opts.global_opts);
             let out = neuron_manage::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
-            print_vec(qr, &out)?;
+            output.print_vec(&out)?;
         }
         Command::ListNeurons(opts) => {
-            let qr = opts.global_opts.qr;
+            let output = Output::new(&opts.global_opts);
             let out = list_neurons::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
-            print_vec(qr, &out)?;
+            output.print_vec(&out)?;
+        }
+        Command::GetFullNeuron(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = get_full_neuron::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+        Command::ListNeuronIds(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = list_neuron_ids::exec(&get_auth(opts.global_opts)?)?;
+            output.print_vec(&out)?;
         }
         Command::ClaimNeurons(opts) => {
-            let qr = opts.global_opts.qr;
+            let output = Output::new(&opts.global_opts);
             claim_neurons::exec(&get_auth(opts.global_opts)?)
-                .and_then(|out| print_vec(qr, &out))?;
+                .and_then(|out| output.print_vec(&out))?;
+        }
+        Command::Custom(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = custom::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
         }
+        #[cfg(feature = "online")]
         Command::ListProposals(opts) => runtime.block_on(async {
             list_proposals::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
         })?,
+        #[cfg(feature = "online")]
         Command::GetProposalInfo(opts) => runtime.block_on(async {
             get_proposal_info::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
         })?,
+        #[cfg(feature = "online")]
         Command::GetNeuronInfo(opts) => runtime.block_on(async {
             get_neuron_info::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
         })?,
+        #[cfg(feature = "online")]
+        Command::VotingHistory(opts) => runtime.block_on(async {
+            voting_history::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
+        })?,
+        #[cfg(feature = "online")]
         Command::AccountBalance(opts) => runtime.block_on(async {
             account_balance::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
         })?,
+        #[cfg(feature = "online")]
+        Command::AccountTransactions(opts) => runtime.block_on(async {
+            account_transactions::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
+        })?,
         Command::UpdateNodeProvider(opts) => {
             let out = update_node_provider::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
             print(&out)?;
@@ -106,10 +232,51 @@ pub fn dispatch(cmd: Command) -> AnyhowResult {
                 replace_node_provide_id::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
             print(&out)?;
         }
+        Command::RegisterKnownNeuron(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = register_known_neuron::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+        Command::MakeProposal(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = make_proposal::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+        #[cfg(feature = "online")]
+        Command::NodeProviderRewards(opts) => runtime.block_on(async {
+            node_provider_rewards::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
+        })?,
+        #[cfg(feature = "online")]
+        Command::ListNodeProviders(opts) => runtime.block_on(async {
+            list_node_providers::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
+        })?,
+        #[cfg(feature = "online")]
         Command::Send(opts) => runtime.block_on(async {
             send::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
         })?,
+        #[cfg(feature = "online")]
+        Command::VerifyReceipt(opts) => runtime.block_on(async {
+            verify_receipt::exec(opts.command_opts, opts.global_opts.fetch_root_key).await
+        })?,
+        #[cfg(feature = "online")]
+        Command::Fetch(opts) => {
+            let auth = get_auth(opts.global_opts)?;
+            runtime.block_on(async { fetch::exec(&auth, opts.command_opts).await })?
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui(opts) => {
+            let auth = get_auth(opts.global_opts)?;
+            runtime.block_on(async { tui::exec(&auth, opts.command_opts).await })?
+        }
+        Command::Sns(opts) => sns::dispatch(opts)?,
         Command::Generate(opts) => generate::exec(opts)?,
+        Command::SelfTest => self_test::exec()?,
+        Command::Version(opts) => version::exec(opts)?,
+        Command::Arg(opts) => arg::exec(opts)?,
+        Command::Contacts(opts) => contacts::exec(opts)?,
+        Command::Neuron(opts) => dispatch(opts.into())?,
+        Command::Ledger(opts) => dispatch(opts.into())?,
+        Command::Governance(opts) => dispatch(opts.into())?,
         // QR code for URL: https://p5deo-6aaaa-aaaab-aaaxq-cai.raw.ic0.app/
         // Source code: https://github.com/ninegua/ic-qr-scanner
         Command::ScannerQRCode => {
@@ -136,6 +303,16 @@ pub fn dispatch(cmd: Command) -> AnyhowResult {
 █████████████████████████████████████"
             );
         }
+        Command::RequestPayment(opts) => {
+            request_payment::exec(opts.command_opts, opts.global_opts.qr)?
+        }
+        Command::SignPolicy(opts) => {
+            sign_policy::exec(&get_auth(opts.global_opts)?, opts.command_opts)?
+        }
+        Command::Session(opts) => {
+            let output = Output::new(&opts.global_opts);
+            session::exec(get_auth(opts.global_opts)?, opts.command_opts, output)?
+        }
         Command::QRCode(opts) => qrcode::exec(opts.command_opts)?,
     }
     Ok(())
@@ -167,7 +344,7 @@ where
     e.write_all(json.as_bytes()).unwrap();
     let json = e.finish().unwrap();
     let json = base64::encode(json);
-    qrcode::print_qr(json.as_str());
+    qrcode::print_qr(json.as_str())?;
     if pause {
         let mut input_string = String::new();
         std::io::stdin()
@@ -177,12 +354,141 @@ where
     Ok(())
 }
 
+/// Where the output of the signing commands goes: STDOUT, as JSON or QR codes, a file on an
+/// output device, or a serial link.
+pub struct Output {
+    qr: bool,
+    device: Option<PathBuf>,
+    eject: bool,
+    serial: Option<PathBuf>,
+    review_summary: Option<PathBuf>,
+}
+
+impl Output {
+    fn new(opts: &GlobalOpts) -> Self {
+        Output {
+            qr: opts.qr,
+            device: opts.output_device.clone(),
+            eject: opts.eject,
+            serial: opts.to_serial.clone(),
+            review_summary: opts.review_summary.clone(),
+        }
+    }
+
+    fn print_vec<T>(&self, arg: &[T]) -> AnyhowResult
+    where
+        T: serde::ser::Serialize,
+    {
+        // The summary is written first, so that it is not missing if the device is ejected.
+        if let Some(path) = &self.review_summary {
+            let summary = summary::render(&serde_json::to_string(arg)?)?;
+            std::fs::write(path, summary)
+                .with_context(|| format!("Cannot write {}", path.display()))?;
+        }
+        if let Some(serial) = &self.serial {
+            return lib::serial::send(serial, serde_json::to_string(arg)?.as_bytes());
+        }
+        match &self.device {
+            Some(dir) => write_to_device(dir, serde_json::to_string(arg)?.as_bytes(), self.eject),
+            None => print_vec(self.qr, arg),
+        }
+    }
+}
+
+// Writes the bundle to a new file in the directory, flushes it to the device, and verifies the
+// file by reading it back before unmounting the device if requested.
+fn write_to_device(dir: &Path, bundle: &[u8], eject: bool) -> AnyhowResult {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis();
+    let path = dir.join(format!("quill-{}.json", timestamp));
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .with_context(|| format!("Cannot create {}", path.display()))?;
+    file.write_all(bundle)
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+    drop(file);
+    // Also persist the directory entry of the new file.
+    if let Ok(dir) = std::fs::File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    let expected = openssl::sha::sha256(bundle);
+    let mut written = Vec::new();
+    std::fs::File::open(&path)
+        .and_then(|mut file| {
+            bypass_page_cache(&file);
+            file.read_to_end(&mut written)
+        })
+        .with_context(|| format!("Cannot read back {}", path.display()))?;
+    if openssl::sha::sha256(&written) != expected {
+        bail!(
+            "{} does not match the signed messages, the device may be faulty",
+            path.display()
+        );
+    }
+    if !is_quiet() {
+        eprintln!(
+            "Wrote {} (sha256 {})",
+            path.display(),
+            hex::encode(expected)
+        );
+    }
+
+    if eject {
+        let status = if cfg!(target_os = "macos") {
+            std::process::Command::new("diskutil")
+                .arg("eject")
+                .arg(dir)
+                .status()
+        } else {
+            std::process::Command::new("umount").arg(dir).status()
+        }
+        .context("Cannot run the command to unmount the output device")?;
+        if !status.success() {
+            bail!("Cannot unmount {}: {}", dir.display(), status);
+        }
+    }
+    Ok(())
+}
+
+// Keeps the file from being read from the page cache, so that reading it back after it was
+// synced reads the device. This is best effort: Linux drops the cached pages, which it may not
+// do for all of them, macOS disables the cache for reads of the file, and other platforms read
+// the cache. Caches of the device itself are not bypassed.
+#[cfg(target_os = "linux")]
+fn bypass_page_cache(file: &std::fs::File) {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: the file descriptor is valid while the file is borrowed, and the advice does not
+    // change the contents of the file.
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn bypass_page_cache(file: &std::fs::File) {
+    use std::os::unix::io::AsRawFd;
+    // SAFETY: the file descriptor is valid while the file is borrowed.
+    unsafe {
+        libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn bypass_page_cache(_file: &std::fs::File) {}
+
 fn print_vec<T>(qr: bool, arg: &[T]) -> AnyhowResult
 where
     T: serde::ser::Serialize,
 {
     if !qr {
         print(arg)
+    } else if arg.len() > 1 && is_quiet() {
+        bail!("Cannot wait between QR codes in quiet mode; output the messages without --qr.")
     } else {
         for (i, a) in arg.iter().enumerate() {
             print_qr(&a, i != arg.len() - 1).context("Failed to print QR code")?;
@@ -1,57 +1,144 @@
+#[cfg(feature = "online")]
+use crate::lib::get_agent;
 use crate::lib::{
-    governance_canister_id,
-    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    contacts::resolve_account_id,
+    governance_canister_id, is_quiet,
+    nns_types::{
+        account_id::parse_account_id,
+        icpts::{E8s, Icp},
+        neuron_id::parse_neuron_id,
+        topic::parse_topic,
+    },
+    signing::{sign_ingress_with_request_status_query, IngressWithRequestId, Precondition},
+    timestamp::{format_timestamp_seconds, now_seconds, parse_timestamp},
     AnyhowResult, AuthInfo,
 };
 use anyhow::{anyhow, bail, Context};
-use candid::{CandidType, Encode, Principal};
-use clap::{ArgEnum, Parser};
+use candid::{CandidType, Decode, Encode, Principal};
+use clap::{ArgEnum, ArgGroup, Parser};
 use ic_base_types::PrincipalId;
 use ic_nns_common::pb::v1::{NeuronId, ProposalId};
+#[cfg(feature = "online")]
+use ic_nns_governance::pb::v1::GovernanceError;
 use ic_nns_governance::pb::v1::{
     manage_neuron::{
-        configure::Operation, AddHotKey, ChangeAutoStakeMaturity, Command, Configure, Disburse,
-        Follow, IncreaseDissolveDelay, JoinCommunityFund, LeaveCommunityFund, Merge, RegisterVote,
-        RemoveHotKey, Split, StakeMaturity, StartDissolving, StopDissolving,
+        claim_or_refresh::By, configure::Operation, disburse::Amount, AddHotKey,
+        ChangeAutoStakeMaturity, ClaimOrRefresh, Command, Configure, Disburse, DisburseToNeuron,
+        Follow, IncreaseDissolveDelay, JoinCommunityFund, LeaveCommunityFund, Merge,
+        NeuronIdOrSubaccount, RegisterVote, RemoveHotKey, SetDissolveTimestamp, Spawn, Split,
+        StakeMaturity, StartDissolving, StopDissolving,
     },
-    ManageNeuron,
+    Empty, ManageNeuron,
 };
+use ledger_canister::DEFAULT_TRANSFER_FEE;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
 
 // These constants are copied from src/governance.rs
 pub const ONE_DAY_SECONDS: u32 = 24 * 60 * 60;
 pub const ONE_YEAR_SECONDS: u32 = (4 * 365 + 1) * ONE_DAY_SECONDS / 4;
 pub const ONE_MONTH_SECONDS: u32 = ONE_YEAR_SECONDS / 12;
 pub const RANGE_LIMIT: usize = 100;
+#[cfg(feature = "online")]
+const MAX_DISSOLVE_DELAY_SECONDS: u64 = 8 * ONE_YEAR_SECONDS as u64;
+#[cfg(feature = "online")]
+const MAX_NEURON_AGE_FOR_AGE_BONUS: u64 = 4 * ONE_YEAR_SECONDS as u64;
+#[cfg(feature = "online")]
+const MIN_DISSOLVE_DELAY_FOR_VOTE_ELIGIBILITY_SECONDS: u64 = 6 * ONE_MONTH_SECONDS as u64;
+/// The minimum stake of a neuron, 1 ICP.
+pub const MIN_STAKE_E8S: u64 = 100_000_000;
 
 #[derive(CandidType)]
 pub struct AccountIdentifier {
     hash: Vec<u8>,
 }
 
+// `RefreshVotingPower` is newer than the governance types quill is built
+// against, so its message is encoded from these minimal definitions instead.
+#[derive(CandidType)]
+pub struct RefreshVotingPower {}
+
+#[derive(CandidType)]
+pub enum RefreshVotingPowerCommand {
+    RefreshVotingPower(RefreshVotingPower),
+}
+
+#[derive(CandidType)]
+pub struct ManageNeuronRefreshVotingPower {
+    pub id: Option<NeuronId>,
+    pub command: Option<RefreshVotingPowerCommand>,
+    pub neuron_id_or_subaccount: Option<NeuronIdOrSubaccount>,
+}
+
+// `SetFollowing` is also newer than the governance types, and sets the followees of several
+// topics in a single message.
+#[derive(CandidType, Deserialize)]
+pub struct FolloweesForTopic {
+    pub topic: Option<i32>,
+    pub followees: Option<Vec<NeuronId>>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SetFollowing {
+    pub topic_following: Option<Vec<FolloweesForTopic>>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum SetFollowingCommand {
+    SetFollowing(SetFollowing),
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ManageNeuronSetFollowing {
+    pub id: Option<NeuronId>,
+    pub command: Option<SetFollowingCommand>,
+    pub neuron_id_or_subaccount: Option<NeuronIdOrSubaccount>,
+}
+
 #[derive(Debug, Clone, Copy, ArgEnum)]
 enum EnableState {
+    #[clap(alias = "true")]
     Enabled,
+    #[clap(alias = "false")]
     Disabled,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+enum Vote {
+    Yes,
+    No,
+}
+
 /// Signs a neuron configuration change.
-#[derive(Parser)]
+#[derive(Default, Parser)]
+#[clap(group(ArgGroup::new("disbursing").args(&["disburse", "disburse-all"])))]
 pub struct ManageOpts {
     /// The id of the neuron to manage.
-    neuron_id: String,
+    #[clap(parse(try_from_str = parse_neuron_id))]
+    neuron_id: u64,
 
-    /// Principal to be used as a hot key.
-    #[clap(long)]
-    add_hot_key: Option<Principal>,
+    /// Principal to be used as a hot key (can be repeated, one message is signed per principal).
+    #[clap(long, multiple_occurrences(true))]
+    add_hot_key: Vec<Principal>,
 
-    /// Principal hot key to be removed.
-    #[clap(long)]
-    remove_hot_key: Option<Principal>,
+    /// Principal hot key to be removed (can be repeated, one message is signed per principal).
+    #[clap(long, multiple_occurrences(true))]
+    remove_hot_key: Vec<Principal>,
 
-    /// Number of dissolve seconds to add.
-    #[clap(short, long)]
+    /// Number of dissolve seconds to add. Also accepts a number with a unit suffix (e.g. 180d;
+    /// s, h, d, w and y are supported) or a constant such as SIX_MONTHS.
+    #[clap(short, long, alias("additional-dissolve-delay"))]
     additional_dissolve_delay_seconds: Option<String>,
 
+    /// Set the dissolve delay so that the neuron dissolves at the given time, given in seconds
+    /// since the Unix epoch or as a date such as 2030-01-01 or 2030-01-01T00:00:00Z.
+    #[clap(
+        long,
+        conflicts_with("additional-dissolve-delay-seconds"),
+        parse(try_from_str = parse_timestamp)
+    )]
+    dissolve_timestamp: Option<u64>,
+
     /// Start dissolving.
     #[clap(long)]
     start_dissolving: bool,
@@ -64,90 +151,254 @@ pub struct ManageOpts {
     #[clap(long)]
     disburse: bool,
 
+    /// Disburse only the given number of ICP (with up to 8 decimal digits).
+    #[clap(long, requires("disburse"))]
+    disburse_amount: Option<Icp>,
+
+    /// Disburse to the given account instead of the controller's account. Also accepts a contact
+    /// as `@<name>`.
+    #[clap(long, requires("disbursing"))]
+    disburse_to: Option<String>,
+
+    /// Disburse the entire stake, computed from the neuron file as the stake minus the
+    /// transaction fee, so that no remainder is left on the neuron.
+    #[clap(long, requires("neuron-file"))]
+    disburse_all: bool,
+
+    /// A neuron report saved from `send --neuron-report json`, or a snapshot written by `fetch`.
+    /// The disbursed amount defaults to the stake of the neuron in the report minus the
+    /// transaction fee, and --disburse-amount is checked against it.
+    #[clap(long, requires("disbursing"))]
+    neuron_file: Option<PathBuf>,
+
+    /// Warn if the neuron report or snapshot was fetched more than this many hours ago, according
+    /// to its timestamp.
+    #[clap(long, default_value = "24")]
+    neuron_file_max_age_hours: u64,
+
     /// Spawn rewards to a new neuron under the controller's account.
     #[clap(long)]
     spawn: bool,
 
-    /// Split off the given number of ICP from a neuron.
+    /// The controller of the spawned neuron, the controller of this neuron by default.
+    #[clap(long, requires("spawn"))]
+    spawn_to: Option<Principal>,
+
+    /// Disburse the given amount from a dissolved neuron into a new neuron, in ICP (e.g. 1.5) or
+    /// in e8s (e.g. 150_000_000e8s).
+    #[clap(long, requires("new-neuron-dissolve-delay"))]
+    disburse_to_neuron: Option<Icp>,
+
+    /// The dissolve delay of the new neuron, in seconds or with a unit suffix (e.g. 180d).
+    #[clap(long, requires("disburse-to-neuron"))]
+    new_neuron_dissolve_delay: Option<String>,
+
+    /// The controller of the new neuron, the principal of the key by default.
+    #[clap(long, requires("disburse-to-neuron"))]
+    new_neuron_controller: Option<Principal>,
+
+    /// The nonce of the new neuron, from which its subaccount is derived. Random by default.
+    #[clap(long, requires("disburse-to-neuron"))]
+    new_neuron_nonce: Option<u64>,
+
+    /// Split off the given amount from a neuron, in ICP (e.g. 1.5) or in e8s (e.g.
+    /// 150_000_000e8s).
     #[clap(long)]
-    split: Option<u64>,
+    split: Option<Icp>,
 
     /// Remove all followees for the NeuronManagement topic
     #[clap(long)]
     clear_manage_neuron_followees: bool,
 
     /// Merge stake, maturity and age from the neuron specified by this option into the neuron being managed.
-    #[clap(long)]
-    merge_from_neuron: Option<String>,
+    #[clap(long, parse(try_from_str = parse_neuron_id))]
+    merge_from_neuron: Option<u64>,
 
     /// Merge the percentage (between 1 and 100) of the maturity of a neuron into the current stake.
     #[clap(hide(true), long)]
     merge_maturity: Option<u32>,
 
-    /// Stake a percentage (between 1 and 100) of the maturity of a neuron.
-    #[clap(long)]
-    stake_maturity: Option<u32>,
+    /// Stake a percentage (between 1 and 100) of the maturity of a neuron, all of it if no
+    /// percentage is given.
+    #[clap(long, value_name = "PERCENTAGE")]
+    stake_maturity: Option<Option<u32>>,
 
-    /// Join the Internet Computer's community fund with this neuron's entire stake.
-    #[clap(long)]
+    /// Join the Internet Computer's community fund, also called the Neurons' Fund, with this
+    /// neuron's entire stake.
+    #[clap(long, alias("join-neurons-fund"))]
     join_community_fund: bool,
 
-    /// Leave the Internet Computer's community fund.
-    #[clap(long, conflicts_with("join-community-fund"))]
+    /// Leave the Internet Computer's community fund, also called the Neurons' Fund.
+    #[clap(
+        long,
+        alias("leave-neurons-fund"),
+        conflicts_with("join-community-fund")
+    )]
     leave_community_fund: bool,
 
-    /// Defines the topic of a follow rule.
-    #[clap(long)]
+    /// Defines the topic of a follow rule, by number or name (e.g. 4 or Governance).
+    #[clap(long, requires("follow-neurons"), parse(try_from_str = parse_topic))]
     follow_topic: Option<i32>,
 
-    /// Defines the neuron ids of a follow rule.
-    #[clap(long, multiple_values(true))]
+    /// Defines the neuron ids of a follow rule, separated by spaces or commas.
+    #[clap(
+        long,
+        multiple_values(true),
+        use_value_delimiter(true),
+        requires("follow-topic"),
+        parse(try_from_str = parse_neuron_id)
+    )]
     follow_neurons: Option<Vec<u64>>,
 
-    /// Vote on proposal(s) (approve by default).
+    /// Sets the followees of a topic, given as TOPIC=NEURON_ID,NEURON_ID (can be repeated). The
+    /// followees of all topics are set in a single message; an empty list clears the followees.
+    #[clap(
+        long,
+        multiple_occurrences(true),
+        value_name("TOPIC=NEURON_IDS"),
+        parse(try_from_str = parse_topic_following)
+    )]
+    set_following: Vec<(i32, Vec<u64>)>,
+
+    /// Vote on proposal(s) (approve by default), given as ids, ranges or comma-separated lists
+    /// of them. One message is signed per proposal.
     #[clap(long, multiple_values(true))]
     register_vote: Option<Vec<String>>,
 
+    /// Also votes with these neurons, separated by spaces or commas, e.g. the neurons of which
+    /// the key is a hot key. One message is signed per neuron and proposal.
+    #[clap(
+        long,
+        multiple_values(true),
+        use_value_delimiter(true),
+        requires("register-vote"),
+        parse(try_from_str = parse_neuron_id)
+    )]
+    also_vote_with: Vec<u64>,
+
+    /// Also votes with the neurons listed in this file: a neuron report saved from `send
+    /// --neuron-report json`, a snapshot written by `fetch --neurons`, or neuron ids separated
+    /// by whitespace or commas, with `#` starting a comment.
+    #[clap(long, requires("register-vote"))]
+    voting_neurons_file: Option<PathBuf>,
+
     /// Reject proposal(s).
     #[clap(long)]
     reject: bool,
 
-    /// Set whether new maturity should be automatically staked.
+    /// The vote on the proposal(s), the same as --reject for "no".
+    #[clap(long, arg_enum, requires("register-vote"), conflicts_with("reject"))]
+    vote: Option<Vote>,
+
+    /// Set whether new maturity should be automatically staked (also accepts true or false).
     #[clap(long, arg_enum)]
     auto_stake_maturity: Option<EnableState>,
+
+    /// Refresh the stake of the neuron after a transfer to its account, e.g. a top-up with
+    /// `neuron-stake --top-up`, so that governance picks up the increased stake.
+    #[clap(long)]
+    refresh: bool,
+
+    /// Refresh the voting power of the neuron. This is done by default along with any other
+    /// operation.
+    #[clap(long)]
+    refresh_voting_power: bool,
+
+    /// Do not refresh the voting power of the neuron along with the other operations.
+    #[clap(long, conflicts_with("refresh-voting-power"))]
+    skip_refresh_voting_power: bool,
+
+    /// Attaches preconditions to the messages, which `send` checks right before submitting
+    /// them: votes are only sent while their proposal is open, and disbursements once the
+    /// neuron is dissolved. Messages whose preconditions fail are skipped.
+    #[clap(long)]
+    with_preconditions: bool,
+}
+
+/// Signs the votes of a neuron on proposals.
+#[derive(Parser)]
+pub struct VoteOpts {
+    /// The id of the voting neuron.
+    #[clap(parse(try_from_str = parse_neuron_id))]
+    neuron_id: u64,
+
+    /// The proposals to vote on, given as ids, as ranges such as 80000-10 for the proposals
+    /// 80000 to 80010, or as comma-separated lists of them such as 80000,80003-5.
+    #[clap(required(true))]
+    proposals: Vec<String>,
+
+    /// Reject the proposals instead of approving them.
+    #[clap(long)]
+    reject: bool,
+
+    /// The vote on the proposals, the same as --reject for "no".
+    #[clap(long, arg_enum, conflicts_with("reject"))]
+    vote: Option<Vote>,
+
+    /// Also votes with these neurons, separated by spaces or commas.
+    #[clap(
+        long,
+        multiple_values(true),
+        use_value_delimiter(true),
+        parse(try_from_str = parse_neuron_id)
+    )]
+    also_vote_with: Vec<u64>,
+
+    /// Also votes with the neurons listed in this file: a neuron report, a snapshot written by
+    /// `fetch --neurons`, or neuron ids separated by whitespace or commas.
+    #[clap(long)]
+    voting_neurons_file: Option<PathBuf>,
+
+    /// Only sends the votes on proposals which are still open when `send` submits them.
+    #[clap(long)]
+    with_preconditions: bool,
+}
+
+impl From<VoteOpts> for ManageOpts {
+    fn from(opts: VoteOpts) -> Self {
+        ManageOpts {
+            neuron_id: opts.neuron_id,
+            register_vote: Some(opts.proposals),
+            also_vote_with: opts.also_vote_with,
+            voting_neurons_file: opts.voting_neurons_file,
+            reject: opts.reject,
+            vote: opts.vote,
+            with_preconditions: opts.with_preconditions,
+            ..Default::default()
+        }
+    }
 }
 
 pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
     let mut msgs = Vec::new();
 
-    let id = Some(NeuronId {
-        id: parse_neuron_id(opts.neuron_id)?,
-    });
-    if opts.add_hot_key.is_some() {
+    let neuron_id = opts.neuron_id;
+    let id = Some(NeuronId { id: neuron_id });
+    for hot_key in opts.add_hot_key {
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
             command: Some(Command::Configure(Configure {
                 operation: Some(Operation::AddHotKey(AddHotKey {
-                    new_hot_key: opts.add_hot_key.map(PrincipalId)
+                    new_hot_key: Some(PrincipalId(hot_key))
                 }))
             })),
             neuron_id_or_subaccount: None,
         })?;
         msgs.push(args);
-    };
+    }
 
-    if opts.remove_hot_key.is_some() {
+    for hot_key in opts.remove_hot_key {
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
             command: Some(Command::Configure(Configure {
                 operation: Some(Operation::RemoveHotKey(RemoveHotKey {
-                    hot_key_to_remove: opts.remove_hot_key.map(PrincipalId)
+                    hot_key_to_remove: Some(PrincipalId(hot_key))
                 }))
             })),
             neuron_id_or_subaccount: None,
         })?;
         msgs.push(args);
-    };
+    }
 
     if opts.stop_dissolving {
         let args = Encode!(&ManageNeuron {
@@ -176,41 +427,9 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
             id: id.clone(),
             command: Some(Command::Configure(Configure {
                 operation: Some(Operation::IncreaseDissolveDelay(IncreaseDissolveDelay {
-                    additional_dissolve_delay_seconds: match additional_dissolve_delay_seconds
-                        .as_ref()
-                    {
-                        "ONE_DAY" => ONE_DAY_SECONDS,
-
-                        "ONE_WEEK" => ONE_DAY_SECONDS * 7,
-                        "TWO_WEEKS" => ONE_DAY_SECONDS * 7 * 2,
-                        "THREE_WEEKS" => ONE_DAY_SECONDS * 7 * 3,
-                        "FOUR_WEEKS" => ONE_DAY_SECONDS * 7 * 4,
-
-                        "ONE_MONTH" => ONE_MONTH_SECONDS,
-                        "TWO_MONTHS" => ONE_MONTH_SECONDS * 2,
-                        "THREE_MONTHS" => ONE_MONTH_SECONDS * 3,
-                        "FOUR_MONTHS" => ONE_MONTH_SECONDS * 4,
-                        "FIVE_MONTHS" => ONE_MONTH_SECONDS * 5,
-                        "SIX_MONTHS" => ONE_MONTH_SECONDS * 6,
-                        "SEVEN_MONTHS" => ONE_MONTH_SECONDS * 7,
-                        "EIGHT_MONTHS" => ONE_MONTH_SECONDS * 8,
-                        "NINE_MONTHS" => ONE_MONTH_SECONDS * 9,
-                        "TEN_MONTHS" => ONE_MONTH_SECONDS * 10,
-                        "ELEVEN_MONTHS" => ONE_MONTH_SECONDS * 11,
-
-                        "ONE_YEAR" => ONE_YEAR_SECONDS,
-                        "TWO_YEARS" => ONE_YEAR_SECONDS * 2,
-                        "THREE_YEARS" => ONE_YEAR_SECONDS * 3,
-                        "FOUR_YEARS" => ONE_YEAR_SECONDS * 4,
-                        "FIVE_YEARS" => ONE_YEAR_SECONDS * 5,
-                        "SIX_YEARS" => ONE_YEAR_SECONDS * 6,
-                        "SEVEN_YEARS" => ONE_YEAR_SECONDS * 7,
-                        "EIGHT_YEARS" => ONE_YEAR_SECONDS * 8,
-
-                        s => s
-                            .parse::<u32>()
-                            .context("Failed to parse the dissolve delay")?,
-                    }
+                    additional_dissolve_delay_seconds: parse_dissolve_delay(
+                        &additional_dissolve_delay_seconds
+                    )?
                 }))
             })),
             neuron_id_or_subaccount: None,
@@ -218,12 +437,43 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
         msgs.push(args);
     };
 
-    if opts.disburse {
+    if let Some(dissolve_timestamp_seconds) = opts.dissolve_timestamp {
+        // Governance only increases dissolve delays, to a time in the future.
+        if dissolve_timestamp_seconds <= now_seconds()? {
+            bail!(
+                "The dissolve timestamp {} is in the past",
+                format_timestamp_seconds(dissolve_timestamp_seconds)
+            );
+        }
+        let args = Encode!(&ManageNeuron {
+            id: id.clone(),
+            command: Some(Command::Configure(Configure {
+                operation: Some(Operation::SetDissolveTimestamp(SetDissolveTimestamp {
+                    dissolve_timestamp_seconds
+                }))
+            })),
+            neuron_id_or_subaccount: None,
+        })?;
+        msgs.push(args);
+    };
+
+    if opts.disburse || opts.disburse_all {
+        let to_account = match &opts.disburse_to {
+            Some(to) => Some(parse_account_id(&resolve_account_id(to)?)?),
+            None => None,
+        };
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
             command: Some(Command::Disburse(Disburse {
-                to_account: None,
-                amount: None
+                to_account: to_account
+                    .map(|to| ledger_canister::protobuf::AccountIdentifier { hash: to.to_vec() }),
+                amount: get_disburse_amount(
+                    neuron_id,
+                    opts.disburse_amount,
+                    opts.neuron_file.as_deref(),
+                    opts.neuron_file_max_age_hours
+                )?
+                .map(|e8s| Amount { e8s: e8s.get() })
             })),
             neuron_id_or_subaccount: None,
         })?;
@@ -233,7 +483,33 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
     if opts.spawn {
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
-            command: Some(Command::Spawn(Default::default())),
+            command: Some(Command::Spawn(Spawn {
+                new_controller: opts.spawn_to.map(PrincipalId),
+                ..Default::default()
+            })),
+            neuron_id_or_subaccount: None,
+        })?;
+        msgs.push(args);
+    };
+
+    if let Some(amount) = opts.disburse_to_neuron {
+        let dissolve_delay_seconds = match &opts.new_neuron_dissolve_delay {
+            Some(delay) => parse_dissolve_delay(delay)?,
+            None => bail!("--disburse-to-neuron requires --new-neuron-dissolve-delay"),
+        };
+        let new_controller = match opts.new_neuron_controller {
+            Some(controller) => controller,
+            None => crate::commands::get_ids(auth)?.0,
+        };
+        let args = Encode!(&ManageNeuron {
+            id: id.clone(),
+            command: Some(Command::DisburseToNeuron(DisburseToNeuron {
+                new_controller: Some(PrincipalId(new_controller)),
+                amount_e8s: E8s::from(amount).get(),
+                dissolve_delay_seconds: u64::from(dissolve_delay_seconds),
+                kyc_verified: true,
+                nonce: opts.new_neuron_nonce.unwrap_or_else(rand::random),
+            })),
             neuron_id_or_subaccount: None,
         })?;
         msgs.push(args);
@@ -243,7 +519,7 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
             command: Some(Command::Split(Split {
-                amount_e8s: amount * 100_000_000
+                amount_e8s: E8s::from(amount).get()
             })),
             neuron_id_or_subaccount: None,
         })?;
@@ -263,30 +539,40 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
     }
 
     if let Some(neuron_id) = opts.merge_from_neuron {
+        // Governance would only refuse the message once it is sent.
+        if neuron_id == opts.neuron_id {
+            bail!("Cannot merge neuron {} into itself", neuron_id);
+        }
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
             command: Some(Command::Merge(Merge {
-                source_neuron_id: Some(NeuronId {
-                    id: parse_neuron_id(neuron_id)?
-                }),
+                source_neuron_id: Some(NeuronId { id: neuron_id }),
             })),
             neuron_id_or_subaccount: None,
         })?;
         msgs.push(args);
     };
 
-    if opts.merge_maturity.is_some() {
-        bail!("Merging maturity is no longer a supported option. See --stake-maturity. https://wiki.internetcomputer.org/wiki/NNS_neuron_operations_related_to_maturity");
+    // Governance refuses `MergeMaturity` since maturity can be staked instead, so a signed
+    // message would only fail once sent.
+    if let Some(percentage) = opts.merge_maturity {
+        bail!(
+            "Merging maturity is no longer supported by governance. Use --stake-maturity {} \
+             instead, which adds the maturity to the voting power of the neuron. \
+             https://wiki.internetcomputer.org/wiki/NNS_neuron_operations_related_to_maturity",
+            percentage
+        );
     };
 
     if let Some(percentage) = opts.stake_maturity {
-        if !(1..=100).contains(&percentage) {
-            bail!("Percentage to merge must be a number from 1 to 100");
+        if percentage.map_or(false, |percentage| !(1..=100).contains(&percentage)) {
+            bail!("Percentage to stake must be a number from 1 to 100");
         }
+        // Without a percentage, governance stakes all of the maturity.
         let args = Encode!(&ManageNeuron {
             id: id.clone(),
             command: Some(Command::StakeMaturity(StakeMaturity {
-                percentage_to_stake: Some(percentage),
+                percentage_to_stake: percentage,
             })),
             neuron_id_or_subaccount: None,
         })?;
@@ -316,22 +602,20 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
     }
 
     if let Some(proposals) = opts.register_vote {
-        for proposal in proposals {
-            let mut proposals = Vec::new();
-            if proposal.contains('-') {
-                let (first, last) = get_range(&proposal)?;
-                proposals = (first..last + 1).collect();
-            } else if let Ok(proposal) = proposal.parse::<u64>() {
-                proposals.push(proposal);
-            } else {
-                return Err(anyhow!("Unable to parse proposal or range."));
-            }
-            for proposal in proposals {
+        let reject = opts.reject || opts.vote == Some(Vote::No);
+        let proposals = parse_proposals(&proposals)?;
+        let voters = voting_neurons(
+            neuron_id,
+            &opts.also_vote_with,
+            opts.voting_neurons_file.as_deref(),
+        )?;
+        for voter in voters {
+            for proposal in &proposals {
                 let args = Encode!(&ManageNeuron {
-                    id: id.clone(),
+                    id: Some(NeuronId { id: voter }),
                     command: Some(Command::RegisterVote(RegisterVote {
-                        vote: if opts.reject { 2 } else { 1 },
-                        proposal: Some(ProposalId { id: proposal }),
+                        vote: if reject { 2 } else { 1 },
+                        proposal: Some(ProposalId { id: *proposal }),
                     })),
                     neuron_id_or_subaccount: None,
                 })?;
@@ -357,10 +641,29 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
         return Err(anyhow!("Followees specified without topic."));
     }
 
+    if !opts.set_following.is_empty() {
+        let topic_following = opts
+            .set_following
+            .iter()
+            .map(|(topic, neuron_ids)| FolloweesForTopic {
+                topic: Some(*topic),
+                followees: Some(neuron_ids.iter().map(|id| NeuronId { id: *id }).collect()),
+            })
+            .collect();
+        let args = Encode!(&ManageNeuronSetFollowing {
+            id: id.clone(),
+            command: Some(SetFollowingCommand::SetFollowing(SetFollowing {
+                topic_following: Some(topic_following),
+            })),
+            neuron_id_or_subaccount: None,
+        })?;
+        msgs.push(args);
+    }
+
     if let Some(enable) = opts.auto_stake_maturity {
         let requested_setting_for_auto_stake_maturity = matches!(enable, EnableState::Enabled);
         let args = Encode!(&ManageNeuron {
-            id,
+            id: id.clone(),
             command: Some(Command::Configure(Configure {
                 operation: Some(Operation::ChangeAutoStakeMaturity(
                     ChangeAutoStakeMaturity {
@@ -373,26 +676,525 @@ pub fn exec(auth: &AuthInfo, opts: ManageOpts) -> AnyhowResult<Vec<IngressWithRe
         msgs.push(args);
     }
 
+    if opts.refresh {
+        let args = Encode!(&ManageNeuron {
+            id: id.clone(),
+            command: Some(Command::ClaimOrRefresh(ClaimOrRefresh {
+                by: Some(By::NeuronIdOrSubaccount(Empty {}))
+            })),
+            neuron_id_or_subaccount: None,
+        })?;
+        msgs.push(args);
+    }
+
+    // Governance resets the following of neurons whose voting power has not been refreshed
+    // for a while, so keep the neuron active whenever it is managed.
+    if opts.refresh_voting_power || (!msgs.is_empty() && !opts.skip_refresh_voting_power) {
+        let args = Encode!(&ManageNeuronRefreshVotingPower {
+            id,
+            command: Some(RefreshVotingPowerCommand::RefreshVotingPower(
+                RefreshVotingPower {}
+            )),
+            neuron_id_or_subaccount: None,
+        })?;
+        msgs.push(args);
+    }
+
     if msgs.is_empty() {
         return Err(anyhow!("No instructions provided"));
     }
 
     let mut generated = Vec::new();
     for args in msgs {
-        generated.push(sign_ingress_with_request_status_query(
+        let preconditions = if opts.with_preconditions {
+            preconditions(neuron_id, &args)
+        } else {
+            Vec::new()
+        };
+        let message = sign_ingress_with_request_status_query(
             auth,
             governance_canister_id(),
             "manage_neuron",
             args,
-        )?);
+        )?;
+        generated.push(message.with_preconditions(preconditions));
     }
     Ok(generated)
 }
 
-fn parse_neuron_id(id: String) -> AnyhowResult<u64> {
-    id.replace('_', "")
-        .parse()
-        .context("Failed to parse the neuron id")
+// Returns the preconditions of the command in the argument of `manage_neuron`.
+fn preconditions(neuron_id: u64, args: &[u8]) -> Vec<Precondition> {
+    // Commands unknown to the governance types, e.g. `SetFollowing`, have none.
+    match Decode!(args, ManageNeuron)
+        .ok()
+        .and_then(|args| args.command)
+    {
+        Some(Command::RegisterVote(RegisterVote {
+            proposal: Some(proposal),
+            ..
+        })) => vec![Precondition::ProposalOpen {
+            proposal_id: proposal.id,
+        }],
+        Some(Command::Disburse(_)) => vec![Precondition::NeuronDissolved { neuron_id }],
+        _ => Vec::new(),
+    }
+}
+
+// Parses a dissolve delay given as seconds, as a number with a unit suffix, or as one of the
+// named constants, and returns it in seconds.
+fn parse_dissolve_delay(delay: &str) -> AnyhowResult<u32> {
+    let seconds = match delay {
+        "ONE_DAY" => ONE_DAY_SECONDS,
+
+        "ONE_WEEK" => ONE_DAY_SECONDS * 7,
+        "TWO_WEEKS" => ONE_DAY_SECONDS * 7 * 2,
+        "THREE_WEEKS" => ONE_DAY_SECONDS * 7 * 3,
+        "FOUR_WEEKS" => ONE_DAY_SECONDS * 7 * 4,
+
+        "ONE_MONTH" => ONE_MONTH_SECONDS,
+        "TWO_MONTHS" => ONE_MONTH_SECONDS * 2,
+        "THREE_MONTHS" => ONE_MONTH_SECONDS * 3,
+        "FOUR_MONTHS" => ONE_MONTH_SECONDS * 4,
+        "FIVE_MONTHS" => ONE_MONTH_SECONDS * 5,
+        "SIX_MONTHS" => ONE_MONTH_SECONDS * 6,
+        "SEVEN_MONTHS" => ONE_MONTH_SECONDS * 7,
+        "EIGHT_MONTHS" => ONE_MONTH_SECONDS * 8,
+        "NINE_MONTHS" => ONE_MONTH_SECONDS * 9,
+        "TEN_MONTHS" => ONE_MONTH_SECONDS * 10,
+        "ELEVEN_MONTHS" => ONE_MONTH_SECONDS * 11,
+
+        "ONE_YEAR" => ONE_YEAR_SECONDS,
+        "TWO_YEARS" => ONE_YEAR_SECONDS * 2,
+        "THREE_YEARS" => ONE_YEAR_SECONDS * 3,
+        "FOUR_YEARS" => ONE_YEAR_SECONDS * 4,
+        "FIVE_YEARS" => ONE_YEAR_SECONDS * 5,
+        "SIX_YEARS" => ONE_YEAR_SECONDS * 6,
+        "SEVEN_YEARS" => ONE_YEAR_SECONDS * 7,
+        "EIGHT_YEARS" => ONE_YEAR_SECONDS * 8,
+
+        s => {
+            let (number, unit) = match s.find(|c: char| !c.is_ascii_digit() && c != '_') {
+                Some(i) => s.split_at(i),
+                None => (s, ""),
+            };
+            let number = number
+                .replace('_', "")
+                .parse::<u32>()
+                .context("Failed to parse the dissolve delay")?;
+            let unit_seconds = match unit {
+                "" => {
+                    if number < 3600 && !is_quiet() {
+                        eprintln!(
+                            "Warning: the dissolve delay {} is interpreted as seconds, \
+                             use a suffix such as {}d to specify days.",
+                            number, number
+                        );
+                    }
+                    1
+                }
+                "s" => 1,
+                "h" => 60 * 60,
+                "d" => ONE_DAY_SECONDS,
+                "w" => ONE_DAY_SECONDS * 7,
+                "y" => ONE_YEAR_SECONDS,
+                _ => bail!("Unknown dissolve delay unit: {}", unit),
+            };
+            number
+                .checked_mul(unit_seconds)
+                .context("The dissolve delay is too large")?
+        }
+    };
+    Ok(seconds)
+}
+
+// Parses the followees of a topic given as TOPIC=NEURON_ID,NEURON_ID.
+fn parse_topic_following(following: &str) -> AnyhowResult<(i32, Vec<u64>)> {
+    let (topic, neuron_ids) = following
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected TOPIC=NEURON_IDS, found {}", following))?;
+    let topic = parse_topic(topic)?;
+    let neuron_ids = neuron_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(parse_neuron_id)
+        .collect::<AnyhowResult<_>>()?;
+    Ok((topic, neuron_ids))
+}
+
+// Only the fields of `NeuronInfo` which determine the voting power are decoded.
+#[cfg(feature = "online")]
+#[derive(CandidType, Deserialize)]
+struct NeuronStake {
+    stake_e8s: u64,
+    dissolve_delay_seconds: u64,
+    age_seconds: u64,
+}
+
+#[cfg(feature = "online")]
+#[derive(CandidType, Deserialize)]
+enum NeuronInfoResult {
+    Ok(NeuronStake),
+    Err(GovernanceError),
+}
+
+/// Returns the voting power of a neuron as computed by governance: the dissolve delay adds up to
+/// 100% for 8 years, and the age up to 25% for 4 years.
+#[cfg(feature = "online")]
+fn voting_power(stake_e8s: u64, dissolve_delay_seconds: u64, age_seconds: u64) -> u64 {
+    if dissolve_delay_seconds < MIN_DISSOLVE_DELAY_FOR_VOTE_ELIGIBILITY_SECONDS {
+        return 0;
+    }
+    let dissolve_delay = u128::from(dissolve_delay_seconds.min(MAX_DISSOLVE_DELAY_SECONDS));
+    let age = u128::from(age_seconds.min(MAX_NEURON_AGE_FOR_AGE_BONUS));
+    let max_dissolve_delay = u128::from(MAX_DISSOLVE_DELAY_SECONDS);
+    let max_age = u128::from(MAX_NEURON_AGE_FOR_AGE_BONUS);
+    let power = u128::from(stake_e8s) * (max_dissolve_delay + dissolve_delay) / max_dissolve_delay
+        * (4 * max_age + age)
+        / (4 * max_age);
+    power as u64
+}
+
+#[cfg(feature = "online")]
+fn format_voting_power(power: u64) -> String {
+    format!("{:.2}", power as f64 / 1e8)
+}
+
+#[cfg(feature = "online")]
+async fn fetch_neuron_stake(neuron_id: u64) -> AnyhowResult<NeuronStake> {
+    let reply = get_agent(&AuthInfo::NoAuth)?
+        .query(&governance_canister_id(), "get_neuron_info")
+        .with_arg(Encode!(&neuron_id)?)
+        .call()
+        .await?;
+    match Decode!(&reply, NeuronInfoResult)? {
+        NeuronInfoResult::Ok(stake) => Ok(stake),
+        NeuronInfoResult::Err(err) => Err(anyhow!(err.error_message)),
+    }
+}
+
+/// Returns the ids of the neurons followed by a `manage_neuron` argument, if it sets followees.
+#[cfg(feature = "online")]
+pub fn followee_ids(arg: &[u8]) -> Vec<u64> {
+    if let Ok(ManageNeuron {
+        command: Some(Command::Follow(follow)),
+        ..
+    }) = Decode!(arg, ManageNeuron)
+    {
+        return follow.followees.iter().map(|id| id.id).collect();
+    }
+    if let Ok(ManageNeuronSetFollowing {
+        command: Some(SetFollowingCommand::SetFollowing(set_following)),
+        ..
+    }) = Decode!(arg, ManageNeuronSetFollowing)
+    {
+        return set_following
+            .topic_following
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|topic| topic.followees.unwrap_or_default())
+            .map(|id| id.id)
+            .collect();
+    }
+    Vec::new()
+}
+
+/// If the argument disburses, splits or merges a neuron, describes the change of its voting power
+/// and the age bonus which is lost, using the current state of the neurons.
+#[cfg(feature = "online")]
+pub async fn describe_stake_impact(arg: &[u8]) -> Option<String> {
+    // Messages of newer commands such as `RefreshVotingPower` do not decode as `ManageNeuron`.
+    let manage = Decode!(arg, ManageNeuron).ok()?;
+    let neuron_id = manage.id?.id;
+    let command = manage.command?;
+    if !matches!(
+        command,
+        Command::Disburse(_) | Command::Split(_) | Command::Merge(_)
+    ) {
+        return None;
+    }
+    Some(match describe_command_impact(neuron_id, command).await {
+        Ok(description) => description,
+        Err(err) => format!("Voting power impact: unavailable ({})", err),
+    })
+}
+
+#[cfg(feature = "online")]
+async fn describe_command_impact(neuron_id: u64, command: Command) -> AnyhowResult<String> {
+    let neuron = fetch_neuron_stake(neuron_id).await?;
+    let fee = DEFAULT_TRANSFER_FEE.get_e8s();
+    let before = voting_power(
+        neuron.stake_e8s,
+        neuron.dissolve_delay_seconds,
+        neuron.age_seconds,
+    );
+    let age_bonus = |age_seconds: u64| {
+        age_seconds.min(MAX_NEURON_AGE_FOR_AGE_BONUS) as f64 / MAX_NEURON_AGE_FOR_AGE_BONUS as f64
+            * 25.0
+    };
+    Ok(match command {
+        Command::Disburse(disburse) => {
+            let amount = disburse
+                .amount
+                .map_or(neuron.stake_e8s.saturating_sub(fee), |amount| amount.e8s);
+            format!(
+                "Disbursing {} ICP of neuron {}, which is {} days old: staked again, they would \
+                 lose an age bonus of +{:.2}%.",
+                Icp::from(E8s::new(amount)),
+                neuron_id,
+                neuron.age_seconds / u64::from(ONE_DAY_SECONDS),
+                age_bonus(neuron.age_seconds)
+            )
+        }
+        Command::Split(split) => {
+            let remaining = neuron.stake_e8s.saturating_sub(split.amount_e8s);
+            let child = split.amount_e8s.saturating_sub(fee);
+            let after =
+                |stake| voting_power(stake, neuron.dissolve_delay_seconds, neuron.age_seconds);
+            format!(
+                "Voting power of neuron {}: {} before, {} after the split, and {} for the new \
+                 neuron, which keeps the dissolve delay and the age; {} ICP are lost to the fee.",
+                neuron_id,
+                format_voting_power(before),
+                format_voting_power(after(remaining)),
+                format_voting_power(after(child)),
+                Icp::from(E8s::new(fee))
+            )
+        }
+        Command::Merge(merge) => {
+            let source_id = merge
+                .source_neuron_id
+                .context("The merge has no source neuron")?
+                .id;
+            let source = fetch_neuron_stake(source_id).await?;
+            let moved = source.stake_e8s.saturating_sub(fee);
+            let stake = neuron.stake_e8s + moved;
+            // The age of the merged stake is the average age weighted by the stakes.
+            let age = if stake == 0 {
+                0
+            } else {
+                ((u128::from(neuron.stake_e8s) * u128::from(neuron.age_seconds)
+                    + u128::from(moved) * u128::from(source.age_seconds))
+                    / u128::from(stake)) as u64
+            };
+            let dissolve_delay = neuron
+                .dissolve_delay_seconds
+                .max(source.dissolve_delay_seconds);
+            let source_before = voting_power(
+                source.stake_e8s,
+                source.dissolve_delay_seconds,
+                source.age_seconds,
+            );
+            format!(
+                "Voting power of neurons {} and {}: {} before, {} after the merge; the merged \
+                 neuron is {} days old (age bonus +{:.2}%).",
+                neuron_id,
+                source_id,
+                format_voting_power(before + source_before),
+                format_voting_power(voting_power(stake, dissolve_delay, age)),
+                age / u64::from(ONE_DAY_SECONDS),
+                age_bonus(age)
+            )
+        }
+        _ => unreachable!(),
+    })
+}
+
+// Only the fields needed from the neuron report are decoded.
+#[derive(Deserialize)]
+struct NeuronSnapshot {
+    id: u64,
+    stake_e8s: u64,
+}
+
+// A neuron file is either a neuron report, or a snapshot written by `fetch`, which records when
+// it was fetched.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NeuronFile {
+    // A report saved before reports recorded when they were fetched.
+    Report(Vec<NeuronSnapshot>),
+    Snapshot {
+        fetched_at_seconds: u64,
+        neurons: Vec<NeuronSnapshot>,
+    },
+}
+
+/// Returns the amount to disburse from the neuron, where `None` disburses the entire stake.
+/// With a neuron file, the amount defaults to the stake minus the transaction fee, and a given
+/// amount must not exceed it.
+fn get_disburse_amount(
+    neuron_id: u64,
+    amount: Option<Icp>,
+    neuron_file: Option<&Path>,
+    max_age_hours: u64,
+) -> AnyhowResult<Option<E8s>> {
+    let neuron_file = match neuron_file {
+        Some(neuron_file) => neuron_file,
+        None => return Ok(amount.map(E8s::from)),
+    };
+    let json = std::fs::read_to_string(neuron_file)
+        .with_context(|| format!("Cannot read the neuron file {}", neuron_file.display()))?;
+    let (neurons, age) = match serde_json::from_str(&json)
+        .with_context(|| format!("Cannot parse the neuron file {}", neuron_file.display()))?
+    {
+        NeuronFile::Report(neurons) => (neurons, None),
+        NeuronFile::Snapshot {
+            fetched_at_seconds,
+            neurons,
+        } => (
+            neurons,
+            Some(now_seconds()?.saturating_sub(fetched_at_seconds)),
+        ),
+    };
+    let neuron = neurons
+        .iter()
+        .find(|neuron| neuron.id == neuron_id)
+        .ok_or_else(|| {
+            anyhow!(
+                "Neuron {} is not in the neuron file {}",
+                neuron_id,
+                neuron_file.display()
+            )
+        })?;
+
+    if !is_quiet() {
+        match age {
+            None => eprintln!(
+                "Warning: the neuron file {} does not record when it was fetched, the stake of the \
+                 neuron may have changed.",
+                neuron_file.display()
+            ),
+            Some(age) if age > max_age_hours.saturating_mul(60 * 60) => eprintln!(
+                "Warning: the neuron file {} is {} hours old, the stake of the neuron may have \
+                 changed.",
+                neuron_file.display(),
+                age / (60 * 60)
+            ),
+            Some(_) => {}
+        }
+    }
+
+    let available = E8s::new(neuron.stake_e8s)
+        .checked_sub(E8s::from(DEFAULT_TRANSFER_FEE))
+        .ok_or_else(|| {
+            anyhow!(
+                "The stake of neuron {} does not cover the transaction fee",
+                neuron_id
+            )
+        })?;
+    match amount.map(E8s::from) {
+        Some(amount) if amount > available => bail!(
+            "Cannot disburse {} ICP, neuron {} has {} ICP after the transaction fee",
+            Icp::from(amount),
+            neuron_id,
+            Icp::from(available)
+        ),
+        Some(amount) => {
+            // A remainder below the minimum stake is usually unintended dust stuck on the neuron.
+            let remainder = available.get() - amount.get();
+            if remainder > 0 && remainder < MIN_STAKE_E8S && !is_quiet() {
+                eprintln!(
+                    "Warning: neuron {} will be left with {} ICP, less than the minimum stake of \
+                     {} ICP. Use --disburse-all to disburse the entire stake.",
+                    neuron_id,
+                    Icp::from(E8s::new(remainder)),
+                    Icp::from(E8s::new(MIN_STAKE_E8S))
+                );
+            }
+            Ok(Some(amount))
+        }
+        None => Ok(Some(available)),
+    }
+}
+
+// Returns the neurons voting: the managed neuron followed by the other ones given, without
+// duplicates.
+fn voting_neurons(neuron_id: u64, others: &[u64], file: Option<&Path>) -> AnyhowResult<Vec<u64>> {
+    let mut voters = vec![neuron_id];
+    voters.extend_from_slice(others);
+    if let Some(file) = file {
+        let text = std::fs::read_to_string(file)
+            .with_context(|| format!("Cannot read the neuron file {}", file.display()))?;
+        voters.extend(
+            parse_neuron_ids(&text)
+                .with_context(|| format!("Cannot parse the neuron file {}", file.display()))?,
+        );
+    }
+    let mut seen = std::collections::HashSet::new();
+    voters.retain(|id| seen.insert(*id));
+    Ok(voters)
+}
+
+// Parses the neuron ids of a neuron report, of a snapshot, or of plain text.
+fn parse_neuron_ids(text: &str) -> AnyhowResult<Vec<u64>> {
+    if let Ok(file) = serde_json::from_str::<NeuronFile>(text) {
+        let neurons = match file {
+            NeuronFile::Report(neurons) | NeuronFile::Snapshot { neurons, .. } => neurons,
+        };
+        return Ok(neurons.iter().map(|neuron| neuron.id).collect());
+    }
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|id| !id.is_empty())
+        .map(parse_neuron_id)
+        .collect()
+}
+
+#[test]
+fn test_parse_neuron_ids() {
+    assert_eq!(
+        parse_neuron_ids("# My neurons\n123, 456\n789 # the last one\n").unwrap(),
+        vec![123, 456, 789]
+    );
+    assert_eq!(
+        parse_neuron_ids(r#"[{"id": 123, "stake_e8s": 1}, {"id": 456, "stake_e8s": 2}]"#).unwrap(),
+        vec![123, 456]
+    );
+    assert_eq!(
+        parse_neuron_ids(r#"{"fetched_at_seconds": 1, "neurons": [{"id": 789, "stake_e8s": 3}]}"#)
+            .unwrap(),
+        vec![789]
+    );
+    assert!(parse_neuron_ids("123 abc").is_err());
+    assert_eq!(
+        voting_neurons(123, &[456, 123, 789], None).unwrap(),
+        vec![123, 456, 789]
+    );
+}
+
+// Expands the proposal ids, ranges and comma-separated lists of them, in order and without
+// duplicates, so that each proposal gets a single vote.
+fn parse_proposals(proposals: &[String]) -> AnyhowResult<Vec<u64>> {
+    let mut ids = Vec::new();
+    for proposal in proposals.iter().flat_map(|list| list.split(',')) {
+        let proposal = proposal.trim();
+        if proposal.contains('-') {
+            let (first, last) = get_range(proposal)?;
+            ids.extend(first..=last);
+        } else if let Ok(proposal) = proposal.parse::<u64>() {
+            ids.push(proposal);
+        } else {
+            return Err(anyhow!("Unable to parse proposal or range {:?}.", proposal));
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    Ok(ids)
+}
+
+#[test]
+fn test_parse_proposals() {
+    let parse = |args: &[&str]| {
+        parse_proposals(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    };
+    assert_eq!(parse(&["123,456"]).unwrap(), vec![123, 456]);
+    assert_eq!(parse(&["123, 456", "789"]).unwrap(), vec![123, 456, 789]);
+    assert_eq!(parse(&["10-2,20"]).unwrap(), vec![10, 11, 12, 20]);
+    assert_eq!(parse(&["11,10-2"]).unwrap(), vec![11, 10, 12]);
+    assert!(parse(&["123,"]).is_err());
+    assert!(parse(&["123,abc"]).is_err());
 }
 
 // Get the range first..last from a string of the form X-Y
@@ -451,3 +1253,97 @@ fn test_get_range() {
     assert_eq!(get_range("777-783").ok(), Some((777, 783)));
     assert_eq!(get_range("999-1001").ok(), Some((999, 1001)));
 }
+
+#[test]
+fn test_parse_dissolve_delay() {
+    assert_eq!(parse_dissolve_delay("3600").ok(), Some(3600));
+    assert_eq!(parse_dissolve_delay("3600s").ok(), Some(3600));
+    assert_eq!(parse_dissolve_delay("2h").ok(), Some(7200));
+    assert_eq!(
+        parse_dissolve_delay("180d").ok(),
+        Some(180 * ONE_DAY_SECONDS)
+    );
+    assert_eq!(parse_dissolve_delay("2w").ok(), Some(14 * ONE_DAY_SECONDS));
+    assert_eq!(parse_dissolve_delay("1y").ok(), Some(ONE_YEAR_SECONDS));
+    assert_eq!(parse_dissolve_delay("1_000").ok(), Some(1000));
+    assert_eq!(
+        parse_dissolve_delay("SIX_MONTHS").ok(),
+        Some(ONE_MONTH_SECONDS * 6)
+    );
+
+    assert!(parse_dissolve_delay("").is_err());
+    assert!(parse_dissolve_delay("d").is_err());
+    assert!(parse_dissolve_delay("10x").is_err());
+    assert!(parse_dissolve_delay("10dd").is_err());
+    assert!(parse_dissolve_delay("100000y").is_err());
+}
+
+#[test]
+fn test_parse_topic_following() {
+    assert_eq!(
+        parse_topic_following("4=123,456").unwrap(),
+        (4, vec![123, 456])
+    );
+    assert_eq!(parse_topic_following("0=").unwrap(), (0, vec![]));
+    assert_eq!(
+        parse_topic_following("SnsAndCommunityFund=123").unwrap(),
+        (14, vec![123])
+    );
+    assert!(parse_topic_following("4").is_err());
+    assert!(parse_topic_following("x=1").is_err());
+    assert!(parse_topic_following("4=1,y").is_err());
+}
+
+#[cfg(feature = "online")]
+#[test]
+fn test_voting_power() {
+    let year = u64::from(ONE_YEAR_SECONDS);
+    assert_eq!(voting_power(100_000_000, 0, 10 * year), 0);
+    assert_eq!(voting_power(100_000_000, 8 * year, 0), 200_000_000);
+    assert_eq!(voting_power(100_000_000, 10 * year, 4 * year), 250_000_000);
+    assert_eq!(voting_power(100_000_000, 4 * year, 2 * year), 168_750_000);
+}
+
+#[test]
+fn test_get_disburse_amount() {
+    use std::io::Write;
+
+    let mut neuron_file = tempfile::NamedTempFile::new().expect("Cannot create temp file");
+    neuron_file
+        .write_all(
+            br#"[{"id": 2313380519530470538, "stake_e8s": 100010000, "state": "Dissolved"}]"#,
+        )
+        .expect("Cannot write to temp file");
+    let amount = |amount: Option<&str>, id: u64| {
+        get_disburse_amount(
+            id,
+            amount.map(|amount| amount.parse().unwrap()),
+            Some(neuron_file.path()),
+            24,
+        )
+    };
+
+    assert_eq!(
+        amount(None, 2313380519530470538).unwrap(),
+        Some(E8s::new(100_000_000))
+    );
+    assert_eq!(
+        amount(Some("0.5"), 2313380519530470538).unwrap(),
+        Some(E8s::new(50_000_000))
+    );
+    assert!(amount(Some("1.00000001"), 2313380519530470538).is_err());
+    assert!(amount(None, 1).is_err());
+    assert_eq!(get_disburse_amount(1, None, None, 24).unwrap(), None);
+
+    let mut snapshot = tempfile::NamedTempFile::new().expect("Cannot create temp file");
+    snapshot
+        .write_all(
+            br#"{"fetched_at_seconds": 1672531200, "network": "mainnet",
+                 "neurons": [{"id": 1, "stake_e8s": 200010000, "state": "Dissolved"}]}"#,
+        )
+        .expect("Cannot write to temp file");
+    assert_eq!(
+        get_disburse_amount(1, None, Some(snapshot.path()), 24).unwrap(),
+        Some(E8s::new(200_000_000))
+    );
+}
@@ -1,16 +1,20 @@
 use crate::{
-    commands::{send::Memo, transfer},
+    commands::{neuron_manage::MIN_STAKE_E8S, transfer},
     lib::{
-        governance_canister_id,
+        governance_canister_id, is_quiet,
+        nns_types::{
+            icpts::{E8s, Icp},
+            Memo, Subaccount,
+        },
         signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
         AnyhowResult, AuthInfo,
     },
 };
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, Context};
 use candid::{CandidType, Encode, Principal};
 use clap::Parser;
 use ic_nns_constants::GOVERNANCE_CANISTER_ID;
-use ledger_canister::{AccountIdentifier, Subaccount};
+use ledger_canister::{AccountIdentifier, DEFAULT_TRANSFER_FEE};
 
 #[derive(CandidType)]
 pub struct ClaimOrRefreshNeuronFromAccount {
@@ -29,13 +33,28 @@ pub struct StakeOpts {
     #[clap(long, validator(neuron_name_validator))]
     name: Option<String>,
 
-    /// The nonce of the neuron.
-    #[clap(long, validator(neuron_name_validator), conflicts_with("name"))]
+    /// The nonce of the neuron, i.e. the memo of the staking transfer.
+    #[clap(
+        long,
+        alias("memo"),
+        validator(neuron_name_validator),
+        conflicts_with("name")
+    )]
     nonce: Option<u64>,
 
     /// Transaction fee, default is 10000 e8s.
     #[clap(long)]
     fee: Option<String>,
+
+    /// The minimum stake of a new neuron, which the amount is checked against. Defaults to the
+    /// minimum of the mainnet, 1 ICP; test networks may have another minimum.
+    #[clap(long)]
+    min_stake: Option<Icp>,
+
+    /// Tops up an existing neuron, whose stake already exceeds the minimum, so the amount is
+    /// not checked against it.
+    #[clap(long, conflicts_with("min-stake"))]
+    top_up: bool,
 }
 
 pub fn exec(auth: &AuthInfo, opts: StakeOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
@@ -45,16 +64,40 @@ pub fn exec(auth: &AuthInfo, opts: StakeOpts) -> AnyhowResult<Vec<IngressWithReq
         (_, Some(name)) => convert_name_to_nonce(name),
         _ => return Err(anyhow!("Either a nonce or a name should be specified")),
     };
-    let gov_subaccount = get_neuron_subaccount(&controller, nonce);
-    let account = AccountIdentifier::new(GOVERNANCE_CANISTER_ID.get(), Some(gov_subaccount));
+    let gov_subaccount = Subaccount::from_nonce(&controller, nonce);
+    let account = AccountIdentifier::new(GOVERNANCE_CANISTER_ID.get(), Some(gov_subaccount.into()));
+    if let (Some(amount), false) = (&opts.amount, opts.top_up) {
+        let fee = opts
+            .fee
+            .as_ref()
+            .map_or(Ok(E8s::from(DEFAULT_TRANSFER_FEE)), |fee| {
+                fee.parse::<Icp>()
+                    .map(E8s::from)
+                    .context("Cannot parse fee")
+            })?;
+        let min_stake = opts.min_stake.map_or(E8s::new(MIN_STAKE_E8S), E8s::from);
+        check_stake(amount.parse()?, min_stake, fee)?;
+    }
+    if opts.amount.is_some() && !is_quiet() {
+        eprint!(
+            "{}",
+            staking_preview(&controller, nonce, &gov_subaccount, &account)
+        );
+    }
     let mut messages = match opts.amount {
         Some(amount) => transfer::exec(
             auth,
             transfer::TransferOpts {
                 to: account.to_hex(),
-                amount,
+                amount: Some(amount),
                 fee: opts.fee,
+                from_subaccount: None,
                 memo: Some(nonce.to_string()),
+                idempotency_key: None,
+                audit_log: None,
+                // The subaccount of the neuron is not a recipient of the address book.
+                new_recipient: true,
+                no_prompt: false,
             },
         )?,
         _ => Vec::new(),
@@ -74,19 +117,40 @@ pub fn exec(auth: &AuthInfo, opts: StakeOpts) -> AnyhowResult<Vec<IngressWithReq
     Ok(messages)
 }
 
-// This function _must_ correspond to how the governance canister computes the
-// subaccount.
-fn get_neuron_subaccount(controller: &Principal, nonce: u64) -> Subaccount {
-    use openssl::sha::Sha256;
-    let mut data = Sha256::new();
-    data.update(&[0x0c]);
-    data.update(b"neuron-stake");
-    data.update(controller.as_slice());
-    data.update(&nonce.to_be_bytes());
-    Subaccount(data.finish())
+// A staking transfer below the minimum stake cannot be claimed as a neuron, and the ICP stays on
+// the subaccount of the neuron until it is topped up.
+fn check_stake(amount: Icp, min_stake: E8s, fee: E8s) -> AnyhowResult {
+    if E8s::from(amount) < min_stake {
+        bail!(
+            "Cannot stake {} ICP: a new neuron needs a stake of at least {} ICP, i.e. a transfer \
+             of {} ICP plus the fee of {} ICP. Use --top-up to add to an existing neuron.",
+            amount,
+            Icp::from(min_stake),
+            Icp::from(min_stake),
+            Icp::from(fee)
+        );
+    }
+    Ok(())
 }
 
-fn convert_name_to_nonce(name: &str) -> u64 {
+// Describes where the staking transfer goes, so that the derivation of the account can be checked
+// on another device before any ICP is sent.
+fn staking_preview(
+    controller: &Principal,
+    nonce: u64,
+    subaccount: &Subaccount,
+    account: &AccountIdentifier,
+) -> String {
+    format!(
+        "Staking account: {}\n\
+         Subaccount of the governance canister: {}\n\
+         Memo: {}\n\
+         Funds sent here can only be claimed by principal {}.\n",
+        account, subaccount, nonce, controller
+    )
+}
+
+pub fn convert_name_to_nonce(name: &str) -> u64 {
     let mut bytes = std::collections::VecDeque::from(name.as_bytes().to_vec());
     while bytes.len() < 8 {
         bytes.push_front(0)
@@ -96,10 +160,35 @@ fn convert_name_to_nonce(name: &str) -> u64 {
     u64::from_be_bytes(arr)
 }
 
-fn neuron_name_validator(name: &str) -> Result<(), String> {
+pub fn neuron_name_validator(name: &str) -> Result<(), String> {
     // Convert to bytes before checking the length to restrict it to ASCII only
     if name.as_bytes().len() > 8 {
         return Err("The neuron name must be 8 character or less".to_string());
     }
     Ok(())
 }
+
+#[test]
+fn test_check_stake() {
+    let fee = E8s::new(10_000);
+    let min_stake = E8s::new(MIN_STAKE_E8S);
+    assert!(check_stake("1".parse().unwrap(), min_stake, fee).is_ok());
+    assert!(check_stake("0.99999999".parse().unwrap(), min_stake, fee).is_err());
+    assert!(check_stake("0.5".parse().unwrap(), E8s::new(10_000_000), fee).is_ok());
+}
+
+#[test]
+fn test_staking_preview() {
+    let controller =
+        Principal::from_text("fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae")
+            .unwrap();
+    let subaccount = Subaccount::from_nonce(&controller, 777);
+    let account = AccountIdentifier::new(GOVERNANCE_CANISTER_ID.get(), Some(subaccount.into()));
+    let preview = staking_preview(&controller, 777, &subaccount, &account);
+    assert!(preview.starts_with(&format!("Staking account: {}\n", account.to_hex())));
+    assert!(preview.contains("\nMemo: 777\n"));
+    assert!(preview.ends_with(
+        "Funds sent here can only be claimed by principal \
+         fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae.\n"
+    ));
+}
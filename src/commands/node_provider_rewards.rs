@@ -0,0 +1,43 @@
+use crate::{
+    commands::send::{submit_unsigned_ingress, QueryOpts},
+    lib::{governance_canister_id, AnyhowResult},
+};
+use candid::Encode;
+use clap::Parser;
+
+#[derive(Parser)]
+pub struct NodeProviderRewardsOpts {
+    /// Computes the rewards for the current month instead of returning the most
+    /// recent distribution.
+    #[clap(long, conflicts_with("fee-free"))]
+    monthly: bool,
+
+    /// Skips confirmation and sends the message directly.
+    #[clap(long)]
+    yes: bool,
+
+    /// Will display the query, but not send it.
+    #[clap(long)]
+    dry_run: bool,
+
+    #[clap(flatten)]
+    query: QueryOpts,
+}
+
+pub async fn exec(opts: NodeProviderRewardsOpts, fetch_root_key: bool) -> AnyhowResult {
+    let method_name = if opts.monthly {
+        "get_monthly_node_provider_rewards"
+    } else {
+        "get_most_recent_monthly_node_provider_rewards"
+    };
+    submit_unsigned_ingress(
+        governance_canister_id(),
+        method_name,
+        Encode!()?,
+        opts.yes,
+        opts.dry_run,
+        opts.query.fee_free,
+        fetch_root_key,
+    )
+    .await
+}
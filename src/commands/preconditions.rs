@@ -0,0 +1,83 @@
+//! Checks of the preconditions attached to messages, e.g. by `neuron-manage --with-preconditions`,
+//! which `send` makes right before submitting each message.
+
+use crate::commands::get_proposal_info::proposal_status_name;
+use crate::lib::{
+    get_agent, governance_canister_id, signing::Precondition, AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, Context};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use ic_agent::Agent;
+use ic_nns_governance::pb::v1::GovernanceError;
+
+// Only the fields which are checked are decoded.
+#[derive(CandidType, Deserialize)]
+struct ProposalInfo {
+    status: i32,
+}
+
+#[derive(CandidType, Deserialize)]
+struct NeuronInfo {
+    state: i32,
+}
+
+#[derive(CandidType, Deserialize)]
+enum NeuronInfoResult {
+    Ok(NeuronInfo),
+    Err(GovernanceError),
+}
+
+const PROPOSAL_STATUS_OPEN: i32 = 1;
+const NEURON_STATE_DISSOLVED: i32 = 3;
+
+/// Returns why the first of the preconditions which fails does, or none if they all hold.
+pub async fn failed_precondition(preconditions: &[Precondition]) -> AnyhowResult<Option<String>> {
+    if preconditions.is_empty() {
+        return Ok(None);
+    }
+    let agent = get_agent(&AuthInfo::NoAuth)?;
+    for precondition in preconditions {
+        if let Some(reason) = check(&agent, precondition)
+            .await
+            .with_context(|| format!("Cannot check that {}", precondition))?
+        {
+            return Ok(Some(reason));
+        }
+    }
+    Ok(None)
+}
+
+async fn check(agent: &Agent, precondition: &Precondition) -> AnyhowResult<Option<String>> {
+    Ok(match precondition {
+        Precondition::ProposalOpen { proposal_id } => {
+            let reply = query(agent, "get_proposal_info", Encode!(proposal_id)?).await?;
+            match Decode!(&reply, Option<ProposalInfo>)? {
+                None => Some(format!("proposal {} does not exist", proposal_id)),
+                Some(info) if info.status != PROPOSAL_STATUS_OPEN => Some(format!(
+                    "proposal {} is not open anymore ({})",
+                    proposal_id,
+                    proposal_status_name(info.status)
+                )),
+                Some(_) => None,
+            }
+        }
+        Precondition::NeuronDissolved { neuron_id } => {
+            let reply = query(agent, "get_neuron_info", Encode!(neuron_id)?).await?;
+            match Decode!(&reply, NeuronInfoResult)? {
+                NeuronInfoResult::Ok(info) if info.state != NEURON_STATE_DISSOLVED => {
+                    Some(format!("neuron {} is not dissolved", neuron_id))
+                }
+                NeuronInfoResult::Ok(_) => None,
+                NeuronInfoResult::Err(err) => return Err(anyhow!(err.error_message)),
+            }
+        }
+    })
+}
+
+async fn query(agent: &Agent, method_name: &str, args: Vec<u8>) -> AnyhowResult<Vec<u8>> {
+    Ok(agent
+        .query(&governance_canister_id(), method_name)
+        .with_arg(args)
+        .call()
+        .await?)
+}
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use crate::lib::{read_from_file, AnyhowResult};
 use clap::Parser;
+#[cfg(feature = "qr")]
 use qrcodegen::{QrCode, QrCodeEcc};
 
 #[derive(Parser)]
@@ -19,16 +20,17 @@ pub struct QRCodeOpts {
 pub fn exec(opts: QRCodeOpts) -> AnyhowResult {
     if let Some(file) = opts.file {
         let data = read_from_file(&file)?;
-        print_qr(&data);
+        print_qr(&data)?;
     }
     if let Some(string) = opts.string {
-        print_qr(&string);
+        print_qr(&string)?;
     }
     Ok(())
 }
 
 // Prints the given QrCode object to the console.
-pub fn print_qr(text: &str) {
+#[cfg(feature = "qr")]
+pub fn print_qr(text: &str) -> AnyhowResult {
     let errcorlvl: QrCodeEcc = QrCodeEcc::Medium; // Error correction level
 
     // Make and print the QR Code symbol
@@ -48,4 +50,10 @@ pub fn print_qr(text: &str) {
         println!();
     }
     println!();
+    Ok(())
+}
+
+#[cfg(not(feature = "qr"))]
+pub fn print_qr(_text: &str) -> AnyhowResult {
+    anyhow::bail!("This build of quill cannot print QR codes, it lacks the `qr` feature.")
 }
@@ -0,0 +1,107 @@
+use crate::lib::{
+    governance_canister_id,
+    nns_types::neuron_id::parse_neuron_id,
+    proposal::check_proposal_text,
+    signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::ensure;
+use candid::Encode;
+use clap::Parser;
+use ic_nns_common::pb::v1::NeuronId;
+use ic_nns_governance::pb::v1::{
+    manage_neuron::Command, proposal::Action, KnownNeuron, KnownNeuronData, ManageNeuron, Proposal,
+};
+
+// The limits enforced by governance on known neuron data.
+const MAX_NAME_BYTES: usize = 200;
+const MAX_DESCRIPTION_BYTES: usize = 3000;
+
+/// Signs a proposal to register a neuron as a known neuron, whose name is shown to the
+/// neurons which may follow it.
+#[derive(Parser)]
+pub struct RegisterKnownNeuronOpts {
+    /// The id of the neuron making the proposal.
+    #[clap(parse(try_from_str = parse_neuron_id))]
+    proposer_neuron_id: u64,
+
+    /// The id of the neuron to register, the proposing neuron by default.
+    #[clap(long, parse(try_from_str = parse_neuron_id))]
+    known_neuron_id: Option<u64>,
+
+    /// The name of the known neuron.
+    #[clap(long)]
+    name: String,
+
+    /// A description of the known neuron.
+    #[clap(long)]
+    description: Option<String>,
+
+    /// The title of the proposal.
+    #[clap(long)]
+    title: Option<String>,
+
+    /// The summary of the proposal.
+    #[clap(long)]
+    summary: Option<String>,
+
+    /// A URL with more information on the proposal.
+    #[clap(long, default_value = "")]
+    url: String,
+}
+
+pub fn exec(
+    auth: &AuthInfo,
+    opts: RegisterKnownNeuronOpts,
+) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    ensure!(
+        !opts.name.is_empty() && opts.name.len() <= MAX_NAME_BYTES,
+        "The name must have between 1 and {} bytes",
+        MAX_NAME_BYTES
+    );
+    ensure!(
+        opts.description
+            .as_ref()
+            .map_or(true, |description| description.len()
+                <= MAX_DESCRIPTION_BYTES),
+        "The description must have at most {} bytes",
+        MAX_DESCRIPTION_BYTES
+    );
+    let known_neuron_id = opts.known_neuron_id.unwrap_or(opts.proposer_neuron_id);
+    let title = opts
+        .title
+        .unwrap_or_else(|| format!("Register Known Neuron: {}", opts.name));
+    let summary = opts.summary.unwrap_or_else(|| {
+        format!(
+            "Registers neuron {} as a known neuron named {}.",
+            known_neuron_id, opts.name
+        )
+    });
+    check_proposal_text(&title, &summary, &opts.url)?;
+    let args = Encode!(&ManageNeuron {
+        id: Some(NeuronId {
+            id: opts.proposer_neuron_id
+        }),
+        command: Some(Command::MakeProposal(Box::new(Proposal {
+            title: Some(title),
+            summary,
+            url: opts.url,
+            action: Some(Action::RegisterKnownNeuron(KnownNeuron {
+                id: Some(NeuronId {
+                    id: known_neuron_id
+                }),
+                known_neuron_data: Some(KnownNeuronData {
+                    name: opts.name,
+                    description: opts.description,
+                }),
+            })),
+        }))),
+        neuron_id_or_subaccount: None,
+    })?;
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        governance_canister_id(),
+        "manage_neuron",
+        args,
+    )?])
+}
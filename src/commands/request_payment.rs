@@ -0,0 +1,39 @@
+use crate::{
+    commands::qrcode,
+    lib::{
+        nns_types::{account_id::parse_account_id, icpts::Icp, payment_request::PaymentRequest},
+        AnyhowResult,
+    },
+};
+use clap::Parser;
+
+/// Prints a payment request URI, which `transfer` and mobile wallets accept.
+#[derive(Parser)]
+pub struct RequestPaymentOpts {
+    /// The account to be paid.
+    #[clap(long)]
+    to: String,
+
+    /// Amount of ICPs requested (with up to 8 decimal digits after comma).
+    #[clap(long)]
+    amount: Option<Icp>,
+
+    /// Reference number of the payment.
+    #[clap(long)]
+    memo: Option<u64>,
+}
+
+pub fn exec(opts: RequestPaymentOpts, qr: bool) -> AnyhowResult {
+    let uri = PaymentRequest {
+        to: parse_account_id(&opts.to)?,
+        amount: opts.amount,
+        memo: opts.memo,
+    }
+    .to_uri();
+    if qr {
+        qrcode::print_qr(&uri)?;
+    } else {
+        println!("{}", uri);
+    }
+    Ok(())
+}
@@ -1,21 +1,67 @@
-use crate::lib::get_ic_url;
-use crate::lib::{get_agent, get_idl_string, signing::RequestStatus, AnyhowResult, AuthInfo};
-use anyhow::{anyhow, Context};
+use crate::lib::get_http_transport;
+use crate::lib::{
+    get_agent, is_quiet,
+    signing::{decode_content, RequestStatus},
+    timestamp::{format_timestamp_seconds, now_seconds},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, bail, Context};
 use candid::Principal;
 use ic_agent::agent::{ReplicaV2Transport, Replied, RequestStatusResponse};
 use ic_agent::AgentError::MessageError;
 use ic_agent::{AgentError, RequestId};
+use serde::{Deserialize, Serialize};
 use std::future::Future;
 use std::pin::Pin;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+/// How long `--watch` keeps polling a request which is not done; this matches the
+/// ingress expiry of the messages quill signs.
+const WATCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// The certified response to a request status query, from which anyone can check the reply
+/// against the IC root key.
+#[derive(Serialize, Deserialize)]
+pub struct Receipt {
+    pub canister_id: String,
+    pub request_id: String,
+    /// The method called, used to decode the reply.
+    #[serde(default)]
+    pub method_name: Option<String>,
+    /// The CBOR-encoded certificate, with the signed state tree (hex-encoded).
+    pub certificate: String,
+}
+
+/// What is printed while the status of a request is polled.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Prints that the request is being processed until it is done.
+    Processing,
+    /// Prints every status transition with a timestamp, and gives up after the ingress expiry.
+    Watch,
+    /// Prints nothing, e.g. when the reply is printed as CSV.
+    Silent,
+}
+
+/// Polls the request status until the call is done and returns the raw candid reply.
 pub async fn submit(
     req: &RequestStatus,
-    method_name: Option<String>,
     fetch_root_key: bool,
-) -> AnyhowResult<String> {
-    let canister_id = Principal::from_text(&req.canister_id).expect("Couldn't parse canister id");
+    progress: Progress,
+) -> AnyhowResult<Vec<u8>> {
+    Ok(submit_certified(req, fetch_root_key, progress).await?.0)
+}
+
+/// Like `submit`, but also returns the receipt made of the certificate which contained the reply.
+pub async fn submit_certified(
+    req: &RequestStatus,
+    fetch_root_key: bool,
+    progress: Progress,
+) -> AnyhowResult<(Vec<u8>, Receipt)> {
+    let canister_id =
+        Principal::from_text(&req.canister_id).context("Invalid argument: canister_id")?;
+    decode_content(&req.content).context("Invalid request status content")?;
     let request_id =
         RequestId::from_str(&req.request_id).context("Invalid argument: request_id")?;
     let mut agent = get_agent(&AuthInfo::NoAuth)?;
@@ -23,19 +69,27 @@ pub async fn submit(
     if fetch_root_key {
         agent.fetch_root_key().await?;
     }
+    let last_response = Arc::new(Mutex::new(None));
     agent.set_transport(ProxySignReplicaV2Transport {
         req: req.clone(),
-        http_transport: Arc::new(
-            ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport::create(get_ic_url())
-                .context("Failed to create an agent")?,
-        ),
+        http_transport: Arc::new(get_http_transport().context("Failed to create an agent")?),
+        last_response: last_response.clone(),
     });
+    let started = std::time::Instant::now();
+    let mut last_status = None;
     let Replied::CallReplied(blob) = async {
         loop {
-            match agent
+            let response = agent
                 .request_status_raw(&request_id, canister_id, false)
-                .await?
-            {
+                .await?;
+            if progress == Progress::Watch {
+                let status = status_name(&response);
+                if last_status != Some(status) {
+                    println!("{}  {}", format_timestamp_seconds(now_seconds()?), status);
+                    last_status = Some(status);
+                }
+            }
+            match response {
                 RequestStatusResponse::Replied { reply } => return Ok(reply),
                 RequestStatusResponse::Rejected {
                     reject_code,
@@ -49,7 +103,16 @@ pub async fn submit(
                 RequestStatusResponse::Unknown
                 | RequestStatusResponse::Received
                 | RequestStatusResponse::Processing => {
-                    println!("The request is being processed...");
+                    if progress == Progress::Processing {
+                        if !is_quiet() {
+                            println!("The request is being processed...");
+                        }
+                    } else if progress == Progress::Watch && started.elapsed() > WATCH_TIMEOUT {
+                        bail!(
+                            "Timed out after {} seconds waiting for the request to complete.",
+                            WATCH_TIMEOUT.as_secs()
+                        );
+                    }
                 }
                 RequestStatusResponse::Done => {
                     return Err(anyhow!(AgentError::RequestStatusDoneNoReply(String::from(
@@ -62,13 +125,44 @@ pub async fn submit(
         }
     }
     .await?;
-    get_idl_string(&blob, canister_id, &method_name.unwrap_or_default(), "rets")
-        .context("Invalid IDL blob.")
+    // The agent only returns the reply once it verified the certificate of the last response.
+    let response = last_response
+        .lock()
+        .unwrap()
+        .take()
+        .context("No certificate was received")?;
+    let response: ReadStateResponse =
+        serde_cbor::from_slice(&response).context("Invalid read_state response")?;
+    let receipt = Receipt {
+        canister_id: req.canister_id.clone(),
+        request_id: req.request_id.clone(),
+        method_name: None,
+        certificate: hex::encode(response.certificate),
+    };
+    Ok((blob, receipt))
+}
+
+#[derive(Deserialize)]
+struct ReadStateResponse {
+    #[serde(with = "serde_bytes")]
+    certificate: Vec<u8>,
+}
+
+fn status_name(response: &RequestStatusResponse) -> &'static str {
+    match response {
+        RequestStatusResponse::Unknown => "unknown",
+        RequestStatusResponse::Received => "received",
+        RequestStatusResponse::Processing => "processing",
+        RequestStatusResponse::Replied { .. } => "replied",
+        RequestStatusResponse::Rejected { .. } => "rejected",
+        RequestStatusResponse::Done => "done",
+    }
 }
 
 pub(crate) struct ProxySignReplicaV2Transport {
     req: RequestStatus,
     http_transport: Arc<dyn 'static + ReplicaV2Transport + Send + Sync>,
+    last_response: Arc<Mutex<Option<Vec<u8>>>>,
 }
 
 impl ReplicaV2Transport for ProxySignReplicaV2Transport {
@@ -86,10 +180,12 @@ impl ReplicaV2Transport for ProxySignReplicaV2Transport {
                     err
                 ))
             })?;
-            transport
+            let response = transport
                 .http_transport
                 .read_state(canister_id, envelope)
-                .await
+                .await?;
+            *transport.last_response.lock().unwrap() = Some(response.clone());
+            Ok(response)
         }
 
         Box::pin(run(self))
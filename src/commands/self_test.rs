@@ -0,0 +1,127 @@
+use crate::{
+    commands::public::get_ids,
+    lib::{
+        ledger_canister_id, nns_types::Subaccount, signing::sign_without_policy, AnyhowResult,
+        AuthInfo, CANDID_SHA256SUMS, EMBEDDED_CANDID,
+    },
+};
+use anyhow::{anyhow, bail, ensure};
+use candid::{
+    parser::typing::{check_prog, TypeEnv},
+    Decode, Encode, IDLProg, Principal,
+};
+use ic_nns_constants::GOVERNANCE_CANISTER_ID;
+use ledger_canister::AccountIdentifier;
+use openssl::sha::sha256;
+
+// The identity used by the tests, and the ids derived from it.
+const TEST_PEM: &str = include_str!("../../e2e/assets/identity.pem");
+const TEST_PRINCIPAL: &str = "fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae";
+const TEST_ACCOUNT: &str = "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752";
+// The account staking a neuron with nonce 777 for the test identity.
+const TEST_NEURON_ACCOUNT: &str =
+    "a0ea9002c2bc3d442050f4431f3732c91dbec13eff79f414b15255d60c4a324c";
+
+/// Verifies the embedded candid interfaces and the signing routines of this
+/// binary against known test vectors.
+pub fn exec() -> AnyhowResult {
+    let mut failures = 0;
+    let mut check = |name: &str, result: AnyhowResult| match result {
+        Ok(()) => println!("ok      {}", name),
+        Err(err) => {
+            failures += 1;
+            println!("FAILED  {}: {:#}", name, err)
+        }
+    };
+
+    for (file, bytes) in EMBEDDED_CANDID {
+        check(&format!("candid {}", file), check_candid(file, bytes));
+    }
+    check("identity", check_identity());
+    check("neuron staking account", check_neuron_account());
+    check("signing", check_signing());
+
+    if failures > 0 {
+        bail!("{} self-test check(s) failed", failures);
+    }
+    println!("All self-test checks passed.");
+    Ok(())
+}
+
+fn check_candid(file: &str, bytes: &[u8]) -> AnyhowResult {
+    let expected = CANDID_SHA256SUMS
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim_start().trim_start_matches('*') == file)
+        .map(|(hash, _)| hash)
+        .ok_or_else(|| anyhow!("no hash is pinned in candid/SHA256SUMS"))?;
+    let hash = hex::encode(sha256(bytes));
+    ensure!(
+        hash == expected,
+        "hash {} does not match {} pinned in candid/SHA256SUMS",
+        hash,
+        expected
+    );
+    let idl = String::from_utf8(bytes.to_vec())?;
+    let ast = candid::pretty_parse::<IDLProg>(file, &idl).map_err(|err| anyhow!("{}", err))?;
+    check_prog(&mut TypeEnv::new(), &ast)?;
+    Ok(())
+}
+
+fn check_identity() -> AnyhowResult {
+    let (principal, account) = get_ids(&AuthInfo::PemFile(TEST_PEM.to_string()))?;
+    ensure!(
+        principal.to_text() == TEST_PRINCIPAL,
+        "unexpected principal {}",
+        principal
+    );
+    ensure!(
+        account.to_hex() == TEST_ACCOUNT,
+        "unexpected account {}",
+        account
+    );
+    Ok(())
+}
+
+fn check_neuron_account() -> AnyhowResult {
+    let principal = Principal::from_text(TEST_PRINCIPAL)?;
+    let account = AccountIdentifier::new(
+        GOVERNANCE_CANISTER_ID.get(),
+        Some(Subaccount::from_nonce(&principal, 777).into()),
+    );
+    ensure!(
+        account.to_hex() == TEST_NEURON_ACCOUNT,
+        "unexpected account {}",
+        account
+    );
+    Ok(())
+}
+
+fn check_signing() -> AnyhowResult {
+    let auth = AuthInfo::PemFile(TEST_PEM.to_string());
+    let args = Encode!(&TEST_ACCOUNT)?;
+    // The test vector is signed regardless of the key usage policy of the machine, and not
+    // recorded in its spending.
+    let message = sign_without_policy(&auth, ledger_canister_id(), "self_test", args)?;
+    let (sender, canister_id, method_name, arg) = message.message.parse_raw()?;
+    ensure!(
+        sender.to_text() == TEST_PRINCIPAL,
+        "unexpected sender {}",
+        sender
+    );
+    ensure!(
+        canister_id == ledger_canister_id() && method_name == "self_test",
+        "unexpected recipient {}.{}",
+        canister_id,
+        method_name
+    );
+    ensure!(
+        Decode!(&arg, String)? == TEST_ACCOUNT,
+        "the argument does not round-trip"
+    );
+    ensure!(
+        message.message.request_id.is_some(),
+        "the message has no request id"
+    );
+    Ok(())
+}
@@ -1,147 +1,839 @@
-use crate::commands::request_status;
+use crate::commands::request_status::Progress;
+use crate::commands::{
+    get_full_neuron, list_neurons, neuron_manage, preconditions, request_status, sns, summary,
+    transfer, voting_history,
+};
 use crate::lib::{
-    get_ic_url, parse_query_response, read_from_file,
-    signing::{Ingress, IngressWithRequestId},
-    AnyhowResult, AuthInfo,
+    audit::{self, AuditEntry},
+    config::load_pinned_subnets,
+    get_agent, get_allowed_canister_ids, get_http_transport, get_ic_url, get_idl_string,
+    get_network, governance_canister_id, is_quiet, ledger_canister_id,
+    metrics::Metrics,
+    notify,
+    pacing::{is_rate_limited, Pacer, MAX_RATE_LIMITED_RETRIES},
+    parse_query_reply, parse_query_response,
+    queue::{MessageState, Queue},
+    read_from_file, read_from_file_limited, serial,
+    signing::{
+        check_ingress_size, decode_content, max_ingress_bytes, parse_bundle, Bundle, Ingress,
+        IngressWithRequestId,
+    },
+    timestamp::now_seconds,
+    AnyhowResult, ArgPart, AuthInfo, OutputFormat, QueryReply, MAX_MESSAGE_FILE_BYTES,
 };
-use anyhow::{anyhow, Context};
-use candid::{CandidType, Principal};
-use clap::Parser;
+use anyhow::{bail, Context};
+use candid::{Decode, Principal};
+use clap::{Args, Parser};
 use ic_agent::agent::ReplicaV2Transport;
-use ic_agent::{agent::http_transport::ReqwestHttpReplicaV2Transport, RequestId};
-use ledger_canister::{Subaccount, Tokens};
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use ic_agent::{AgentError, RequestId};
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Instant;
 
-#[derive(
-    Serialize,
-    Deserialize,
-    CandidType,
-    Clone,
-    Copy,
-    Hash,
-    Debug,
-    Default,
-    PartialEq,
-    Eq,
-    PartialOrd,
-    Ord,
-)]
-pub struct Memo(pub u64);
-
-#[derive(CandidType)]
-pub struct TimeStamp {
-    pub timestamp_nanos: u64,
-}
-
-#[derive(CandidType)]
-pub struct SendArgs {
-    pub memo: Memo,
-    pub amount: Tokens,
-    pub fee: Tokens,
-    pub from_subaccount: Option<Subaccount>,
-    pub to: String,
-    pub created_at_time: Option<TimeStamp>,
-}
+/// The id of the NNS subnet, whose key is the root key of the IC.
+const NNS_SUBNET_ID: &str = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe";
 
 /// Sends a signed message or a set of messages.
 #[derive(Parser)]
 pub struct SendOpts {
     /// Path to the signed message
-    file_name: PathBuf,
+    #[clap(
+        required_unless_present_any(&["from-serial", "resume-from"]),
+        conflicts_with("from-serial")
+    )]
+    file_name: Option<PathBuf>,
+
+    /// Waits for the signed messages on this serial device, as sent by a signing command with
+    /// --to-serial.
+    #[clap(long)]
+    from_serial: Option<PathBuf>,
+
+    /// Records the state of every update call (pending, submitted, replied or rejected) and a
+    /// copy of the bundle in this directory, so that the submission can be resumed with
+    /// --resume-from after a crash or a reboot.
+    #[clap(long, conflicts_with("dry-run"))]
+    state_dir: Option<PathBuf>,
+
+    /// Resumes the submission recorded in this directory by --state-dir, without the original
+    /// bundle. The calls whose outcome is known are not submitted again.
+    #[clap(long, conflicts_with_all(&["file-name", "from-serial", "state-dir"]))]
+    resume_from: Option<PathBuf>,
+
+    #[clap(skip)]
+    queue: Option<Queue>,
 
     /// Will display the signed message, but not send it.
     #[clap(long)]
     dry_run: bool,
 
-    /// Skips confirmation and sends the message directly.
+    /// Resolves the subnet of the canisters targeted by the messages from a certificate of the
+    /// IC and prints it, and refuses to submit the messages if it differs from the subnet
+    /// pinned in `subnets.toml` in the configuration directory.
+    #[clap(long)]
+    high_assurance: bool,
+
+    /// Refuses to send the messages unless this Markdown summary, written by the signing command
+    /// with --review-summary, is the one of the bundle.
+    #[clap(long)]
+    review_summary: Option<PathBuf>,
+
+    /// Ignores fields of the signed messages which this version of quill does not know,
+    /// instead of refusing the bundle.
+    #[clap(long)]
+    allow_unknown_fields: bool,
+
+    /// Skips confirmation and sends the message directly. Required to send update calls
+    /// with --quiet, which fails instead of asking for confirmation.
     #[clap(long)]
     yes: bool,
+
+    /// Only send messages targeting this canister (can be repeated). Overrides
+    /// the QUILL_ALLOWED_CANISTERS environment variable.
+    #[clap(long = "allowed-canister", multiple_occurrences(true))]
+    allowed_canisters: Vec<Principal>,
+
+    /// Appends a record of the submitted bundle to this file.
+    #[clap(long)]
+    audit_log: Option<PathBuf>,
+
+    /// Counter-signs the bundle hash with this operator key (not used for IC
+    /// calls); the signature is recorded in the audit log.
+    #[clap(long, requires("audit-log"))]
+    operator_pem_file: Option<PathBuf>,
+
+    /// Saves the raw reply and its decoded form of every update call to this directory, in
+    /// files named after the request ids.
+    #[clap(long)]
+    save_responses: Option<PathBuf>,
+
+    /// Saves the certificate containing the reply of every update call to this directory, in
+    /// files named `<request id>.cert.json`. The certificates can be checked by anyone against
+    /// the IC root key.
+    #[clap(long)]
+    save_certificates: Option<PathBuf>,
+
+    /// Prints every status transition of the submitted requests with a timestamp, and gives
+    /// up if a request is not done before its expiry.
+    #[clap(long)]
+    watch: bool,
+
+    /// Prints the reply to a `list_neurons` call as a normalized neuron report in the given
+    /// format (csv or json).
+    #[clap(long, arg_enum)]
+    neuron_report: Option<list_neurons::ReportFormat>,
+
+    /// Prints the followees of the neurons in the reply to a `list_neurons` call as a table of
+    /// topics by neurons, with the names of known neurons.
+    #[clap(long, conflicts_with("neuron-report"))]
+    following_report: bool,
+
+    /// On a dry run, fetches the neurons disbursed, split or merged by the messages and shows
+    /// the change of their voting power and the age bonus lost. For SNS maturity disbursements,
+    /// fetches the current maturity modulation and estimates the maturity disbursed.
+    #[clap(long, requires("dry-run"))]
+    stake_impact: bool,
+
+    /// Submits at most this many messages per second, e.g. 0.5 for one message every two
+    /// seconds. Submissions also slow down when the boundary node replies that its rate limit
+    /// is exceeded.
+    #[clap(long)]
+    messages_per_second: Option<f64>,
+
+    #[clap(skip)]
+    pacer: Pacer,
+
+    /// Writes the counts of the messages submitted, replied, rejected and retried, and the
+    /// histogram of the durations of the calls, to this file in the Prometheus text format. The
+    /// file is replaced after every call, so that long submissions can be monitored.
+    #[clap(long)]
+    metrics_file: Option<PathBuf>,
+
+    #[clap(skip)]
+    metrics: Metrics,
+
+    /// Runs this shell command for every call whose outcome is known, with the outcome as JSON
+    /// (request id, canister, method, status, and the reply or the rejection) on its standard
+    /// input.
+    #[clap(long)]
+    on_complete: Option<String>,
+
+    /// Posts the outcome of every call whose outcome is known as JSON to this URL.
+    #[clap(long)]
+    webhook: Option<String>,
+
+    /// The format of the replies: hex ("raw"), compact candid ("idl"), pretty-printed candid
+    /// ("pp") or JSON ("json").
+    #[clap(long, arg_enum, default_value = "pp")]
+    output_type: OutputFormat,
 }
 
-pub async fn exec(opts: SendOpts, fetch_root_key: bool) -> AnyhowResult {
-    let json = read_from_file(&opts.file_name)?;
-    if let Ok(val) = serde_json::from_str::<Ingress>(&json) {
-        send(&val, &opts).await?;
-    } else if let Ok(vals) = serde_json::from_str::<Vec<Ingress>>(&json) {
-        for msg in vals {
-            send(&msg, &opts).await?;
+pub async fn exec(mut opts: SendOpts, fetch_root_key: bool) -> AnyhowResult {
+    let mut queue = None;
+    let json = match (&opts.resume_from, &opts.from_serial, &opts.file_name) {
+        (Some(dir), _, _) => {
+            let (resumed, json) = Queue::open(dir)?;
+            if !is_quiet() {
+                let counts: Vec<_> = resumed
+                    .counts()
+                    .into_iter()
+                    .map(|(state, count)| format!("{} {}", count, state))
+                    .collect();
+                eprintln!("Resuming the submission: {}.", counts.join(", "));
+            }
+            queue = Some(resumed);
+            json
         }
-    } else if let Ok(vals) = serde_json::from_str::<Vec<IngressWithRequestId>>(&json) {
-        for tx in vals {
-            submit_ingress_and_check_status(&tx, &opts, fetch_root_key).await?;
+        (None, Some(device), _) => {
+            String::from_utf8(serial::receive(device, MAX_MESSAGE_FILE_BYTES)?)
+                .context("The received messages are not valid UTF-8")?
+        }
+        (None, None, Some(file_name)) => read_from_file_limited(file_name, MAX_MESSAGE_FILE_BYTES)?,
+        (None, None, None) => {
+            bail!("Either a file name, --from-serial or --resume-from is required")
+        }
+    };
+    let bundle_hash = audit::bundle_hash(json.as_bytes());
+    if let Some(path) = &opts.review_summary {
+        let summary = read_from_file(path).context("Cannot read the review summary")?;
+        summary::verify(&summary, &bundle_hash)?;
+    }
+    // Counter-sign before submitting anything so that an unusable operator key
+    // does not leave an unrecorded submission behind.
+    let operator = match &opts.operator_pem_file {
+        Some(path) => {
+            let pem = read_from_file(path).context("Cannot read the operator PEM file")?;
+            Some(audit::counter_sign(&AuthInfo::PemFile(pem), &bundle_hash)?)
+        }
+        None => None,
+    };
+    let bundle = parse_bundle(&json, opts.allow_unknown_fields)?;
+    if !opts.dry_run {
+        check_network(&bundle, fetch_root_key)?;
+    }
+    if opts.high_assurance {
+        check_subnets(&bundle, fetch_root_key).await?;
+    }
+    if let (Some(dir), false) = (&opts.state_dir, opts.dry_run) {
+        let request_ids = bundle
+            .ingress_messages()
+            .into_iter()
+            .filter_map(|msg| msg.request_id.clone());
+        queue = Some(Queue::create(dir, &json, request_ids)?);
+    }
+    opts.queue = queue;
+    let recipients = transfer_recipients(&bundle);
+    let mut request_ids = Vec::new();
+    let result = send_bundle(bundle, &opts, fetch_root_key, &mut request_ids).await;
+    // The messages submitted before a failure are recorded too, with the error.
+    if let (Some(path), false) = (&opts.audit_log, opts.dry_run) {
+        let timestamp_seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        audit::append(
+            path,
+            &AuditEntry {
+                timestamp_seconds,
+                bundle_sha256: hex::encode(bundle_hash),
+                request_ids,
+                recipients,
+                operator,
+                error: result.as_ref().err().map(|err| format!("{:#}", err)),
+            },
+        )?;
+    }
+    result
+}
+
+// Sends the messages of the bundle in order, and adds the request id of every message to
+// `request_ids` before it is submitted, so that they are known if a later message fails.
+async fn send_bundle(
+    bundle: Bundle,
+    opts: &SendOpts,
+    fetch_root_key: bool,
+    request_ids: &mut Vec<String>,
+) -> AnyhowResult {
+    match bundle {
+        Bundle::Ingress(val) => {
+            request_ids.extend(val.request_id.clone());
+            if !already_done(&val, opts) {
+                send(&val, opts).await?;
+            }
+        }
+        Bundle::Messages(vals) => {
+            let args: Vec<_> = vals.par_iter().map(decode_arguments).collect();
+            for (msg, args) in vals.iter().zip(args) {
+                request_ids.extend(msg.request_id.clone());
+                if !already_done(msg, opts) {
+                    send_decoded(msg, args, opts).await?;
+                }
+            }
+        }
+        Bundle::WithRequestStatus(vals) => {
+            let args: Vec<_> = vals
+                .par_iter()
+                .map(|tx| decode_arguments(&tx.ingress))
+                .collect();
+            for (tx, args) in vals.iter().zip(args) {
+                request_ids.extend(tx.ingress.request_id.clone());
+                if !already_done(&tx.ingress, opts) {
+                    submit_ingress_and_check_status(tx, args, opts, fetch_root_key).await?;
+                }
+            }
         }
-    } else {
-        return Err(anyhow!("Invalid JSON content"));
     }
     Ok(())
 }
 
+/// The choice between an update call and a query call of the read-only commands.
+#[derive(Args)]
+pub struct QueryOpts {
+    /// Sends a query call instead of an update call. Faster and does not use
+    /// ingress capacity, but the reply is not certified.
+    #[clap(long)]
+    pub fee_free: bool,
+}
+
 pub async fn submit_unsigned_ingress(
     canister_id: Principal,
     method_name: &str,
     args: Vec<u8>,
     yes: bool,
     dry_run: bool,
+    fee_free: bool,
     fetch_root_key: bool,
 ) -> AnyhowResult {
+    let opts = SendOpts {
+        file_name: None,
+        from_serial: None,
+        yes,
+        dry_run,
+        high_assurance: false,
+        review_summary: None,
+        allow_unknown_fields: false,
+        allowed_canisters: vec![canister_id],
+        audit_log: None,
+        operator_pem_file: None,
+        save_responses: None,
+        save_certificates: None,
+        watch: false,
+        neuron_report: None,
+        following_report: false,
+        stake_impact: false,
+        state_dir: None,
+        resume_from: None,
+        queue: None,
+        messages_per_second: None,
+        pacer: Pacer::default(),
+        metrics_file: None,
+        metrics: Metrics::default(),
+        on_complete: None,
+        webhook: None,
+        output_type: OutputFormat::Pretty,
+    };
+    if fee_free {
+        let msg =
+            crate::lib::signing::sign_query(&AuthInfo::NoAuth, canister_id, method_name, args)?;
+        if !is_quiet() {
+            eprintln!("Note: the reply to a query call is not certified.");
+        }
+        return send(&msg, &opts).await;
+    }
     let msg = crate::lib::signing::sign_ingress_with_request_status_query(
         &AuthInfo::NoAuth,
         canister_id,
         method_name,
         args,
     )?;
-    submit_ingress_and_check_status(
-        &msg,
-        &SendOpts {
-            file_name: Default::default(),
-            yes,
-            dry_run,
-        },
-        fetch_root_key,
-    )
-    .await
+    let args = decode_arguments(&msg.ingress);
+    submit_ingress_and_check_status(&msg, args, &opts, fetch_root_key).await
+}
+
+/// Signs an anonymous update call, submits it, and returns the raw reply without printing the
+/// message.
+pub async fn submit_unsigned_ingress_raw(
+    canister_id: Principal,
+    method_name: &str,
+    args: Vec<u8>,
+    fetch_root_key: bool,
+) -> AnyhowResult<Vec<u8>> {
+    let msg = crate::lib::signing::sign_ingress_with_request_status_query(
+        &AuthInfo::NoAuth,
+        canister_id,
+        method_name,
+        args,
+    )?;
+    let request_id = RequestId::from_str(
+        msg.ingress
+            .request_id
+            .as_ref()
+            .context("Cannot get request_id from the update message")?,
+    )?;
+    let transport = get_http_transport()?;
+    transport
+        .call(
+            canister_id,
+            decode_content(&msg.ingress.content)?,
+            request_id,
+        )
+        .await?;
+    // The reply is printed by the caller, e.g. as CSV, so nothing else goes to STDOUT.
+    request_status::submit(&msg.request_status, fetch_root_key, Progress::Silent).await
+}
+
+// Returns whether the outcome of the call is known from an interrupted submission.
+fn already_done(message: &Ingress, opts: &SendOpts) -> bool {
+    let done = match (&opts.queue, &message.request_id) {
+        (Some(queue), Some(request_id)) => queue.state(request_id).map_or(false, |s| s.is_done()),
+        _ => false,
+    };
+    if done && !is_quiet() {
+        eprintln!(
+            "Skipping request {}, whose outcome is known.",
+            message.request_id.as_deref().unwrap_or_default()
+        );
+    }
+    done
+}
+
+// Records the new state of a call of the submission, if it is recorded.
+fn record_state(message: &Ingress, opts: &SendOpts, state: MessageState) -> AnyhowResult {
+    match (&opts.queue, &message.request_id) {
+        (Some(queue), Some(request_id)) => queue.set(request_id, state),
+        _ => Ok(()),
+    }
+}
+
+// Returns the accounts receiving ICP from the ledger transfers of the bundle.
+fn transfer_recipients(bundle: &Bundle) -> Vec<String> {
+    let mut recipients = BTreeSet::new();
+    for msg in bundle.ingress_messages() {
+        if let Ok((_, canister_id, method_name, arg)) = msg.parse_raw() {
+            if canister_id == ledger_canister_id() && method_name == "send_dfx" {
+                if let Ok(args) = Decode!(&arg, transfer::SendArgs) {
+                    recipients.insert(args.to);
+                }
+            }
+        }
+    }
+    recipients.into_iter().collect()
+}
+
+// Refuses to submit messages signed for another network than the one IC_URL points to, e.g.
+// mainnet messages to a test instance, where they could be replayed later.
+fn check_network(bundle: &Bundle, fetch_root_key: bool) -> AnyhowResult {
+    // A fetched root key is never the one of the mainnet.
+    let network = if fetch_root_key {
+        get_ic_url().trim_end_matches('/').to_string()
+    } else {
+        get_network()
+    };
+    let networks: Vec<_> = match bundle {
+        Bundle::Ingress(msg) => vec![&msg.network],
+        Bundle::Messages(msgs) => msgs.iter().map(|msg| &msg.network).collect(),
+        Bundle::WithRequestStatus(msgs) => msgs
+            .iter()
+            .flat_map(|msg| vec![&msg.ingress.network, &msg.request_status.network])
+            .collect(),
+    };
+    // Messages signed before they were tagged are not checked.
+    if let Some(signed_for) = networks.into_iter().flatten().find(|&n| *n != network) {
+        bail!(
+            "The messages were signed for {}, but would be sent to {}. Set IC_URL to the \
+             network they were signed for, or sign them again.",
+            signed_for,
+            network
+        );
+    }
+    Ok(())
+}
+
+// Resolves the subnet of every canister targeted by the bundle, and compares it with the
+// subnet pinned for the canister, so that a mistyped canister id cannot route a call elsewhere.
+async fn check_subnets(bundle: &Bundle, fetch_root_key: bool) -> AnyhowResult {
+    let mut canister_ids = BTreeSet::new();
+    for msg in bundle.ingress_messages() {
+        canister_ids.insert(msg.parse_raw()?.1);
+    }
+    let pinned = load_pinned_subnets()?;
+    let agent = get_agent(&AuthInfo::NoAuth)?;
+    if fetch_root_key {
+        agent.fetch_root_key().await?;
+    }
+    for canister_id in canister_ids {
+        // Any state tree path of the canister is certified by its subnet: a certificate without
+        // a delegation is signed with the root key, i.e. by the NNS subnet.
+        let certificate = agent
+            .read_state_raw(vec![vec!["time".into()]], canister_id, false)
+            .await
+            .with_context(|| format!("Cannot resolve the subnet of {}", canister_id))?;
+        let subnet_id = match &certificate.delegation {
+            Some(delegation) => Principal::try_from(&delegation.subnet_id)?,
+            None if fetch_root_key => {
+                bail!("Cannot resolve the subnet of {}: it is on the root subnet of a local instance.", canister_id)
+            }
+            None => Principal::from_text(NNS_SUBNET_ID)?,
+        };
+        if !is_quiet() {
+            eprintln!("Canister {} is on subnet {}", canister_id, subnet_id);
+        }
+        if let Some(expected) = pinned.get(&canister_id) {
+            if *expected != subnet_id {
+                bail!(
+                    "Canister {} is on subnet {}, but subnet {} is pinned for it. No message was \
+                     sent.",
+                    canister_id,
+                    subnet_id,
+                    expected
+                );
+            }
+        }
+    }
+    Ok(())
 }
 
 async fn submit_ingress_and_check_status(
     message: &IngressWithRequestId,
+    args: AnyhowResult<String>,
     opts: &SendOpts,
     fetch_root_key: bool,
 ) -> AnyhowResult {
-    send(&message.ingress, opts).await?;
+    let (_, canister_id, method_name, _) = &message.ingress.parse()?;
+    if message.request_status.canister_id != canister_id.to_text() {
+        bail!(
+            "The request status query targets canister {} but the message targets {}.",
+            message.request_status.canister_id,
+            canister_id
+        );
+    }
+    if !opts.dry_run {
+        if let Some(reason) = preconditions::failed_precondition(&message.preconditions).await? {
+            println!(
+                "Skipping the message {}: {}.\n",
+                message.request_status.request_id, reason
+            );
+            return Ok(());
+        }
+    }
+    let start = Instant::now();
+    send_decoded(&message.ingress, args, opts).await?;
     if opts.dry_run {
+        if !message.preconditions.is_empty() {
+            let preconditions: Vec<_> = message
+                .preconditions
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            println!("  Preconditions: {}", preconditions.join(", "));
+        }
         return Ok(());
     }
-    let (_, _, method_name, _) = &message.ingress.parse()?;
-    match request_status::submit(
-        &message.request_status,
-        Some(method_name.to_string()),
-        fetch_root_key,
-    )
-    .await
-    {
-        Ok(result) => println!("{}\n", result),
+    let progress = if opts.watch {
+        Progress::Watch
+    } else {
+        Progress::Processing
+    };
+    let status =
+        request_status::submit_certified(&message.request_status, fetch_root_key, progress).await;
+    // Other errors, e.g. of the network, leave the outcome of the call unknown.
+    match &status {
+        Ok(_) => {
+            opts.metrics.replied(start.elapsed());
+            record_state(&message.ingress, opts, MessageState::Replied)?
+        }
+        Err(err) if matches!(err.downcast_ref(), Some(AgentError::ReplicaError { .. })) => {
+            opts.metrics.rejected(start.elapsed());
+            record_state(&message.ingress, opts, MessageState::Rejected)?
+        }
+        Err(_) => {}
+    }
+    write_metrics(opts);
+    notify_completion(message, &status, opts).await;
+    match status {
+        Ok((blob, mut receipt)) => {
+            if let Some(dir) = &opts.save_certificates {
+                receipt.method_name = Some(method_name.clone());
+                save_certificate(dir, &receipt)?;
+            }
+            let result = get_idl_string(
+                &blob,
+                *canister_id,
+                method_name,
+                ArgPart::Rets,
+                opts.output_type,
+            )
+            .context("Invalid IDL blob.");
+            if let Some(dir) = &opts.save_responses {
+                save_response(dir, &message.request_status.request_id, &blob, &result)?;
+            }
+            if print_neuron_report(&blob, method_name, opts).await? {
+                return Ok(());
+            }
+            // The full data of a neuron is printed readably, unless another format is requested.
+            if opts.output_type == OutputFormat::Pretty && method_name == "get_full_neuron" {
+                let names = known_neuron_names().await;
+                println!("{}", get_full_neuron::render_full_neuron(&blob, &names)?);
+                return Ok(());
+            }
+            match result {
+                Ok(result) => println!("{}\n", result),
+                Err(err) => println!("{}\n", err),
+            }
+        }
         Err(err) => println!("{}\n", err),
     };
     Ok(())
 }
 
+// Prints the reply of `list_neurons` as the report requested with --neuron-report or
+// --following-report, if any, and returns whether it did.
+async fn print_neuron_report(
+    blob: &[u8],
+    method_name: &str,
+    opts: &SendOpts,
+) -> AnyhowResult<bool> {
+    if method_name != "list_neurons" {
+        return Ok(false);
+    }
+    if let Some(format) = opts.neuron_report {
+        let report = list_neurons::render_report(blob, format, now_seconds()?)?;
+        println!("{}", report);
+        return Ok(true);
+    }
+    if opts.following_report {
+        let names = known_neuron_names().await;
+        println!("{}", list_neurons::render_following(blob, &names)?);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// Replaces the --metrics-file. Failing to write it does not stop the submission.
+fn write_metrics(opts: &SendOpts) {
+    if let Some(path) = &opts.metrics_file {
+        if let Err(err) = opts.metrics.write(path) {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+}
+
+// Runs the --on-complete command and posts to the --webhook URL if the outcome of the call is
+// known. Failing notifications do not stop the submission of the other messages.
+async fn notify_completion(
+    message: &IngressWithRequestId,
+    status: &AnyhowResult<(Vec<u8>, request_status::Receipt)>,
+    opts: &SendOpts,
+) {
+    if opts.on_complete.is_none() && opts.webhook.is_none() {
+        return;
+    }
+    let completion = match completion(message, status) {
+        Ok(Some(completion)) => completion,
+        Ok(None) => return,
+        Err(err) => {
+            eprintln!("Warning: cannot render the outcome of the call: {}", err);
+            return;
+        }
+    };
+    if let Some(command) = &opts.on_complete {
+        if let Err(err) = notify::run_command(command, &completion) {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+    if let Some(url) = &opts.webhook {
+        if let Err(err) = notify::post_webhook(url, &completion).await {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+}
+
+fn completion(
+    message: &IngressWithRequestId,
+    status: &AnyhowResult<(Vec<u8>, request_status::Receipt)>,
+) -> AnyhowResult<Option<notify::Completion>> {
+    let (_, canister_id, method_name, _) = message.ingress.parse_raw()?;
+    let mut completion = notify::Completion {
+        request_id: message.request_status.request_id.clone(),
+        canister_id: canister_id.to_text(),
+        method_name: method_name.clone(),
+        status: "replied",
+        reply: None,
+        reject_code: None,
+        reject_message: None,
+    };
+    match status {
+        Ok((blob, _)) => {
+            let reply = get_idl_string(
+                blob,
+                canister_id,
+                &method_name,
+                ArgPart::Rets,
+                OutputFormat::Json,
+            )?;
+            completion.reply = Some(serde_json::from_str(&reply)?);
+        }
+        Err(err) => match err.downcast_ref() {
+            Some(AgentError::ReplicaError {
+                reject_code,
+                reject_message,
+            }) => {
+                completion.status = "rejected";
+                completion.reject_code = Some(*reject_code);
+                completion.reject_message = Some(reject_message.clone());
+            }
+            _ => return Ok(None),
+        },
+    }
+    Ok(Some(completion))
+}
+
+// Returns the names of the known neurons, or none if they cannot be fetched.
+async fn known_neuron_names() -> HashMap<u64, String> {
+    list_neurons::fetch_known_neuron_names()
+        .await
+        .unwrap_or_else(|err| {
+            if !is_quiet() {
+                eprintln!("Warning: cannot fetch the names of known neurons: {}", err);
+            }
+            Default::default()
+        })
+}
+
+// Writes the raw reply and its decoded form to `<request id>.bin` and `<request id>.txt`.
+fn save_response(
+    dir: &Path,
+    request_id: &str,
+    blob: &[u8],
+    decoded: &AnyhowResult<String>,
+) -> AnyhowResult {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Cannot create the directory {}", dir.display()))?;
+    let path = dir.join(format!("{}.bin", request_id));
+    std::fs::write(&path, blob).with_context(|| format!("Cannot write {}", path.display()))?;
+    if let Ok(decoded) = decoded {
+        let path = dir.join(format!("{}.txt", request_id));
+        std::fs::write(&path, decoded)
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+// Writes the receipt of a reply to `<request id>.cert.json`.
+fn save_certificate(dir: &Path, receipt: &request_status::Receipt) -> AnyhowResult {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Cannot create the directory {}", dir.display()))?;
+    let path = dir.join(format!("{}.cert.json", receipt.request_id));
+    std::fs::write(&path, serde_json::to_string_pretty(receipt)?)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
 async fn send(message: &Ingress, opts: &SendOpts) -> AnyhowResult {
-    let (sender, canister_id, method_name, args) = message.parse()?;
+    send_decoded(message, decode_arguments(message), opts).await
+}
+
+// Renders the arguments of a message for display. Bundles of many messages, e.g. thousands of
+// transfers, have their arguments decoded in parallel beforehand.
+fn decode_arguments(message: &Ingress) -> AnyhowResult<String> {
+    let (_, canister_id, method_name, arg) = message.parse_raw()?;
+    get_idl_string(
+        &arg,
+        canister_id,
+        &method_name,
+        ArgPart::Args,
+        OutputFormat::Pretty,
+    )
+}
+
+async fn send_decoded(
+    message: &Ingress,
+    args: AnyhowResult<String>,
+    opts: &SendOpts,
+) -> AnyhowResult {
+    let (sender, canister_id, method_name, arg) = message.parse_raw()?;
+    let args = args?;
 
-    println!("Sending message with\n");
-    println!("  Call type:   {}", message.call_type);
-    println!("  Sender:      {}", sender);
-    println!("  Canister id: {}", canister_id);
-    println!("  Method name: {}", method_name);
-    println!("  Arguments:   {}", args);
+    // The message is always displayed on a dry run, which is only meant to display it.
+    if !is_quiet() || opts.dry_run {
+        println!("Sending message with\n");
+        println!("  Call type:   {}", message.call_type);
+        println!("  Sender:      {}", sender);
+        println!("  Canister id: {}", canister_id);
+        println!("  Method name: {}", method_name);
+        println!("  Arguments:   {}", args);
+    }
+
+    let allowed_canisters = if opts.allowed_canisters.is_empty() {
+        get_allowed_canister_ids()?
+    } else {
+        opts.allowed_canisters.clone()
+    };
+    if !allowed_canisters.contains(&canister_id) {
+        bail!(
+            "Refusing to send a message to canister {}, which is not in the allowed list.",
+            canister_id
+        );
+    }
+
+    let content = decode_content(&message.content)?;
+    // The size goes to STDERR so that the displayed message stays comparable between runs.
+    if opts.dry_run && !is_quiet() {
+        eprintln!(
+            "  Size:        {} bytes (at most {})",
+            content.len(),
+            max_ingress_bytes(canister_id)
+        );
+    }
+    check_ingress_size(canister_id, &method_name, content.len())?;
 
     if opts.dry_run {
+        let is_sns_governance = || -> AnyhowResult<bool> {
+            Ok(crate::lib::sns::configured_sns_canister_ids()?
+                .map_or(false, |sns| sns.governance_canister_id == canister_id))
+        };
+        if opts.stake_impact && method_name == "manage_neuron" && is_sns_governance()? {
+            if let Some(description) = sns::describe_disburse_maturity(&arg).await {
+                println!("\n{}", description);
+            }
+        }
+        if opts.stake_impact
+            && canister_id == governance_canister_id()
+            && method_name == "manage_neuron"
+        {
+            if let Some(description) = neuron_manage::describe_stake_impact(&arg).await {
+                println!("\n{}", description);
+            }
+        }
         return Ok(());
     }
 
+    // Followees are resolved before the confirmation, since known neurons are easier to check
+    // by name than by id.
+    if !is_quiet() && canister_id == governance_canister_id() && method_name == "manage_neuron" {
+        let followees = neuron_manage::followee_ids(&arg);
+        if !followees.is_empty() {
+            if let Ok(names) = list_neurons::fetch_known_neuron_names().await {
+                let labels: Vec<_> = followees
+                    .iter()
+                    .map(|id| list_neurons::known_neuron_label(*id, &names))
+                    .collect();
+                println!("  Followees:   {}", labels.join(", "));
+            }
+            // Delegating votes to a neuron which no longer votes loses the rewards of the
+            // follower, which is only noticed much later.
+            let followees: BTreeSet<_> = followees.into_iter().collect();
+            let followees: Vec<_> = followees.into_iter().collect();
+            for warning in voting_history::inactive_followee_warnings(&followees).await {
+                eprintln!("Warning: {}", warning);
+            }
+        }
+    }
+
     if message.call_type == "update" && !opts.yes {
+        if is_quiet() {
+            bail!("Refusing to ask for confirmation in quiet mode; use --yes to send the message.");
+        }
         println!("\nDo you want to send this message? [y/N]");
         let mut input = String::new();
         std::io::stdin().read_line(&mut input)?;
@@ -150,17 +842,20 @@ async fn send(message: &Ingress, opts: &SendOpts) -> AnyhowResult {
         }
     }
 
-    let transport = ReqwestHttpReplicaV2Transport::create(get_ic_url())?;
-    let content = hex::decode(&message.content)?;
+    let transport = get_http_transport()?;
 
     match message.call_type.as_str() {
         "query" => {
-            let response = parse_query_response(
+            let response =
                 ic_agent::agent::ReplicaV2Transport::query(&transport, canister_id, content)
-                    .await?,
-                canister_id,
-                &method_name,
-            )?;
+                    .await?;
+            if let QueryReply::Replied(reply) = parse_query_reply(&response)? {
+                if print_neuron_report(&reply, &method_name, opts).await? {
+                    return Ok(());
+                }
+            }
+            let response =
+                parse_query_response(response, canister_id, &method_name, opts.output_type)?;
             println!("Response: {}", response);
         }
         "update" => {
@@ -170,11 +865,49 @@ async fn send(message: &Ingress, opts: &SendOpts) -> AnyhowResult {
                     .request_id
                     .context("Cannot get request_id from the update message")?,
             )?;
-            transport.call(canister_id, content, request_id).await?;
+            submit_paced(&transport, canister_id, content, request_id, opts).await?;
+            record_state(message, opts, MessageState::Submitted)?;
             let request_id = format!("0x{}", String::from(request_id));
             println!("Request ID: {}", request_id);
         }
-        _ => unreachable!(),
+        call_type => bail!("Unsupported call type: {}", call_type),
     }
     Ok(())
 }
+
+// Submits an update call at the configured rate, and submits it again after slowing down if the
+// boundary node replies that its rate limit is exceeded.
+async fn submit_paced(
+    transport: &impl ReplicaV2Transport,
+    canister_id: Principal,
+    content: Vec<u8>,
+    request_id: RequestId,
+    opts: &SendOpts,
+) -> AnyhowResult {
+    let mut retries = 0;
+    loop {
+        opts.pacer.wait(opts.messages_per_second).await;
+        match transport
+            .call(canister_id, content.clone(), request_id)
+            .await
+        {
+            Ok(()) => {
+                opts.pacer.accepted();
+                opts.metrics.submitted();
+                write_metrics(opts);
+                return Ok(());
+            }
+            Err(err) if is_rate_limited(&err) && retries < MAX_RATE_LIMITED_RETRIES => {
+                retries += 1;
+                opts.metrics.retried();
+                let interval = opts.pacer.slow_down(opts.messages_per_second);
+                eprintln!(
+                    "The boundary node is rate-limiting the messages, slowing down to one message \
+                     every {:.1} s.",
+                    interval.as_secs_f64()
+                );
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    commands::{
+        custom, list_neurons, neuron_manage, neuron_stake, public, transfer, Empty, Output,
+    },
+    lib::{is_quiet, AnyhowResult, AuthInfo},
+};
+use anyhow::bail;
+use clap::Parser;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+/// Loads the key once and signs the commands entered interactively, until `exit` or the idle
+/// timeout. The key is only held in memory.
+#[derive(Parser)]
+pub struct SessionOpts {
+    /// Locks the session after this many seconds without input.
+    #[clap(long, default_value = "300")]
+    idle_timeout_seconds: u64,
+}
+
+/// The commands available in a session, which are signed with the key of the session.
+#[derive(Parser)]
+#[clap(name("session"), no_binary_name(true))]
+enum SessionCommand {
+    /// Prints the principal id and the account id.
+    PublicIds(public::PublicOpts),
+    Transfer(transfer::TransferOpts),
+    /// Claim seed neurons from the Genesis Token Canister.
+    ClaimNeurons(Empty),
+    NeuronStake(neuron_stake::StakeOpts),
+    NeuronManage(neuron_manage::ManageOpts),
+    /// Signs the query for all neurons belonging to the signing principal.
+    ListNeurons(list_neurons::ListNeuronsOpts),
+    Custom(custom::CustomOpts),
+    /// Locks the session and exits.
+    Exit,
+}
+
+pub fn exec(auth: AuthInfo, opts: SessionOpts, output: Output) -> AnyhowResult {
+    if let AuthInfo::NoAuth = auth {
+        bail!("session cannot be used without specifying a private key");
+    }
+    if is_quiet() {
+        bail!("Refusing to start an interactive session in quiet mode.");
+    }
+    // Check the key before the first command so that a wrong PEM file or PIN is reported
+    // right away.
+    let (principal, _) = public::get_ids(&auth)?;
+    eprintln!(
+        "Session started for {}. Enter commands without `quill`, `help` or `exit`.",
+        principal
+    );
+
+    // Lines are read on a separate thread so that the session can lock while waiting.
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let timeout = Duration::from_secs(opts.idle_timeout_seconds);
+    loop {
+        eprint!("quill> ");
+        std::io::stderr().flush()?;
+        let line = match receiver.recv_timeout(timeout) {
+            Ok(line) => line?,
+            Err(RecvTimeoutError::Timeout) => {
+                eprintln!("\nSession locked after {} idle seconds.", timeout.as_secs());
+                break;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let words = match split_words(&line) {
+            Ok(words) if words.is_empty() => continue,
+            Ok(words) => words,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                continue;
+            }
+        };
+        let command = match SessionCommand::try_parse_from(words) {
+            Ok(command) => command,
+            Err(err) => {
+                let _ = err.print();
+                continue;
+            }
+        };
+        if let SessionCommand::Exit = command {
+            break;
+        }
+        if let Err(err) = run(&auth, command, &output) {
+            eprintln!("Error: {:#}", err);
+        }
+    }
+    // The key is dropped with `auth` when the session ends.
+    Ok(())
+}
+
+fn run(auth: &AuthInfo, command: SessionCommand, output: &Output) -> AnyhowResult {
+    match command {
+        SessionCommand::PublicIds(opts) => public::exec(auth, opts)?,
+        SessionCommand::Transfer(mut opts) => {
+            // The input of the session is read by another thread.
+            opts.no_prompt = true;
+            output.print_vec(&transfer::exec(auth, opts)?)?
+        }
+        SessionCommand::ClaimNeurons(_) => {
+            output.print_vec(&crate::commands::claim_neurons::exec(auth)?)?
+        }
+        SessionCommand::NeuronStake(opts) => output.print_vec(&neuron_stake::exec(auth, opts)?)?,
+        SessionCommand::NeuronManage(opts) => {
+            output.print_vec(&neuron_manage::exec(auth, opts)?)?
+        }
+        SessionCommand::ListNeurons(opts) => output.print_vec(&list_neurons::exec(auth, opts)?)?,
+        SessionCommand::Custom(opts) => output.print_vec(&custom::exec(auth, opts)?)?,
+        SessionCommand::Exit => {}
+    }
+    println!();
+    Ok(())
+}
+
+// Splits a command line into words, honoring single and double quotes.
+fn split_words(line: &str) -> AnyhowResult<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        bail!("Unterminated quote");
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[test]
+fn test_split_words() {
+    assert_eq!(
+        split_words("transfer  abc --amount 1").unwrap(),
+        vec!["transfer", "abc", "--amount", "1"]
+    );
+    assert_eq!(
+        split_words(r#"custom ledger --args '(record { a = "b c" })'"#).unwrap(),
+        vec!["custom", "ledger", "--args", r#"(record { a = "b c" })"#]
+    );
+    assert_eq!(split_words("a \"\" b").unwrap(), vec!["a", "", "b"]);
+    assert!(split_words("").unwrap().is_empty());
+    assert!(split_words("a 'b").is_err());
+}
@@ -0,0 +1,37 @@
+use crate::lib::{
+    audit::counter_sign,
+    policy::{policy_path, signature_path, Policy},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{bail, Context};
+use clap::Parser;
+use std::path::PathBuf;
+
+/// Signs a key usage policy, which is enforced before signing once QUILL_POLICY_SIGNER is set
+/// to the principal of the signing key.
+#[derive(Parser)]
+pub struct SignPolicyOpts {
+    /// The policy file, `policy.toml` in the configuration directory by default.
+    policy_file: Option<PathBuf>,
+}
+
+pub fn exec(auth: &AuthInfo, opts: SignPolicyOpts) -> AnyhowResult {
+    if let AuthInfo::NoAuth = auth {
+        bail!("sign-policy cannot be used without specifying a private key");
+    }
+    let path = match opts.policy_file {
+        Some(path) => path,
+        None => policy_path()?,
+    };
+    let content =
+        std::fs::read(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+    toml::from_str::<Policy>(std::str::from_utf8(&content)?)
+        .with_context(|| format!("Cannot parse {}", path.display()))?;
+    let signature = counter_sign(auth, &openssl::sha::sha256(&content))?;
+    let sig_path = signature_path(&path);
+    std::fs::write(&sig_path, serde_json::to_string_pretty(&signature)?)
+        .with_context(|| format!("Cannot write {}", sig_path.display()))?;
+    println!("Signature written to {}", sig_path.display());
+    println!("Signer: {}", signature.principal);
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use crate::{
+    commands::sns::{
+        disburse_maturity::parse_sns_neuron_id, AddNeuronPermissions, Command, ManageNeuron,
+        NeuronPermissionList, RemoveNeuronPermissions, SnsCanisterIdsOpts,
+    },
+    lib::{
+        is_quiet,
+        signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+        sns::load_sns_canister_ids,
+        AnyhowResult, AuthInfo,
+    },
+};
+use anyhow::bail;
+use candid::{Encode, Principal};
+use clap::Parser;
+
+// All the permission types of SNS neurons, from ConfigureDissolveState (1) to
+// ManageVotingPermission (10).
+const ALL_PERMISSIONS: std::ops::RangeInclusive<i32> = 1..=10;
+
+/// Signs the transfer of all permissions on an SNS neuron to a new controller. NNS neurons
+/// cannot change their controller.
+#[derive(Parser)]
+pub struct ChangeControllerOpts {
+    /// The id of the neuron (hex-encoded).
+    neuron_id: String,
+
+    /// The principal which receives all permissions on the neuron.
+    #[clap(long)]
+    new_controller: Principal,
+
+    /// Keep the permissions of the signing key instead of removing them.
+    #[clap(long)]
+    keep_current_controller: bool,
+
+    /// Skips the confirmation. Required with --quiet, which fails instead of asking for
+    /// confirmation.
+    #[clap(long)]
+    yes: bool,
+
+    #[clap(flatten)]
+    sns: SnsCanisterIdsOpts,
+}
+
+pub fn exec(
+    auth: &AuthInfo,
+    opts: ChangeControllerOpts,
+) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    let (controller, _) = crate::commands::public::get_ids(auth)?;
+    if opts.new_controller == controller {
+        bail!("The new controller is the current controller");
+    }
+    let canister_ids = load_sns_canister_ids(opts.sns.canister_ids_file.as_deref())?;
+    let subaccount = parse_sns_neuron_id(&opts.neuron_id)?;
+    if !opts.yes {
+        confirm(
+            &opts.new_controller,
+            controller,
+            opts.keep_current_controller,
+        )?;
+    }
+
+    let mut messages = vec![sign_ingress_with_request_status_query(
+        auth,
+        canister_ids.governance_canister_id,
+        "manage_neuron",
+        Encode!(&ManageNeuron {
+            subaccount: subaccount.clone(),
+            command: Some(Command::AddNeuronPermissions(AddNeuronPermissions {
+                principal_id: Some(opts.new_controller),
+                permissions_to_add: Some(NeuronPermissionList {
+                    permissions: ALL_PERMISSIONS.collect(),
+                }),
+            })),
+        })?,
+    )?];
+    if !opts.keep_current_controller {
+        messages.push(sign_ingress_with_request_status_query(
+            auth,
+            canister_ids.governance_canister_id,
+            "manage_neuron",
+            Encode!(&ManageNeuron {
+                subaccount,
+                command: Some(Command::RemoveNeuronPermissions(RemoveNeuronPermissions {
+                    principal_id: Some(controller),
+                    permissions_to_remove: Some(NeuronPermissionList {
+                        permissions: ALL_PERMISSIONS.collect(),
+                    }),
+                })),
+            })?,
+        )?);
+    }
+    Ok(messages)
+}
+
+// Asks for the new controller to be typed again, since a wrong principal loses the neuron.
+fn confirm(new_controller: &Principal, controller: Principal, keep: bool) -> AnyhowResult {
+    if is_quiet() {
+        bail!("Refusing to ask for confirmation in quiet mode; use --yes to sign the messages.");
+    }
+    eprintln!(
+        "WARNING: {} will be able to disburse the neuron.",
+        new_controller
+    );
+    if !keep {
+        eprintln!(
+            "WARNING: the current controller {} will lose all permissions on the neuron.",
+            controller
+        );
+    }
+    eprintln!("Type the new controller principal again to confirm:");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    if input.trim() != new_controller.to_text() {
+        bail!("The principals do not match, no message was signed.");
+    }
+    Ok(())
+}
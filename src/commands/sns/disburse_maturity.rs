@@ -0,0 +1,121 @@
+#[cfg(feature = "online")]
+use crate::lib::{cycles_minting_canister_id, get_agent};
+use crate::{
+    commands::sns::{
+        Account, Command, DisburseMaturity, ManageNeuron, SnsCanisterIdsOpts, Subaccount,
+    },
+    lib::{
+        contacts::resolve_principal,
+        nns_types::Subaccount as NnsSubaccount,
+        signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+        sns::load_sns_canister_ids,
+        AnyhowResult, AuthInfo,
+    },
+};
+#[cfg(feature = "online")]
+use anyhow::anyhow;
+use anyhow::{bail, Context};
+use candid::Encode;
+#[cfg(feature = "online")]
+use candid::{CandidType, Decode, Deserialize};
+use clap::Parser;
+
+/// Signs the disbursement of an SNS neuron's maturity.
+#[derive(Parser)]
+pub struct DisburseMaturityOpts {
+    /// The id of the neuron (hex-encoded).
+    neuron_id: String,
+
+    /// The percentage (between 1 and 100) of the maturity to disburse.
+    #[clap(long, default_value = "100")]
+    percentage: u32,
+
+    /// The owner of the account to disburse to, a principal or a contact as `@<name>`. Defaults
+    /// to the neuron's controller.
+    #[clap(long)]
+    to: Option<String>,
+
+    /// The subaccount (64 hex digits) of the account to disburse to.
+    #[clap(long, requires("to"))]
+    subaccount: Option<NnsSubaccount>,
+
+    #[clap(flatten)]
+    sns: SnsCanisterIdsOpts,
+}
+
+pub fn exec(
+    auth: &AuthInfo,
+    opts: DisburseMaturityOpts,
+) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    if !(1..=100).contains(&opts.percentage) {
+        bail!("Percentage to disburse must be a number from 1 to 100");
+    }
+    let canister_ids = load_sns_canister_ids(opts.sns.canister_ids_file.as_deref())?;
+    let subaccount = opts.subaccount.map(Subaccount::from);
+    let to = opts.to.as_deref().map(resolve_principal).transpose()?;
+    let args = Encode!(&ManageNeuron {
+        subaccount: parse_sns_neuron_id(&opts.neuron_id)?,
+        command: Some(Command::DisburseMaturity(DisburseMaturity {
+            percentage_to_disburse: opts.percentage,
+            to_account: to.map(|owner| Account {
+                owner: Some(owner),
+                subaccount,
+            }),
+        })),
+    })?;
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        canister_ids.governance_canister_id,
+        "manage_neuron",
+        args,
+    )?])
+}
+
+/// Parses the hex-encoded id of an SNS neuron, which is the subaccount of its stake.
+pub fn parse_sns_neuron_id(id: &str) -> AnyhowResult<Vec<u8>> {
+    let id = hex::decode(id).context("The SNS neuron id must be hex-encoded")?;
+    if id.len() != 32 {
+        bail!("The SNS neuron id must be 32 bytes long");
+    }
+    Ok(id)
+}
+
+#[cfg(feature = "online")]
+#[derive(CandidType, Deserialize)]
+enum MaturityModulationResult {
+    Ok(i32),
+    Err(String),
+}
+
+/// If the argument disburses maturity, describes how much will be disbursed given the current
+/// maturity modulation reported by the cycles minting canister.
+#[cfg(feature = "online")]
+pub async fn describe_disburse_maturity(arg: &[u8]) -> Option<String> {
+    // Only messages disbursing maturity decode as this `ManageNeuron`.
+    let percentage = match Decode!(arg, ManageNeuron).ok()?.command? {
+        Command::DisburseMaturity(disburse) => disburse.percentage_to_disburse,
+        _ => return None,
+    };
+    Some(match fetch_maturity_modulation().await {
+        Ok(basis_points) => format!(
+            "Maturity modulation: {:+.2}%, an estimated {:.2}% of the neuron's maturity will be \
+             disbursed",
+            f64::from(basis_points) / 100.0,
+            f64::from(percentage) * (1.0 + f64::from(basis_points) / 10_000.0)
+        ),
+        Err(err) => format!("Maturity modulation: unavailable ({})", err),
+    })
+}
+
+#[cfg(feature = "online")]
+async fn fetch_maturity_modulation() -> AnyhowResult<i32> {
+    let reply = get_agent(&AuthInfo::NoAuth)?
+        .query(&cycles_minting_canister_id(), "neuron_maturity_modulation")
+        .with_arg(Encode!()?)
+        .call()
+        .await?;
+    match Decode!(&reply, MaturityModulationResult)? {
+        MaturityModulationResult::Ok(basis_points) => Ok(basis_points),
+        MaturityModulationResult::Err(err) => Err(anyhow!(err)),
+    }
+}
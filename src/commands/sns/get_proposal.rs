@@ -0,0 +1,46 @@
+use crate::commands::sns::{
+    proposals::{render_proposal, GetProposalResponse, GetProposalResult, ProposalId},
+    SnsCanisterIdsOpts,
+};
+use crate::lib::{get_agent, sns::load_sns_canister_ids, AnyhowResult, AuthInfo};
+use anyhow::{anyhow, bail, Context};
+use candid::{CandidType, Decode, Encode};
+use clap::Parser;
+
+/// Queries an SNS proposal and prints it with its payload decoded, e.g. the recipient of a
+/// treasury transfer or the hash of the wasm module of an upgrade.
+#[derive(Parser)]
+pub struct GetProposalOpts {
+    /// The id of the proposal.
+    proposal_id: u64,
+
+    #[clap(flatten)]
+    sns: SnsCanisterIdsOpts,
+}
+
+#[derive(CandidType)]
+struct GetProposal {
+    proposal_id: Option<ProposalId>,
+}
+
+pub async fn exec(opts: GetProposalOpts) -> AnyhowResult {
+    let canister_ids = load_sns_canister_ids(opts.sns.canister_ids_file.as_deref())?;
+    let args = Encode!(&GetProposal {
+        proposal_id: Some(ProposalId {
+            id: opts.proposal_id
+        }),
+    })?;
+    let reply = get_agent(&AuthInfo::NoAuth)?
+        .query(&canister_ids.governance_canister_id, "get_proposal")
+        .with_arg(args)
+        .call()
+        .await?;
+    let response =
+        Decode!(&reply, GetProposalResponse).context("Cannot decode get_proposal reply")?;
+    match response.result {
+        Some(GetProposalResult::Proposal(proposal)) => print!("{}", render_proposal(&proposal)),
+        Some(GetProposalResult::Error(err)) => bail!(err.error_message),
+        None => return Err(anyhow!("No proposal {}", opts.proposal_id)),
+    }
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use crate::commands::sns::{
+    proposals::{render_proposal_line, ListProposalsResponse, ProposalId},
+    SnsCanisterIdsOpts,
+};
+use crate::lib::{get_agent, sns::load_sns_canister_ids, AnyhowResult, AuthInfo};
+use anyhow::Context;
+use candid::{CandidType, Decode, Encode};
+use clap::Parser;
+
+/// Lists the latest SNS proposals, one per line, with their actions decoded.
+#[derive(Parser)]
+pub struct ListProposalsOpts {
+    /// The maximum number of proposals to list.
+    #[clap(long, default_value("20"))]
+    limit: u32,
+
+    /// Lists the proposals older than this one, to page through the proposals.
+    #[clap(long)]
+    before: Option<u64>,
+
+    #[clap(flatten)]
+    sns: SnsCanisterIdsOpts,
+}
+
+#[derive(CandidType)]
+struct ListProposals {
+    include_reward_status: Vec<i32>,
+    before_proposal: Option<ProposalId>,
+    limit: u32,
+    exclude_type: Vec<u64>,
+    include_status: Vec<i32>,
+}
+
+pub async fn exec(opts: ListProposalsOpts) -> AnyhowResult {
+    let canister_ids = load_sns_canister_ids(opts.sns.canister_ids_file.as_deref())?;
+    let args = Encode!(&ListProposals {
+        include_reward_status: Vec::new(),
+        before_proposal: opts.before.map(|id| ProposalId { id }),
+        limit: opts.limit,
+        exclude_type: Vec::new(),
+        include_status: Vec::new(),
+    })?;
+    let reply = get_agent(&AuthInfo::NoAuth)?
+        .query(&canister_ids.governance_canister_id, "list_proposals")
+        .with_arg(args)
+        .call()
+        .await?;
+    let response =
+        Decode!(&reply, ListProposalsResponse).context("Cannot decode list_proposals reply")?;
+    for proposal in &response.proposals {
+        println!("{}", render_proposal_line(proposal));
+    }
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use crate::{
+    commands::sns::{
+        disburse_maturity::parse_sns_neuron_id,
+        proposals::{
+            describe_action, sha256_hex, Action, Proposal, TransferSnsTreasuryFunds,
+            UpgradeSnsControlledCanister,
+        },
+        Command, ManageNeuron, SnsCanisterIdsOpts, Subaccount,
+    },
+    lib::{
+        contacts::resolve_principal,
+        is_quiet,
+        nns_types::{
+            icpts::{E8s, Icp},
+            Subaccount as NnsSubaccount,
+        },
+        proposal::{check_proposal_text, print_preview, read_summary},
+        signing::{
+            sign_ingress_with_request_status_query, IngressWithRequestId, MAX_INGRESS_BYTES,
+        },
+        sns::load_sns_canister_ids,
+        AnyhowResult, AuthInfo,
+    },
+};
+use anyhow::{anyhow, bail, Context};
+use candid::{Encode, IDLArgs, Principal};
+use clap::{ArgEnum, ArgGroup, Parser};
+use std::path::PathBuf;
+
+// Leaves room in the signed message for the rest of the proposal and the envelope.
+const MAX_WASM_BYTES: usize = MAX_INGRESS_BYTES - 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, ArgEnum)]
+enum Treasury {
+    /// The ICP raised by the decentralization swap.
+    Icp,
+    /// The SNS tokens held by the governance canister.
+    Sns,
+}
+
+/// Signs a proposal made by an SNS neuron, whose action is built from the options.
+#[derive(Parser)]
+#[clap(group(
+    ArgGroup::new("action")
+        .args(&["transfer-treasury", "upgrade-canister"])
+        .required(true)
+))]
+pub struct MakeProposalOpts {
+    /// The id of the neuron making the proposal (hex-encoded).
+    neuron_id: String,
+
+    /// The title of the proposal.
+    #[clap(long)]
+    title: String,
+
+    /// The summary of the proposal.
+    #[clap(long, required_unless_present("summary-file"))]
+    summary: Option<String>,
+
+    /// Path to a file with the summary of the proposal, e.g. a Markdown document.
+    #[clap(long, conflicts_with("summary"))]
+    summary_file: Option<PathBuf>,
+
+    /// A URL with more information on the proposal.
+    #[clap(long, default_value = "")]
+    url: String,
+
+    /// Proposes to transfer funds from a treasury of the SNS.
+    #[clap(long, requires_all(&["amount", "to"]))]
+    transfer_treasury: bool,
+
+    /// The treasury the funds are transferred from.
+    #[clap(long, arg_enum, default_value = "icp", requires("transfer-treasury"))]
+    treasury: Treasury,
+
+    /// The amount of tokens to transfer, e.g. `12.5` or `150_000_000e8s`.
+    #[clap(long, requires("transfer-treasury"))]
+    amount: Option<Icp>,
+
+    /// The recipient of the funds, a principal or a contact as `@<name>`.
+    #[clap(long, requires("transfer-treasury"))]
+    to: Option<String>,
+
+    /// The subaccount (64 hex digits) of the recipient.
+    #[clap(long, requires("to"))]
+    subaccount: Option<NnsSubaccount>,
+
+    /// The memo of the transfer.
+    #[clap(long, requires("transfer-treasury"))]
+    memo: Option<u64>,
+
+    /// Proposes to upgrade a canister controlled by the SNS, e.g. a dapp canister.
+    #[clap(long, requires("wasm-path"))]
+    upgrade_canister: Option<Principal>,
+
+    /// The wasm module (optionally gzipped) to upgrade the canister to.
+    #[clap(long, requires("upgrade-canister"))]
+    wasm_path: Option<PathBuf>,
+
+    /// The argument of the upgrade in candid text format, e.g. '(record { mode = 1 })'.
+    #[clap(long, requires("upgrade-canister"))]
+    upgrade_arg: Option<String>,
+
+    #[clap(flatten)]
+    sns: SnsCanisterIdsOpts,
+}
+
+pub fn exec(auth: &AuthInfo, opts: MakeProposalOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    let canister_ids = load_sns_canister_ids(opts.sns.canister_ids_file.as_deref())?;
+    let mut summary = read_summary(opts.summary.clone(), opts.summary_file.as_deref())?;
+    let action = if opts.upgrade_canister.is_some() {
+        let upgrade = upgrade_canister(&opts)?;
+        // Voters compare the hash in the summary to the one of a reproducible build.
+        let hash = sha256_hex(&upgrade.new_canister_wasm);
+        if !is_quiet() {
+            eprintln!("SHA-256 of the wasm module:\n\n  {}\n", hash);
+        }
+        if !summary.contains(&hash) {
+            summary.push_str(&format!("\n\nWasm module SHA-256: {}", hash));
+        }
+        Action::UpgradeSnsControlledCanister(upgrade)
+    } else {
+        transfer_treasury_action(&opts)?
+    };
+    check_proposal_text(&opts.title, &summary, &opts.url)?;
+    print_preview(&opts.title, &summary, &opts.url);
+    if !is_quiet() {
+        eprintln!("{}", describe_action(&action));
+    }
+    let args = Encode!(&ManageNeuron {
+        subaccount: parse_sns_neuron_id(&opts.neuron_id)?,
+        command: Some(Command::MakeProposal(Proposal {
+            url: opts.url,
+            title: opts.title,
+            action: Some(action),
+            summary,
+        })),
+    })?;
+    Ok(vec![sign_ingress_with_request_status_query(
+        auth,
+        canister_ids.governance_canister_id,
+        "manage_neuron",
+        args,
+    )?])
+}
+
+fn transfer_treasury_action(opts: &MakeProposalOpts) -> AnyhowResult<Action> {
+    let amount = opts
+        .amount
+        .ok_or_else(|| anyhow!("--transfer-treasury requires --amount"))?;
+    let to = opts
+        .to
+        .as_deref()
+        .ok_or_else(|| anyhow!("--transfer-treasury requires --to"))?;
+    Ok(Action::TransferSnsTreasuryFunds(TransferSnsTreasuryFunds {
+        // The values of the `TransferFrom` enum of SNS governance.
+        from_treasury: match opts.treasury {
+            Treasury::Icp => 1,
+            Treasury::Sns => 2,
+        },
+        to_principal: Some(resolve_principal(to)?),
+        to_subaccount: opts.subaccount.map(Subaccount::from),
+        memo: opts.memo,
+        amount_e8s: E8s::from(amount).get(),
+    }))
+}
+
+fn upgrade_canister(opts: &MakeProposalOpts) -> AnyhowResult<UpgradeSnsControlledCanister> {
+    let wasm_path = opts
+        .wasm_path
+        .as_ref()
+        .ok_or_else(|| anyhow!("--upgrade-canister requires --wasm-path"))?;
+    let wasm = std::fs::read(wasm_path)
+        .with_context(|| format!("Cannot read the wasm module {}", wasm_path.display()))?;
+    // The SNS governance canister takes the whole module in the proposal, which is a single
+    // ingress message: there is no way to upload it in chunks.
+    if wasm.len() > MAX_WASM_BYTES {
+        bail!(
+            "The wasm module {} is {} bytes, but a proposal can only hold {} bytes. Compress it \
+             with gzip, which canisters accept, or reduce its size.",
+            wasm_path.display(),
+            wasm.len(),
+            MAX_WASM_BYTES
+        );
+    }
+    let canister_upgrade_arg = opts
+        .upgrade_arg
+        .as_deref()
+        .map(|arg| {
+            arg.parse::<IDLArgs>()
+                .and_then(|args| args.to_bytes())
+                .context("Cannot parse the upgrade argument")
+        })
+        .transpose()?
+        .map(serde_bytes::ByteBuf::from);
+    Ok(UpgradeSnsControlledCanister {
+        new_canister_wasm: wasm,
+        canister_id: opts.upgrade_canister,
+        canister_upgrade_arg,
+    })
+}
+
+#[test]
+fn test_transfer_treasury_action() {
+    let opts = MakeProposalOpts::try_parse_from(&[
+        "make-proposal",
+        "00",
+        "--title",
+        "Pay the auditors",
+        "--summary",
+        "Pays the audit of the dapp.",
+        "--transfer-treasury",
+        "--treasury",
+        "sns",
+        "--amount",
+        "2.5",
+        "--to",
+        "2vxsx-fae",
+    ])
+    .unwrap();
+    assert_eq!(
+        describe_action(&transfer_treasury_action(&opts).unwrap()),
+        "Transfer 2.50000000 tokens from the SNS token treasury to 2vxsx-fae"
+    );
+    assert!(MakeProposalOpts::try_parse_from(&[
+        "make-proposal",
+        "00",
+        "--title",
+        "t",
+        "--summary",
+        "s",
+        "--transfer-treasury",
+        "--amount",
+        "2.5",
+    ])
+    .is_err());
+}
+
+#[test]
+fn test_upgrade_canister_action() {
+    let dir = tempfile::tempdir().unwrap();
+    let wasm_path = dir.path().join("dapp.wasm");
+    std::fs::write(&wasm_path, b"abc").unwrap();
+    let opts = MakeProposalOpts::try_parse_from(&[
+        "make-proposal",
+        "00",
+        "--title",
+        "Upgrade the dapp",
+        "--summary",
+        "Release 1.2.",
+        "--upgrade-canister",
+        "2vxsx-fae",
+        "--wasm-path",
+        wasm_path.to_str().unwrap(),
+        "--upgrade-arg",
+        "()",
+    ])
+    .unwrap();
+    assert_eq!(
+        describe_action(&Action::UpgradeSnsControlledCanister(
+            upgrade_canister(&opts).unwrap()
+        )),
+        "Upgrade canister 2vxsx-fae to the wasm module with SHA-256 \
+         ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad (3 bytes), with the \
+         upgrade argument with SHA-256 \
+         0fee102bd16b053022b69f2c65fd5e2f41d150ce9c214ac8731cfaf496ebda4e (6 bytes)"
+    );
+}
@@ -0,0 +1,160 @@
+//! Commands for the neurons and tokens of a Service Nervous System (SNS).
+
+use crate::{commands::Output, get_auth, lib::AnyhowResult, BaseOpts};
+use candid::{CandidType, Deserialize, Principal};
+use clap::Parser;
+use std::path::PathBuf;
+#[cfg(feature = "online")]
+use tokio::runtime::Runtime;
+
+mod change_controller;
+mod disburse_maturity;
+#[cfg(feature = "online")]
+mod get_proposal;
+#[cfg(feature = "online")]
+mod list_proposals;
+mod make_proposal;
+mod proposals;
+mod stake_neuron;
+
+#[cfg(feature = "online")]
+pub use disburse_maturity::describe_disburse_maturity;
+
+/// Commands for SNS neurons and tokens.
+#[derive(Parser)]
+pub struct SnsOpts {
+    #[clap(subcommand)]
+    command: SnsCommand,
+}
+
+#[derive(Parser)]
+pub enum SnsCommand {
+    ChangeController(BaseOpts<change_controller::ChangeControllerOpts>),
+    DisburseMaturity(BaseOpts<disburse_maturity::DisburseMaturityOpts>),
+    #[cfg(feature = "online")]
+    GetProposal(BaseOpts<get_proposal::GetProposalOpts>),
+    #[cfg(feature = "online")]
+    ListProposals(BaseOpts<list_proposals::ListProposalsOpts>),
+    MakeProposal(BaseOpts<make_proposal::MakeProposalOpts>),
+    StakeNeuron(BaseOpts<stake_neuron::StakeNeuronOpts>),
+}
+
+/// Selects the SNS the command applies to.
+#[derive(Parser)]
+pub struct SnsCanisterIdsOpts {
+    /// Path to a JSON file with the canister ids of the SNS. Defaults to sns_canister_ids.json
+    /// in the configuration directory.
+    #[clap(long)]
+    canister_ids_file: Option<PathBuf>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct Subaccount {
+    pub subaccount: Vec<u8>,
+}
+
+impl From<crate::lib::nns_types::Subaccount> for Subaccount {
+    fn from(subaccount: crate::lib::nns_types::Subaccount) -> Self {
+        Subaccount {
+            subaccount: subaccount.0.to_vec(),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct Account {
+    pub owner: Option<Principal>,
+    pub subaccount: Option<Subaccount>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct DisburseMaturity {
+    pub percentage_to_disburse: u32,
+    pub to_account: Option<Account>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct MemoAndController {
+    pub controller: Option<Principal>,
+    pub memo: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum By {
+    MemoAndController(MemoAndController),
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ClaimOrRefresh {
+    pub by: Option<By>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct NeuronPermissionList {
+    pub permissions: Vec<i32>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AddNeuronPermissions {
+    pub principal_id: Option<Principal>,
+    pub permissions_to_add: Option<NeuronPermissionList>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct RemoveNeuronPermissions {
+    pub principal_id: Option<Principal>,
+    pub permissions_to_remove: Option<NeuronPermissionList>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum Command {
+    DisburseMaturity(DisburseMaturity),
+    ClaimOrRefresh(ClaimOrRefresh),
+    AddNeuronPermissions(AddNeuronPermissions),
+    RemoveNeuronPermissions(RemoveNeuronPermissions),
+    MakeProposal(proposals::Proposal),
+}
+
+/// The argument of `manage_neuron` on the SNS governance canister; the neuron
+/// is identified by the subaccount of its stake.
+#[derive(CandidType, Deserialize)]
+pub struct ManageNeuron {
+    pub subaccount: Vec<u8>,
+    pub command: Option<Command>,
+}
+
+pub fn dispatch(opts: SnsOpts) -> AnyhowResult {
+    match opts.command {
+        SnsCommand::ChangeController(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = change_controller::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+        SnsCommand::DisburseMaturity(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = disburse_maturity::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+        #[cfg(feature = "online")]
+        SnsCommand::GetProposal(opts) => {
+            let runtime = Runtime::new().expect("Unable to create a runtime");
+            runtime.block_on(get_proposal::exec(opts.command_opts))?;
+        }
+        #[cfg(feature = "online")]
+        SnsCommand::ListProposals(opts) => {
+            let runtime = Runtime::new().expect("Unable to create a runtime");
+            runtime.block_on(list_proposals::exec(opts.command_opts))?;
+        }
+        SnsCommand::MakeProposal(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = make_proposal::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+        SnsCommand::StakeNeuron(opts) => {
+            let output = Output::new(&opts.global_opts);
+            let out = stake_neuron::exec(&get_auth(opts.global_opts)?, opts.command_opts)?;
+            output.print_vec(&out)?;
+        }
+    }
+    Ok(())
+}
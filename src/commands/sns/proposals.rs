@@ -0,0 +1,312 @@
+//! Readable renderings of SNS proposals. The payloads of the proposals are decoded, e.g. the
+//! recipient and amount of a treasury transfer or the hash of an upgrade's wasm module, so that
+//! voters can evaluate them without reading raw candid.
+
+use crate::lib::{
+    nns_types::icpts::{E8s, Icp},
+    timestamp::format_timestamp_seconds,
+};
+use candid::{CandidType, Deserialize, Principal};
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposalId {
+    pub id: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GovernanceError {
+    pub error_message: String,
+}
+
+// The actions are decoded with the fields needed to describe them; the others are skipped.
+#[derive(CandidType, Deserialize)]
+pub struct EmptyRecord {}
+
+#[derive(CandidType, Deserialize)]
+pub struct Motion {
+    pub motion_text: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct TransferSnsTreasuryFunds {
+    pub from_treasury: i32,
+    pub to_principal: Option<Principal>,
+    pub to_subaccount: Option<super::Subaccount>,
+    pub memo: Option<u64>,
+    pub amount_e8s: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct UpgradeSnsControlledCanister {
+    #[serde(with = "serde_bytes")]
+    pub new_canister_wasm: Vec<u8>,
+    pub canister_id: Option<Principal>,
+    pub canister_upgrade_arg: Option<serde_bytes::ByteBuf>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ExecuteGenericNervousSystemFunction {
+    pub function_id: u64,
+    #[serde(with = "serde_bytes")]
+    pub payload: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct NervousSystemFunction {
+    pub id: u64,
+    pub name: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct RegisterDappCanisters {
+    pub canister_ids: Vec<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct DeregisterDappCanisters {
+    pub canister_ids: Vec<Principal>,
+    pub new_controllers: Vec<Principal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum Action {
+    ManageNervousSystemParameters(EmptyRecord),
+    AddGenericNervousSystemFunction(NervousSystemFunction),
+    RemoveGenericNervousSystemFunction(u64),
+    UpgradeSnsToNextVersion(EmptyRecord),
+    RegisterDappCanisters(RegisterDappCanisters),
+    TransferSnsTreasuryFunds(TransferSnsTreasuryFunds),
+    UpgradeSnsControlledCanister(UpgradeSnsControlledCanister),
+    DeregisterDappCanisters(DeregisterDappCanisters),
+    Unspecified(EmptyRecord),
+    ManageSnsMetadata(EmptyRecord),
+    ExecuteGenericNervousSystemFunction(ExecuteGenericNervousSystemFunction),
+    Motion(Motion),
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct Proposal {
+    pub url: String,
+    pub title: String,
+    pub action: Option<Action>,
+    pub summary: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct Tally {
+    pub yes: u64,
+    pub no: u64,
+    pub total: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ProposalData {
+    pub id: Option<ProposalId>,
+    pub payload_text_rendering: Option<String>,
+    pub proposal_creation_timestamp_seconds: u64,
+    pub latest_tally: Option<Tally>,
+    pub decided_timestamp_seconds: u64,
+    pub executed_timestamp_seconds: u64,
+    pub failed_timestamp_seconds: u64,
+    pub proposal: Option<Proposal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum GetProposalResult {
+    Error(GovernanceError),
+    Proposal(ProposalData),
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct GetProposalResponse {
+    pub result: Option<GetProposalResult>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct ListProposalsResponse {
+    pub proposals: Vec<ProposalData>,
+}
+
+/// Returns the SHA-256 of the bytes in hex, as published for wasm modules.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(openssl::sha::sha256(bytes))
+}
+
+/// Describes what a proposal does when adopted.
+pub fn describe_action(action: &Action) -> String {
+    match action {
+        Action::Motion(motion) => format!("Motion: {}", motion.motion_text),
+        Action::TransferSnsTreasuryFunds(transfer) => {
+            let treasury = match transfer.from_treasury {
+                1 => "ICP",
+                2 => "SNS token",
+                _ => "unspecified",
+            };
+            let mut description = format!(
+                "Transfer {} tokens from the {} treasury to {}",
+                Icp::from(E8s::new(transfer.amount_e8s)),
+                treasury,
+                transfer
+                    .to_principal
+                    .map_or_else(|| "an unspecified principal".to_string(), |p| p.to_text())
+            );
+            if let Some(subaccount) = &transfer.to_subaccount {
+                description.push_str(&format!(
+                    ", subaccount {}",
+                    hex::encode(&subaccount.subaccount)
+                ));
+            }
+            if let Some(memo) = transfer.memo {
+                description.push_str(&format!(", memo {}", memo));
+            }
+            description
+        }
+        Action::UpgradeSnsControlledCanister(upgrade) => {
+            let mut description = format!(
+                "Upgrade canister {} to the wasm module with SHA-256 {} ({} bytes)",
+                upgrade
+                    .canister_id
+                    .map_or_else(|| "?".to_string(), |id| id.to_text()),
+                sha256_hex(&upgrade.new_canister_wasm),
+                upgrade.new_canister_wasm.len()
+            );
+            if let Some(arg) = &upgrade.canister_upgrade_arg {
+                description.push_str(&format!(
+                    ", with the upgrade argument with SHA-256 {} ({} bytes)",
+                    sha256_hex(arg),
+                    arg.len()
+                ));
+            }
+            description
+        }
+        Action::ExecuteGenericNervousSystemFunction(execute) => format!(
+            "Execute the generic function {} with a payload with SHA-256 {} ({} bytes)",
+            execute.function_id,
+            sha256_hex(&execute.payload),
+            execute.payload.len()
+        ),
+        Action::AddGenericNervousSystemFunction(function) => format!(
+            "Add the generic function {} ({})",
+            function.id, function.name
+        ),
+        Action::RemoveGenericNervousSystemFunction(id) => {
+            format!("Remove the generic function {}", id)
+        }
+        Action::RegisterDappCanisters(register) => format!(
+            "Register the dapp canisters {}",
+            join_principals(&register.canister_ids)
+        ),
+        Action::DeregisterDappCanisters(deregister) => format!(
+            "Deregister the dapp canisters {}, handing them over to {}",
+            join_principals(&deregister.canister_ids),
+            join_principals(&deregister.new_controllers)
+        ),
+        Action::UpgradeSnsToNextVersion(_) => "Upgrade the SNS to the next version".to_string(),
+        Action::ManageNervousSystemParameters(_) => "Change the parameters of the SNS".to_string(),
+        Action::ManageSnsMetadata(_) => "Change the metadata of the SNS".to_string(),
+        Action::Unspecified(_) => "Unspecified".to_string(),
+    }
+}
+
+fn join_principals(principals: &[Principal]) -> String {
+    principals
+        .iter()
+        .map(Principal::to_text)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn status(proposal: &ProposalData) -> &'static str {
+    if proposal.executed_timestamp_seconds > 0 {
+        "Executed"
+    } else if proposal.failed_timestamp_seconds > 0 {
+        "Failed"
+    } else if proposal.decided_timestamp_seconds > 0 {
+        "Decided"
+    } else {
+        "Open"
+    }
+}
+
+/// Renders a proposal with its decoded action.
+pub fn render_proposal(proposal: &ProposalData) -> String {
+    let id = proposal.id.as_ref().map_or(0, |id| id.id);
+    let mut text = format!("Proposal {}\n", id);
+    if let Some(content) = &proposal.proposal {
+        text.push_str(&format!("Title:   {}\n", content.title));
+        if !content.url.is_empty() {
+            text.push_str(&format!("URL:     {}\n", content.url));
+        }
+    }
+    text.push_str(&format!(
+        "Created: {}\nStatus:  {}\n",
+        format_timestamp_seconds(proposal.proposal_creation_timestamp_seconds),
+        status(proposal)
+    ));
+    if let Some(tally) = &proposal.latest_tally {
+        text.push_str(&format!(
+            "Tally:   {} yes, {} no, of {}\n",
+            tally.yes, tally.no, tally.total
+        ));
+    }
+    if let Some(action) = proposal.proposal.as_ref().and_then(|p| p.action.as_ref()) {
+        text.push_str(&format!("Action:  {}\n", describe_action(action)));
+    }
+    // The SNS renders the payloads of generic functions with their validators.
+    if let Some(rendering) = &proposal.payload_text_rendering {
+        text.push_str(&format!("Payload: {}\n", rendering));
+    }
+    if let Some(content) = &proposal.proposal {
+        text.push_str(&format!("\n{}\n", content.summary));
+    }
+    text
+}
+
+/// Renders a proposal on a single line, for lists.
+pub fn render_proposal_line(proposal: &ProposalData) -> String {
+    format!(
+        "{}\t{}\t{}\t{}",
+        proposal.id.as_ref().map_or(0, |id| id.id),
+        status(proposal),
+        proposal.proposal.as_ref().map_or("", |p| p.title.as_str()),
+        proposal
+            .proposal
+            .as_ref()
+            .and_then(|p| p.action.as_ref())
+            .map_or_else(String::new, describe_action)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_treasury_transfers() {
+        let action = Action::TransferSnsTreasuryFunds(TransferSnsTreasuryFunds {
+            from_treasury: 1,
+            to_principal: Some(Principal::anonymous()),
+            to_subaccount: None,
+            memo: Some(7),
+            amount_e8s: 150_000_000,
+        });
+        assert_eq!(
+            describe_action(&action),
+            "Transfer 1.50000000 tokens from the ICP treasury to 2vxsx-fae, memo 7"
+        );
+    }
+
+    #[test]
+    fn describes_upgrades_by_hash() {
+        let action = Action::UpgradeSnsControlledCanister(UpgradeSnsControlledCanister {
+            new_canister_wasm: b"abc".to_vec(),
+            canister_id: Some(Principal::anonymous()),
+            canister_upgrade_arg: None,
+        });
+        assert_eq!(
+            describe_action(&action),
+            "Upgrade canister 2vxsx-fae to the wasm module with SHA-256 \
+             ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad (3 bytes)"
+        );
+    }
+}
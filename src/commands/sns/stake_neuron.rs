@@ -0,0 +1,109 @@
+use crate::{
+    commands::{
+        sns::{By, ClaimOrRefresh, Command, ManageNeuron, MemoAndController, SnsCanisterIdsOpts},
+        transfer::token_amount_validator,
+    },
+    lib::{
+        nns_types::{
+            icpts::{E8s, Icp},
+            Subaccount,
+        },
+        signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
+        sns::load_sns_canister_ids,
+        AnyhowResult, AuthInfo,
+    },
+};
+use anyhow::Context;
+use candid::{CandidType, Encode, Nat, Principal};
+use clap::Parser;
+
+#[derive(CandidType)]
+pub struct Account {
+    pub owner: Principal,
+    pub subaccount: Option<Vec<u8>>,
+}
+
+/// The argument of `icrc1_transfer` on the SNS ledger canister.
+#[derive(CandidType)]
+pub struct TransferArg {
+    pub from_subaccount: Option<Vec<u8>>,
+    pub to: Account,
+    pub amount: Nat,
+    pub fee: Option<Nat>,
+    pub memo: Option<Vec<u8>>,
+    pub created_at_time: Option<u64>,
+}
+
+/// Signs staking of an SNS neuron (new or existing) identified by a memo.
+#[derive(Parser)]
+pub struct StakeNeuronOpts {
+    /// Tokens to be staked on the neuron (with up to 8 decimal digits after comma).
+    /// Without it, only the neuron is claimed or refreshed.
+    #[clap(long, validator(token_amount_validator))]
+    amount: Option<String>,
+
+    /// The memo of the neuron, distinguishing the neurons of a controller.
+    #[clap(long)]
+    memo: u64,
+
+    /// Transaction fee. Defaults to the fee of the SNS ledger.
+    #[clap(long, validator(token_amount_validator))]
+    fee: Option<String>,
+
+    #[clap(flatten)]
+    sns: SnsCanisterIdsOpts,
+}
+
+pub fn exec(auth: &AuthInfo, opts: StakeNeuronOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
+    let (controller, _) = crate::commands::public::get_ids(auth)?;
+    let canister_ids = load_sns_canister_ids(opts.sns.canister_ids_file.as_deref())?;
+    let subaccount = Subaccount::from_nonce(&controller, opts.memo).0.to_vec();
+
+    let mut messages = Vec::new();
+    if let Some(amount) = opts.amount {
+        let amount = E8s::from(amount.parse::<Icp>()?);
+        let fee = opts
+            .fee
+            .map(|fee| {
+                fee.parse::<Icp>()
+                    .map(E8s::from)
+                    .context("Cannot parse fee")
+            })
+            .transpose()?;
+        let args = Encode!(&TransferArg {
+            from_subaccount: None,
+            to: Account {
+                owner: canister_ids.governance_canister_id,
+                subaccount: Some(subaccount.clone()),
+            },
+            amount: amount.into(),
+            fee: fee.map(Nat::from),
+            memo: Some(opts.memo.to_be_bytes().to_vec()),
+            created_at_time: None,
+        })?;
+        messages.push(sign_ingress_with_request_status_query(
+            auth,
+            canister_ids.ledger_canister_id,
+            "icrc1_transfer",
+            args,
+        )?);
+    }
+
+    let args = Encode!(&ManageNeuron {
+        subaccount,
+        command: Some(Command::ClaimOrRefresh(ClaimOrRefresh {
+            by: Some(By::MemoAndController(MemoAndController {
+                controller: Some(controller),
+                memo: opts.memo,
+            })),
+        })),
+    })?;
+    messages.push(sign_ingress_with_request_status_query(
+        auth,
+        canister_ids.governance_canister_id,
+        "manage_neuron",
+        args,
+    )?);
+
+    Ok(messages)
+}
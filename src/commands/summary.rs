@@ -0,0 +1,174 @@
+//! Human-readable summaries of bundles of signed messages, to be printed, reviewed and signed off
+//! before a bundle leaves the signing machine.
+
+use crate::commands::transfer::SendArgs;
+use crate::lib::{
+    audit::bundle_hash,
+    get_idl_string, ledger_canister_id,
+    nns_types::icpts::{E8s, Icp},
+    signing::parse_bundle,
+    timestamp::format_timestamp_seconds,
+    AnyhowResult, ArgPart, OutputFormat,
+};
+use anyhow::{anyhow, bail};
+use candid::{Decode, Principal};
+use std::fmt::Write;
+
+const HASH_LABEL: &str = "- Bundle SHA-256:";
+
+/// Renders the Markdown summary of a bundle, given as it is written by the signing commands.
+pub fn render(bundle: &str) -> AnyhowResult<String> {
+    let messages = parse_bundle(bundle, false)?;
+    let messages = messages.ingress_messages();
+    let mut summary = String::new();
+    writeln!(summary, "# Signed messages\n")?;
+    writeln!(
+        summary,
+        "{} `{}`",
+        HASH_LABEL,
+        hex::encode(bundle_hash(bundle.as_bytes()))
+    )?;
+    writeln!(summary, "- Messages: {}", messages.len())?;
+    if let Some(network) = messages.iter().find_map(|msg| msg.network.as_ref()) {
+        writeln!(summary, "- Network: {}", network)?;
+    }
+    for (i, msg) in messages.iter().enumerate() {
+        let (sender, canister_id, method_name, arg) = msg.parse_raw()?;
+        writeln!(summary, "\n## {}. `{}`\n", i + 1, method_name)?;
+        if let Some(operation) = describe_operation(canister_id, &method_name, &arg) {
+            writeln!(summary, "- Operation: {}", operation)?;
+        }
+        writeln!(summary, "- Call type: {}", msg.call_type)?;
+        writeln!(summary, "- Sender: `{}`", sender)?;
+        writeln!(summary, "- Canister id: `{}`", canister_id)?;
+        writeln!(
+            summary,
+            "- Expires: {}",
+            format_timestamp_seconds(msg.expiry_seconds()?)
+        )?;
+        if let Some(request_id) = &msg.request_id {
+            writeln!(summary, "- Request id: `{}`", request_id)?;
+        }
+        let args = get_idl_string(
+            &arg,
+            canister_id,
+            &method_name,
+            ArgPart::Args,
+            OutputFormat::Pretty,
+        )
+        .unwrap_or_else(|_| hex::encode(&arg));
+        writeln!(summary, "\n```\n{}\n```", args)?;
+    }
+    writeln!(
+        summary,
+        "\n## Sign-off\n\nReviewed by: ______________________  Date: ____________\n\n\
+         Signature: ______________________"
+    )?;
+    Ok(summary)
+}
+
+// Describes the transfers of ICP, whose amounts and recipients matter most in a review.
+fn describe_operation(canister_id: Principal, method_name: &str, arg: &[u8]) -> Option<String> {
+    if canister_id != ledger_canister_id() || method_name != "send_dfx" {
+        return None;
+    }
+    let args = Decode!(arg, SendArgs).ok()?;
+    Some(format!(
+        "transfer of {} ICP to `{}` (fee {} ICP, memo {})",
+        Icp::from(E8s::from(args.amount)),
+        args.to,
+        Icp::from(E8s::from(args.fee)),
+        args.memo
+    ))
+}
+
+/// Fails unless the summary was rendered for the bundle with the given hash.
+pub fn verify(summary: &str, bundle_hash: &[u8; 32]) -> AnyhowResult {
+    let recorded = summary
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(HASH_LABEL))
+        .map(|hash| hash.trim().trim_matches('`'))
+        .ok_or_else(|| anyhow!("The summary does not record the hash of a bundle"))?;
+    if !recorded.eq_ignore_ascii_case(&hex::encode(bundle_hash)) {
+        bail!(
+            "The summary is the one of the bundle with hash {}, but the bundle has hash {}. \
+             No message was sent.",
+            recorded,
+            hex::encode(bundle_hash)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, verify};
+    use crate::commands::transfer::SendArgs;
+    use crate::lib::{audit::bundle_hash, ledger_canister_id, nns_types::Memo};
+    use candid::Encode;
+    use ledger_canister::Tokens;
+    use serde_cbor::Value;
+    use std::collections::BTreeMap;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    // A bundle with an unsigned transfer of 1.5 ICP.
+    fn transfer_bundle() -> String {
+        let arg = Encode!(&SendArgs {
+            memo: Memo(7),
+            amount: Tokens::from_e8s(150_000_000),
+            fee: Tokens::from_e8s(10_000),
+            from_subaccount: None,
+            to: "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752".to_string(),
+            created_at_time: None,
+        })
+        .unwrap();
+        let mut content = BTreeMap::new();
+        content.insert(text("request_type"), text("call"));
+        content.insert(
+            text("canister_id"),
+            Value::Bytes(ledger_canister_id().as_slice().to_vec()),
+        );
+        content.insert(text("method_name"), text("send_dfx"));
+        content.insert(text("arg"), Value::Bytes(arg));
+        content.insert(text("sender"), Value::Bytes(vec![0x04]));
+        content.insert(
+            text("ingress_expiry"),
+            Value::Integer(1_685_570_400_000_000_000),
+        );
+        let mut envelope = BTreeMap::new();
+        envelope.insert(text("content"), Value::Map(content));
+        let content = hex::encode(serde_cbor::to_vec(&Value::Map(envelope)).unwrap());
+        format!(
+            r#"{{"call_type":"update","request_id":null,"content":"{}"}}"#,
+            content
+        )
+    }
+
+    #[test]
+    fn renders_transfers() {
+        let bundle = transfer_bundle();
+        let summary = render(&bundle).unwrap();
+        assert!(summary.contains(&format!(
+            "- Bundle SHA-256: `{}`",
+            hex::encode(bundle_hash(bundle.as_bytes()))
+        )));
+        assert!(summary.contains(
+            "- Operation: transfer of 1.50000000 ICP to \
+             `345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752` \
+             (fee 0.00010000 ICP, memo 7)"
+        ));
+        assert!(summary.contains("- Canister id: `ryjl3-tyaaa-aaaaa-aaaba-cai`"));
+    }
+
+    #[test]
+    fn verifies_the_bundle_hash() {
+        let bundle = transfer_bundle();
+        let summary = render(&bundle).unwrap();
+        verify(&summary, &bundle_hash(bundle.as_bytes())).unwrap();
+        assert!(verify(&summary, &bundle_hash(b"[]")).is_err());
+        assert!(verify("# Signed messages", &bundle_hash(bundle.as_bytes())).is_err());
+    }
+}
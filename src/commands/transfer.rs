@@ -1,91 +1,202 @@
-use crate::commands::send::{Memo, SendArgs};
 use crate::lib::{
-    ledger_canister_id,
+    audit::paid_recipients,
+    contacts::{is_contact, load_contacts, resolve_account_id},
+    idempotency::{self, KeyedTransfer},
+    is_quiet, ledger_canister_id,
+    nns_types::{
+        account_id::parse_account_id,
+        icpts::{E8s, Icp},
+        payment_request::PaymentRequest,
+        Memo, Subaccount as NnsSubaccount,
+    },
     signing::{sign_ingress_with_request_status_query, IngressWithRequestId},
     AnyhowResult, AuthInfo,
 };
-use anyhow::{anyhow, bail, Context};
-use candid::Encode;
+use anyhow::{bail, Context};
+use candid::{CandidType, Encode};
 use clap::Parser;
-use ledger_canister::{Tokens, DEFAULT_TRANSFER_FEE};
+use ledger_canister::{Subaccount, Tokens, DEFAULT_TRANSFER_FEE};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(CandidType, Deserialize)]
+pub struct TimeStamp {
+    pub timestamp_nanos: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SendArgs {
+    pub memo: Memo,
+    pub amount: Tokens,
+    pub fee: Tokens,
+    pub from_subaccount: Option<Subaccount>,
+    pub to: String,
+    pub created_at_time: Option<TimeStamp>,
+}
 
 /// Signs an ICP transfer transaction.
 #[derive(Default, Parser)]
 pub struct TransferOpts {
-    /// Destination account.
+    /// Destination account. Also accepts a dashboard URL containing the account id, or a
+    /// payment request such as `icp:<account id>?amount=10&memo=42`, which provides the
+    /// amount and the memo, or a contact as `@<name>`.
     pub to: String,
 
-    /// Amount of ICPs to transfer (with up to 8 decimal digits after comma).
+    /// Amount of ICPs to transfer (with up to 8 decimal digits after comma). Required unless
+    /// the destination is a payment request with an amount.
     #[clap(long, validator(token_amount_validator))]
-    pub amount: String,
+    pub amount: Option<String>,
 
-    /// Reference number, default is 0.
+    /// Reference number, default is 0. Also accepts hex digits prefixed with 0x, or text such
+    /// as TPUP, which is stored as its bytes if it has up to 8 characters and hashed otherwise.
     #[clap(long, validator(memo_validator))]
     pub memo: Option<String>,
 
     /// Transaction fee, default is 10000 e8s.
     #[clap(long, validator(token_amount_validator))]
     pub fee: Option<String>,
+
+    /// The subaccount (64 hex digits) of the account to transfer from, the default account of
+    /// the sender otherwise.
+    #[clap(long)]
+    pub from_subaccount: Option<NnsSubaccount>,
+
+    /// A key identifying the payment, e.g. in a batch. The memo defaults to a number derived
+    /// from the key, and the transfer is created at the time the key was first used, so the
+    /// ledger refuses to execute the same payment twice within 24 hours.
+    #[clap(long)]
+    pub idempotency_key: Option<String>,
+
+    /// The audit log of `send`, whose recipients are known like contacts. Transfers to a
+    /// recipient which is neither a contact nor paid in the audit log must be confirmed, once
+    /// there is an address book or an audit log.
+    #[clap(long)]
+    pub audit_log: Option<PathBuf>,
+
+    /// Confirms that the recipient is new, instead of asking for the account id to be typed
+    /// again. Required with --quiet for new recipients.
+    #[clap(long)]
+    pub new_recipient: bool,
+
+    /// Set when input is not available for confirmations, which then fail as in quiet mode.
+    #[clap(skip)]
+    pub no_prompt: bool,
 }
 
 pub fn exec(auth: &AuthInfo, opts: TransferOpts) -> AnyhowResult<Vec<IngressWithRequestId>> {
-    let amount = parse_tokens(&opts.amount).context("Cannot parse amount")?;
-    let fee = opts.fee.map_or(Ok(DEFAULT_TRANSFER_FEE), |v| {
-        parse_tokens(&v).context("Cannot parse fee")
+    let to = resolve_account_id(&opts.to)?;
+    let request = if PaymentRequest::is_uri(&to) {
+        PaymentRequest::from_uri(&to)?
+    } else {
+        PaymentRequest {
+            to: parse_account_id(&to)?,
+            amount: None,
+            memo: None,
+        }
+    };
+    let amount = match (opts.amount, request.amount) {
+        (Some(amount), requested) => {
+            let amount = amount.parse::<Icp>()?;
+            if requested.map_or(false, |requested| requested != amount) {
+                bail!("The amount differs from the amount of the payment request");
+            }
+            amount
+        }
+        (None, Some(requested)) => requested,
+        (None, None) => bail!("The amount to transfer is missing"),
+    };
+    let fee = opts.fee.map_or(Ok(E8s::from(DEFAULT_TRANSFER_FEE)), |v| {
+        v.parse::<Icp>().map(E8s::from).context("Cannot parse fee")
     })?;
-    let memo = Memo(
-        opts.memo
-            .unwrap_or_else(|| "0".to_string())
-            .parse::<u64>()
-            .context("Failed to parse memo as unsigned integer")?,
-    );
-    let to = opts.to;
+    let memo = match (opts.memo, request.memo) {
+        (Some(memo), requested) => {
+            let memo = memo.parse::<Memo>()?.0;
+            if requested.map_or(false, |requested| requested != memo) {
+                bail!("The memo differs from the memo of the payment request");
+            }
+            memo
+        }
+        (None, requested) => requested
+            .or_else(|| {
+                opts.idempotency_key
+                    .as_deref()
+                    .map(idempotency::memo_for_key)
+            })
+            .unwrap_or(0),
+    };
+    let to = request.to.to_hex();
+    if !opts.new_recipient {
+        check_recipient(&to, opts.audit_log.as_deref(), opts.no_prompt)?;
+    }
+    let created_at_time = match &opts.idempotency_key {
+        Some(key) => Some(TimeStamp {
+            timestamp_nanos: idempotency::created_at_time(
+                key,
+                KeyedTransfer {
+                    to: to.clone(),
+                    amount_e8s: E8s::from(amount).get(),
+                    memo,
+                    created_at_time_nanos: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)?
+                        .as_nanos() as u64,
+                },
+            )?,
+        }),
+        None => None,
+    };
 
     let args = Encode!(&SendArgs {
-        memo,
-        amount,
-        fee,
-        from_subaccount: None,
+        memo: Memo(memo),
+        amount: E8s::from(amount).into(),
+        fee: fee.into(),
+        from_subaccount: opts.from_subaccount.map(Subaccount::from),
         to,
-        created_at_time: None,
+        created_at_time,
     })?;
 
     let msg = sign_ingress_with_request_status_query(auth, ledger_canister_id(), "send_dfx", args)?;
     Ok(vec![msg])
 }
 
-fn new_tokens(tokens: u64, e8s: u64) -> AnyhowResult<Tokens> {
-    Tokens::new(tokens, e8s)
-        .map_err(|err| anyhow!(err))
-        .context("Cannot create new tokens structure")
-}
-
-fn parse_tokens(amount: &str) -> AnyhowResult<Tokens> {
-    let parse = |s: &str| {
-        s.parse::<u64>()
-            .context("Failed to parse tokens as unsigned integer")
-    };
-    match &amount.split('.').collect::<Vec<_>>().as_slice() {
-        [tokens] => new_tokens(parse(tokens)?, 0),
-        [tokens, e8s] => {
-            let mut e8s = e8s.to_string();
-            while e8s.len() < 8 {
-                e8s.push('0');
-            }
-            let e8s = &e8s[..8];
-            new_tokens(parse(tokens)?, parse(e8s)?)
+// Asks for the account id of a new recipient to be typed again, if an address book or an audit
+// log is kept.
+fn check_recipient(to: &str, audit_log: Option<&Path>, no_prompt: bool) -> AnyhowResult {
+    let contacts = load_contacts()?;
+    if contacts.is_empty() && audit_log.is_none() {
+        return Ok(());
+    }
+    if is_contact(to, &contacts)? {
+        return Ok(());
+    }
+    if let Some(path) = audit_log {
+        if paid_recipients(path)?.contains(to) {
+            return Ok(());
         }
-        _ => bail!("Cannot parse amount {}", amount),
     }
+    if is_quiet() || no_prompt {
+        bail!(
+            "{} is a new recipient. Refusing to ask for confirmation; use --new-recipient to \
+             sign the transfer.",
+            to
+        );
+    }
+    eprintln!(
+        "WARNING: {} is a new recipient: it is not a contact and was never paid before.",
+        to
+    );
+    eprintln!("Type the account id again to confirm:");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    if !parse_account_id(&input).map_or(false, |account_id| account_id.to_hex() == to) {
+        bail!("The account ids do not match, no transfer was signed.");
+    }
+    Ok(())
 }
 
-fn token_amount_validator(tokens: &str) -> AnyhowResult<()> {
-    parse_tokens(tokens).map(|_| ())
+pub fn token_amount_validator(tokens: &str) -> AnyhowResult<()> {
+    tokens.parse::<Icp>().map(|_| ())
 }
 
-fn memo_validator(memo: &str) -> Result<(), String> {
-    if memo.parse::<u64>().is_ok() {
-        return Ok(());
-    }
-    Err("Memo must be an unsigned integer".to_string())
+fn memo_validator(memo: &str) -> AnyhowResult<()> {
+    memo.parse::<Memo>().map(|_| ())
 }
@@ -0,0 +1,423 @@
+//! An interactive dashboard of the online state, for operators who keep an eye on it: the
+//! balances of accounts, the neurons of the key, the open proposals and the submissions recorded
+//! by `send --state-dir`, refreshed periodically.
+
+use crate::commands::{
+    fetch::{
+        fetch_balances, fetch_neurons, fetch_proposals, resolve_accounts, Balance, ProposalSummary,
+    },
+    list_neurons::NeuronReportRow,
+};
+use crate::lib::{
+    get_agent, get_network,
+    nns_types::icpts::{E8s, Icp},
+    queue::{MessageState, Queue},
+    timestamp::{format_timestamp_seconds, now_seconds},
+    AnyhowResult, AuthInfo,
+};
+use clap::Parser;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ic_agent::Agent;
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::Spans,
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState, Tabs},
+    Frame, Terminal,
+};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Displays the balances, the neurons, the open proposals and the pending submissions in an
+/// interactive dashboard, refreshed periodically.
+#[derive(Parser)]
+pub struct TuiOpts {
+    /// The account whose balance is displayed (can be repeated), the account of the key by
+    /// default. Also accepts a principal or a contact as `@<name>`.
+    #[clap(long = "account", multiple_occurrences(true))]
+    accounts: Vec<String>,
+
+    /// The state directory of a submission by `send --state-dir`, whose messages are displayed
+    /// with their states (can be repeated).
+    #[clap(long = "state-dir", multiple_occurrences(true))]
+    state_dirs: Vec<PathBuf>,
+
+    /// The number of seconds between refreshes.
+    #[clap(long, default_value = "30")]
+    refresh: u64,
+}
+
+const TABS: [&str; 4] = ["Balances", "Neurons", "Proposals", "Requests"];
+
+const BALANCE_HEADER: &[&str] = &["Account", "Balance (ICP)"];
+const BALANCE_WIDTHS: &[Constraint] = &[Constraint::Length(66), Constraint::Min(20)];
+const NEURON_HEADER: &[&str] = &[
+    "Neuron",
+    "Stake (ICP)",
+    "Maturity (ICP)",
+    "State",
+    "Dissolve delay",
+    "Age",
+];
+const NEURON_WIDTHS: &[Constraint] = &[
+    Constraint::Length(20),
+    Constraint::Length(20),
+    Constraint::Length(20),
+    Constraint::Length(12),
+    Constraint::Length(16),
+    Constraint::Min(10),
+];
+const PROPOSAL_HEADER: &[&str] = &["Proposal", "Topic", "Deadline", "Title"];
+const PROPOSAL_WIDTHS: &[Constraint] = &[
+    Constraint::Length(10),
+    Constraint::Length(24),
+    Constraint::Length(26),
+    Constraint::Min(20),
+];
+const REQUEST_HEADER: &[&str] = &["State directory", "Request id", "State"];
+const REQUEST_WIDTHS: &[Constraint] = &[
+    Constraint::Percentage(30),
+    Constraint::Length(68),
+    Constraint::Min(10),
+];
+
+/// The state of a message of a submission.
+struct RequestRow {
+    state_dir: String,
+    request_id: String,
+    state: MessageState,
+}
+
+/// The data displayed by the dashboard.
+#[derive(Default)]
+struct Dashboard {
+    balances: Vec<Balance>,
+    neurons: Vec<NeuronReportRow>,
+    proposals: Vec<ProposalSummary>,
+    requests: Vec<RequestRow>,
+}
+
+struct App {
+    network: String,
+    dashboard: Dashboard,
+    tab: usize,
+    selected: [TableState; 4],
+    status: String,
+}
+
+pub async fn exec(auth: &AuthInfo, opts: TuiOpts) -> AnyhowResult {
+    let agent = get_agent(auth)?;
+    // Without a key, only the accounts given explicitly have a balance to display.
+    let accounts = if matches!(auth, AuthInfo::NoAuth) && opts.accounts.is_empty() {
+        Vec::new()
+    } else {
+        resolve_accounts(auth, &opts.accounts)?
+    };
+    let mut app = App {
+        network: get_network(),
+        dashboard: Dashboard::default(),
+        tab: 0,
+        selected: Default::default(),
+        status: String::new(),
+    };
+    let _guard = TerminalGuard::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    let refresh = Duration::from_secs(opts.refresh.max(1));
+    let mut next_refresh = Instant::now();
+    loop {
+        if Instant::now() >= next_refresh {
+            app.status = "Refreshing...".to_string();
+            terminal.draw(|f| app.draw(f))?;
+            app.status = match fetch_dashboard(&agent, auth, &accounts, &opts.state_dirs).await {
+                Ok(dashboard) => {
+                    app.dashboard = dashboard;
+                    app.clamp_selections();
+                    format!("Refreshed at {}", format_timestamp_seconds(now_seconds()?))
+                }
+                // The previous data stays displayed until a refresh succeeds.
+                Err(err) => format!("Refresh failed: {:#}", err),
+            };
+            next_refresh = Instant::now() + refresh;
+        }
+        terminal.draw(|f| app.draw(f))?;
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Char('r') => next_refresh = Instant::now(),
+            KeyCode::Tab | KeyCode::Right => app.tab = (app.tab + 1) % TABS.len(),
+            KeyCode::BackTab | KeyCode::Left => app.tab = (app.tab + TABS.len() - 1) % TABS.len(),
+            KeyCode::Char(c @ '1'..='4') => app.tab = c as usize - '1' as usize,
+            KeyCode::Down | KeyCode::Char('j') => app.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => app.move_selection(-1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+async fn fetch_dashboard(
+    agent: &Agent,
+    auth: &AuthInfo,
+    accounts: &[String],
+    state_dirs: &[PathBuf],
+) -> AnyhowResult<Dashboard> {
+    let balances = fetch_balances(agent, accounts.to_vec()).await?;
+    // Neurons are only readable by their controller and hot keys.
+    let neurons = if matches!(auth, AuthInfo::NoAuth) {
+        Vec::new()
+    } else {
+        fetch_neurons(agent).await?
+    };
+    let proposals = fetch_proposals(agent).await?;
+    let mut requests = Vec::new();
+    for dir in state_dirs {
+        let (queue, _) = Queue::open(dir)?;
+        requests.extend(
+            queue
+                .states()
+                .into_iter()
+                .map(|(request_id, state)| RequestRow {
+                    state_dir: dir.display().to_string(),
+                    request_id,
+                    state,
+                }),
+        );
+    }
+    Ok(Dashboard {
+        balances,
+        neurons,
+        proposals,
+        requests,
+    })
+}
+
+impl App {
+    fn draw<B: Backend>(&mut self, f: &mut Frame<B>) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ]
+                .as_ref(),
+            )
+            .split(f.size());
+        let tabs = Tabs::new(TABS.iter().map(|&title| Spans::from(title)).collect())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("quill ({})", self.network)),
+            )
+            .select(self.tab)
+            .highlight_style(Style::default().fg(Color::Yellow));
+        f.render_widget(tabs, chunks[0]);
+
+        let (header, widths, rows) = self.dashboard.table(self.tab);
+        let table = Table::new(rows.into_iter().map(Row::new))
+            .header(
+                Row::new(header.iter().copied())
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().borders(Borders::ALL))
+            .widths(widths)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(table, chunks[1], &mut self.selected[self.tab]);
+
+        let help = format!(
+            "{} | Tab/1-4: switch, Up/Down: select, r: refresh, q: quit",
+            self.status
+        );
+        f.render_widget(Paragraph::new(help), chunks[2]);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.dashboard.len(self.tab);
+        let state = &mut self.selected[self.tab];
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+        let current = state.selected().map_or(-1, |i| i as isize);
+        state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    // Keeps the selections within the tables, which may have shrunk on a refresh.
+    fn clamp_selections(&mut self) {
+        for tab in 0..TABS.len() {
+            let len = self.dashboard.len(tab);
+            let state = &mut self.selected[tab];
+            if let Some(selected) = state.selected() {
+                state.select(if len == 0 {
+                    None
+                } else {
+                    Some(selected.min(len - 1))
+                });
+            }
+        }
+    }
+}
+
+impl Dashboard {
+    fn len(&self, tab: usize) -> usize {
+        match tab {
+            0 => self.balances.len(),
+            1 => self.neurons.len(),
+            2 => self.proposals.len(),
+            _ => self.requests.len(),
+        }
+    }
+
+    // Returns the header, the column widths and the rows of the table of a tab.
+    fn table(
+        &self,
+        tab: usize,
+    ) -> (
+        &'static [&'static str],
+        &'static [Constraint],
+        Vec<Vec<String>>,
+    ) {
+        match tab {
+            0 => (BALANCE_HEADER, BALANCE_WIDTHS, balance_rows(&self.balances)),
+            1 => (NEURON_HEADER, NEURON_WIDTHS, neuron_rows(&self.neurons)),
+            2 => (
+                PROPOSAL_HEADER,
+                PROPOSAL_WIDTHS,
+                proposal_rows(&self.proposals),
+            ),
+            _ => (REQUEST_HEADER, REQUEST_WIDTHS, request_rows(&self.requests)),
+        }
+    }
+}
+
+fn icp(e8s: u64) -> String {
+    Icp::from(E8s::new(e8s)).to_string()
+}
+
+fn days(seconds: u64) -> String {
+    format!("{} days", seconds / 86400)
+}
+
+fn balance_rows(balances: &[Balance]) -> Vec<Vec<String>> {
+    balances
+        .iter()
+        .map(|balance| vec![balance.account.clone(), icp(balance.balance_e8s)])
+        .collect()
+}
+
+fn neuron_rows(neurons: &[NeuronReportRow]) -> Vec<Vec<String>> {
+    neurons
+        .iter()
+        .map(|neuron| {
+            vec![
+                neuron.id.to_string(),
+                icp(neuron.stake_e8s),
+                icp(neuron.maturity_e8s),
+                neuron.state.to_string(),
+                days(neuron.dissolve_delay_seconds),
+                days(neuron.age_seconds),
+            ]
+        })
+        .collect()
+}
+
+fn proposal_rows(proposals: &[ProposalSummary]) -> Vec<Vec<String>> {
+    proposals
+        .iter()
+        .map(|proposal| {
+            vec![
+                proposal.id.to_string(),
+                proposal.topic.clone(),
+                proposal
+                    .deadline_timestamp_seconds
+                    .map_or_else(String::new, format_timestamp_seconds),
+                proposal.title.clone().unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
+
+fn request_rows(requests: &[RequestRow]) -> Vec<Vec<String>> {
+    requests
+        .iter()
+        .map(|request| {
+            vec![
+                request.state_dir.clone(),
+                request.request_id.clone(),
+                format!("{:?}", request.state).to_lowercase(),
+            ]
+        })
+        .collect()
+}
+
+// Switches the terminal to raw mode on an alternate screen, and restores it when dropped, also
+// when the dashboard fails.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> AnyhowResult<Self> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+#[test]
+fn test_rows() {
+    let neurons = vec![NeuronReportRow {
+        id: 123,
+        stake_e8s: 150_000_000,
+        maturity_e8s: 1,
+        state: "locked",
+        dissolve_delay_seconds: 8 * 365 * 86400,
+        age_seconds: 86399,
+        hot_keys: Vec::new(),
+    }];
+    assert_eq!(
+        neuron_rows(&neurons),
+        vec![vec![
+            "123",
+            "1.50000000",
+            "0.00000001",
+            "locked",
+            "2920 days",
+            "0 days"
+        ]]
+    );
+    let requests = vec![RequestRow {
+        state_dir: "state".to_string(),
+        request_id: "0xaa".to_string(),
+        state: MessageState::Submitted,
+    }];
+    assert_eq!(
+        request_rows(&requests),
+        vec![vec!["state", "0xaa", "submitted"]]
+    );
+    let dashboard = Dashboard {
+        requests,
+        ..Default::default()
+    };
+    assert_eq!(dashboard.len(3), 1);
+    assert_eq!(dashboard.table(0).2.len(), 0);
+}
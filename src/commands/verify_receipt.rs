@@ -0,0 +1,114 @@
+use crate::commands::request_status::Receipt;
+use crate::lib::{
+    get_agent, get_idl_string, read_from_file, timestamp::format_timestamp_seconds, AnyhowResult,
+    ArgPart, AuthInfo, OutputFormat,
+};
+use anyhow::{anyhow, bail, Context};
+use candid::Principal;
+use clap::Parser;
+use ic_agent::hash_tree::{Label, LookupResult};
+use ic_agent::{Certificate, RequestId};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Verifies a receipt saved by `send --save-certificates` against the IC root key, without
+/// contacting the IC, and prints the certified reply and the time of the certificate.
+#[derive(Parser)]
+pub struct VerifyReceiptOpts {
+    /// Path to the receipt.
+    certificate_file: PathBuf,
+
+    /// The format of the reply: hex ("raw"), compact candid ("idl"), pretty-printed candid
+    /// ("pp") or JSON ("json").
+    #[clap(long, arg_enum, default_value = "pp")]
+    output_type: OutputFormat,
+}
+
+pub async fn exec(opts: VerifyReceiptOpts, fetch_root_key: bool) -> AnyhowResult {
+    let receipt: Receipt = serde_json::from_str(&read_from_file(&opts.certificate_file)?)
+        .context("Invalid receipt")?;
+    let canister_id =
+        Principal::from_text(&receipt.canister_id).context("Invalid argument: canister_id")?;
+    let request_id =
+        RequestId::from_str(&receipt.request_id).context("Invalid argument: request_id")?;
+    let certificate = hex::decode(&receipt.certificate).context("Invalid certificate encoding")?;
+    let certificate: Certificate =
+        serde_cbor::from_slice(&certificate).context("Invalid certificate")?;
+
+    // The agent is only used for its root key, which is embedded unless it is fetched from a
+    // local instance.
+    let agent = get_agent(&AuthInfo::NoAuth)?;
+    if fetch_root_key {
+        agent.fetch_root_key().await?;
+    }
+    agent
+        .verify(&certificate, canister_id, false)
+        .context("The certificate is not signed by the IC")?;
+
+    let status = lookup(&certificate, request_path(&request_id, "status"))?;
+    if status != b"replied" {
+        bail!(
+            "The request was not replied to, its status is {}",
+            String::from_utf8_lossy(status)
+        );
+    }
+    let reply = lookup(&certificate, request_path(&request_id, "reply"))?;
+    let time = decode_leb128(lookup(&certificate, vec!["time".into()])?)?;
+
+    println!("Certificate verified.\n");
+    println!("  Canister id: {}", canister_id);
+    println!("  Request id:  {}", receipt.request_id);
+    println!(
+        "  Time:        {}",
+        format_timestamp_seconds(time / 1_000_000_000)
+    );
+    let reply = match &receipt.method_name {
+        Some(method_name) => get_idl_string(
+            reply,
+            canister_id,
+            method_name,
+            ArgPart::Rets,
+            opts.output_type,
+        )?,
+        None => hex::encode(reply),
+    };
+    println!("  Reply:       {}", reply);
+    Ok(())
+}
+
+fn request_path(request_id: &RequestId, leaf: &str) -> Vec<Label> {
+    vec![
+        "request_status".into(),
+        request_id.as_slice().to_vec().into(),
+        leaf.into(),
+    ]
+}
+
+fn lookup<'a>(certificate: &'a Certificate, path: Vec<Label>) -> AnyhowResult<&'a [u8]> {
+    match certificate.tree.lookup_path(&path) {
+        LookupResult::Found(value) => Ok(value),
+        _ => Err(anyhow!("The certificate does not contain {:?}", path)),
+    }
+}
+
+// The certified time is an unsigned LEB128 number of nanoseconds.
+fn decode_leb128(bytes: &[u8]) -> AnyhowResult<u64> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i >= 10 {
+            break;
+        }
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("Invalid LEB128 number")
+}
+
+#[test]
+fn test_decode_leb128() {
+    assert_eq!(decode_leb128(&[0x00]).unwrap(), 0);
+    assert_eq!(decode_leb128(&[0xe5, 0x8e, 0x26]).unwrap(), 624_485);
+    assert!(decode_leb128(&[0x80]).is_err());
+}
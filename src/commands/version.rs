@@ -0,0 +1,179 @@
+use crate::{
+    lib::{get_local_candid, governance_canister_id, AnyhowResult, EMBEDDED_CANDID},
+    CliOpts,
+};
+use anyhow::{anyhow, bail, Context};
+use candid::{
+    parser::typing::{check_prog, TypeEnv},
+    types::Type,
+    IDLProg,
+};
+use clap::{crate_version, CommandFactory, Parser};
+use openssl::sha::sha256;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// The ways of providing the signing identity.
+const IDENTITY_BACKENDS: &[&str] = &[
+    "pem-file",
+    "seed-file",
+    #[cfg(feature = "hsm")]
+    "hsm",
+];
+
+/// Prints the version of quill.
+#[derive(Parser)]
+pub struct VersionOpts {
+    /// Print the build information, the embedded interfaces and the supported features as JSON.
+    #[clap(long, alias("json"))]
+    verbose: bool,
+
+    /// Checks that the SHA-256 hash of this binary is listed in the given file, e.g. the
+    /// checksums published with a reproducible build (in the format of `sha256sum`).
+    #[clap(long, conflicts_with("verbose"))]
+    verify_binary: Option<PathBuf>,
+}
+
+const GIT_COMMIT: &str = env!("QUILL_GIT_COMMIT");
+
+// The lock file the binary was built with, which pins the versions of all dependencies.
+const CARGO_LOCK: &[u8] = include_bytes!("../../Cargo.lock");
+
+#[derive(Serialize)]
+struct BuildInfo {
+    git_commit: &'static str,
+    cargo_lock_sha256: String,
+    target: &'static str,
+}
+
+#[derive(Serialize)]
+struct CandidFile {
+    file: &'static str,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct VersionReport {
+    version: &'static str,
+    build: BuildInfo,
+    candid: Vec<CandidFile>,
+    commands: Vec<String>,
+    governance_commands: Vec<String>,
+    identity_backends: &'static [&'static str],
+}
+
+pub fn exec(opts: VersionOpts) -> AnyhowResult {
+    if let Some(expected) = opts.verify_binary {
+        return verify_binary(&expected);
+    }
+    if !opts.verbose {
+        println!("quill {}", crate_version!());
+        return Ok(());
+    }
+    let report = VersionReport {
+        version: crate_version!(),
+        build: BuildInfo {
+            git_commit: GIT_COMMIT,
+            cargo_lock_sha256: hex::encode(sha256(CARGO_LOCK)),
+            target: env!("QUILL_TARGET"),
+        },
+        candid: EMBEDDED_CANDID
+            .iter()
+            .map(|&(file, bytes)| CandidFile {
+                file,
+                sha256: hex::encode(sha256(bytes)),
+            })
+            .collect(),
+        commands: commands(),
+        governance_commands: governance_commands()?,
+        identity_backends: IDENTITY_BACKENDS,
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+// Compares the hash of the running binary with the hashes listed in the file.
+fn verify_binary(expected: &std::path::Path) -> AnyhowResult {
+    let path = std::env::current_exe().context("Cannot locate the quill binary")?;
+    let binary = std::fs::read(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let hash = hex::encode(sha256(&binary));
+    let listed = std::fs::read_to_string(expected)
+        .with_context(|| format!("Cannot read {}", expected.display()))?;
+    if !listed_hashes(&listed).any(|listed| listed.eq_ignore_ascii_case(&hash)) {
+        bail!(
+            "The SHA-256 hash {} of {} is not listed in {}.",
+            hash,
+            path.display(),
+            expected.display()
+        );
+    }
+    println!(
+        "The binary matches the published build (SHA-256 {}, commit {}).",
+        hash, GIT_COMMIT
+    );
+    Ok(())
+}
+
+// Returns the hashes of a file with a hash per line, optionally followed by a file name as in
+// the output of `sha256sum`.
+fn listed_hashes(listed: &str) -> impl Iterator<Item = &str> {
+    listed
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+}
+
+/// Returns the names of all commands, including subcommands such as `sns stake-neuron`.
+fn commands() -> Vec<String> {
+    let mut names = Vec::new();
+    for command in CliOpts::command().get_subcommands() {
+        let subcommands = command.get_subcommands().collect::<Vec<_>>();
+        if subcommands.is_empty() {
+            names.push(command.get_name().to_string());
+        }
+        for subcommand in subcommands {
+            names.push(format!("{} {}", command.get_name(), subcommand.get_name()));
+        }
+    }
+    names
+}
+
+/// Returns the neuron management commands of the embedded governance interface.
+fn governance_commands() -> AnyhowResult<Vec<String>> {
+    let idl = get_local_candid(governance_canister_id())?;
+    let ast = candid::pretty_parse::<IDLProg>("governance.did", &idl)
+        .map_err(|err| anyhow!("{}", err))?;
+    let mut env = TypeEnv::new();
+    check_prog(&mut env, &ast)?;
+    match env.find_type("Command")? {
+        Type::Variant(fields) => Ok(fields.iter().map(|field| field.id.to_string()).collect()),
+        _ => bail!("The governance Command type is not a variant"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{commands, governance_commands, listed_hashes};
+
+    #[test]
+    fn lists_commands() {
+        let commands = commands();
+        assert!(commands.iter().any(|name| name == "neuron-manage"));
+        assert!(commands.iter().any(|name| name == "sns stake-neuron"));
+        assert!(!commands.iter().any(|name| name == "sns"));
+
+        let governance_commands = governance_commands().unwrap();
+        assert!(governance_commands.iter().any(|name| name == "Disburse"));
+        assert!(governance_commands
+            .iter()
+            .any(|name| name == "RefreshVotingPower"));
+    }
+
+    #[test]
+    fn reads_listed_hashes() {
+        let listed = "abc123  quill-linux-x86_64\n\ndef456 *quill-macos-x86_64\n";
+        assert_eq!(
+            listed_hashes(listed).collect::<Vec<_>>(),
+            vec!["abc123", "def456"]
+        );
+    }
+}
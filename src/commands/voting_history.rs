@@ -0,0 +1,353 @@
+//! Reports how neurons voted on the recent proposals, to check that followees actually vote.
+
+use crate::commands::{list_neurons::format_table, neuron_manage::ONE_DAY_SECONDS};
+use crate::lib::{
+    get_agent, governance_canister_id,
+    nns_types::{neuron_id::parse_neuron_id, topic::topic_name},
+    timestamp::{format_timestamp_seconds, now_seconds},
+    AnyhowResult, AuthInfo,
+};
+use anyhow::{anyhow, Context};
+use candid::{CandidType, Decode, Deserialize, Encode};
+use clap::Parser;
+use ic_agent::Agent;
+use ic_nns_common::pb::v1::ProposalId;
+use ic_nns_governance::pb::v1::GovernanceError;
+
+// Governance only keeps the ballots of the last proposals a neuron voted on.
+const MAX_RECENT_BALLOTS: usize = 100;
+
+/// Followees whose last vote is older than this are reported as inactive.
+pub const INACTIVE_FOLLOWEE_DAYS: u64 = 30;
+
+// Only neurons following the managed neuron vote on `ManageNeuron` proposals, and the exchange
+// rate and KYC proposals are not voted on.
+const EXCLUDED_TOPICS: &[i32] = &[1, 2, 9];
+
+/// Lists how a neuron voted on the recent proposals and the proposals it missed, e.g. to check
+/// that its followees actually vote. The replies are not certified.
+#[derive(Parser)]
+pub struct VotingHistoryOpts {
+    #[clap(parse(try_from_str = parse_neuron_id))]
+    neuron_id: u64,
+
+    /// The number of recent proposals to report on.
+    #[clap(long, default_value = "100")]
+    limit: u32,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct BallotInfo {
+    pub proposal_id: Option<ProposalId>,
+    pub vote: i32,
+}
+
+/// The ballots a neuron cast on the last proposals it voted on.
+#[derive(CandidType, Deserialize)]
+pub struct NeuronBallots {
+    pub created_timestamp_seconds: u64,
+    pub recent_ballots: Vec<BallotInfo>,
+}
+
+#[derive(CandidType, Deserialize)]
+enum NeuronBallotsResult {
+    Ok(NeuronBallots),
+    Err(GovernanceError),
+}
+
+#[derive(CandidType, Deserialize)]
+struct ListProposals {
+    include_reward_status: Vec<i32>,
+    before_proposal: Option<ProposalId>,
+    limit: u32,
+    exclude_topic: Vec<i32>,
+    include_status: Vec<i32>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ProposalTitle {
+    title: Option<String>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ProposalSummary {
+    id: Option<ProposalId>,
+    topic: i32,
+    reward_status: i32,
+    proposal_timestamp_seconds: u64,
+    proposal: Option<ProposalTitle>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ListProposalsResponse {
+    proposal_info: Vec<ProposalSummary>,
+}
+
+pub async fn exec(opts: VotingHistoryOpts, fetch_root_key: bool) -> AnyhowResult {
+    let agent = get_agent(&AuthInfo::NoAuth)?;
+    if fetch_root_key {
+        agent.fetch_root_key().await?;
+    }
+    let ballots = fetch_recent_ballots(&agent, opts.neuron_id).await?;
+    let proposals = fetch_proposals(&agent, opts.limit).await?;
+    print!("{}", render_history(&ballots, &proposals));
+    Ok(())
+}
+
+/// Fetches the ballots of the last proposals a neuron voted on.
+pub async fn fetch_recent_ballots(agent: &Agent, neuron_id: u64) -> AnyhowResult<NeuronBallots> {
+    let reply = agent
+        .query(&governance_canister_id(), "get_neuron_info")
+        .with_arg(Encode!(&neuron_id)?)
+        .call()
+        .await?;
+    match Decode!(&reply, NeuronBallotsResult).context("Cannot decode get_neuron_info reply")? {
+        NeuronBallotsResult::Ok(ballots) => Ok(ballots),
+        NeuronBallotsResult::Err(err) => Err(anyhow!(err.error_message)),
+    }
+}
+
+/// Returns the time of the last proposal the neuron voted on, in seconds since the Unix epoch, or
+/// None if it has no recent ballots.
+pub async fn last_vote_seconds(agent: &Agent, neuron_id: u64) -> AnyhowResult<Option<u64>> {
+    let ballots = fetch_recent_ballots(agent, neuron_id).await?;
+    let last_proposal_id = match ballots
+        .recent_ballots
+        .iter()
+        .filter_map(|ballot| ballot.proposal_id.as_ref().map(|id| id.id))
+        .max()
+    {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    let reply = agent
+        .query(&governance_canister_id(), "get_proposal_info")
+        .with_arg(Encode!(&last_proposal_id)?)
+        .call()
+        .await?;
+    Ok(Decode!(&reply, Option<ProposalSummary>)
+        .context("Cannot decode get_proposal_info reply")?
+        .map(|proposal| proposal.proposal_timestamp_seconds))
+}
+
+/// Returns warnings for the followees which have not voted recently, so that a neuron does not
+/// delegate its votes to a neuron which no longer votes.
+pub async fn inactive_followee_warnings(followees: &[u64]) -> Vec<String> {
+    let (now, agent) = match (now_seconds(), get_agent(&AuthInfo::NoAuth)) {
+        (Ok(now), Ok(agent)) => (now, agent),
+        _ => return Vec::new(),
+    };
+    let mut warnings = Vec::new();
+    for &id in followees {
+        let warning = match last_vote_seconds(&agent, id).await {
+            Ok(last_vote) => inactivity_warning(id, last_vote, now),
+            Err(err) => Some(format!(
+                "Cannot check when followee {} last voted: {}",
+                id, err
+            )),
+        };
+        warnings.extend(warning);
+    }
+    warnings
+}
+
+fn inactivity_warning(id: u64, last_vote_seconds: Option<u64>, now: u64) -> Option<String> {
+    match last_vote_seconds {
+        None => Some(format!(
+            "Followee {} has not voted on any recent proposal.",
+            id
+        )),
+        Some(last)
+            if now.saturating_sub(last) > INACTIVE_FOLLOWEE_DAYS * u64::from(ONE_DAY_SECONDS) =>
+        {
+            Some(format!(
+                "Followee {} last voted on a proposal made {} days ago, on {}.",
+                id,
+                now.saturating_sub(last) / u64::from(ONE_DAY_SECONDS),
+                format_timestamp_seconds(last)
+            ))
+        }
+        Some(_) => None,
+    }
+}
+
+// Fetches the last proposals, newest first, a page at a time.
+async fn fetch_proposals(agent: &Agent, limit: u32) -> AnyhowResult<Vec<ProposalSummary>> {
+    let mut proposals: Vec<ProposalSummary> = Vec::new();
+    while proposals.len() < limit as usize {
+        let page_limit = (limit - proposals.len() as u32).min(100);
+        let reply = agent
+            .query(&governance_canister_id(), "list_proposals")
+            .with_arg(Encode!(&ListProposals {
+                include_reward_status: Vec::new(),
+                before_proposal: proposals.last().and_then(|proposal| proposal.id.clone()),
+                limit: page_limit,
+                exclude_topic: EXCLUDED_TOPICS.to_vec(),
+                include_status: Vec::new(),
+            })?)
+            .call()
+            .await?;
+        let page = Decode!(&reply, ListProposalsResponse)
+            .context("Cannot decode list_proposals reply")?
+            .proposal_info;
+        let last_page = page.len() < page_limit as usize;
+        proposals.extend(page);
+        if last_page {
+            break;
+        }
+    }
+    Ok(proposals)
+}
+
+// Renders the vote of the neuron on every proposal made since it was created, and counts the
+// decided proposals it did not vote on.
+fn render_history(ballots: &NeuronBallots, proposals: &[ProposalSummary]) -> String {
+    let vote = |id: u64| {
+        ballots
+            .recent_ballots
+            .iter()
+            .find(|ballot| ballot.proposal_id.as_ref().map(|id| id.id) == Some(id))
+            .map(|ballot| ballot.vote)
+    };
+    // With a full list of ballots, older proposals may have been voted on without a ballot left.
+    let oldest_ballot = match ballots.recent_ballots.len() {
+        n if n >= MAX_RECENT_BALLOTS => ballots
+            .recent_ballots
+            .iter()
+            .filter_map(|ballot| ballot.proposal_id.as_ref().map(|id| id.id))
+            .min(),
+        _ => None,
+    };
+
+    let mut rows = vec![vec![
+        "Proposal".to_string(),
+        "Date".to_string(),
+        "Topic".to_string(),
+        "Vote".to_string(),
+        "Title".to_string(),
+    ]];
+    let (mut decided, mut voted, mut missed_settled) = (0, 0, 0);
+    let mut truncated = false;
+    for proposal in proposals {
+        let id = match &proposal.id {
+            Some(id) => id.id,
+            None => continue,
+        };
+        if proposal.proposal_timestamp_seconds < ballots.created_timestamp_seconds {
+            continue;
+        }
+        if oldest_ballot.map_or(false, |oldest| id < oldest) {
+            truncated = true;
+            break;
+        }
+        // Reward statuses: 1 accepting votes, 3 settled.
+        let open = proposal.reward_status == 1;
+        let vote = match vote(id) {
+            Some(1) => "Yes",
+            Some(2) => "No",
+            _ if open => "Open",
+            _ => "Missed",
+        };
+        if !open {
+            decided += 1;
+            match vote {
+                "Missed" if proposal.reward_status == 3 => missed_settled += 1,
+                "Missed" => {}
+                _ => voted += 1,
+            }
+        }
+        rows.push(vec![
+            id.to_string(),
+            format_timestamp_seconds(proposal.proposal_timestamp_seconds),
+            topic_name(proposal.topic),
+            vote.to_string(),
+            proposal
+                .proposal
+                .as_ref()
+                .and_then(|proposal| proposal.title.clone())
+                .unwrap_or_default(),
+        ]);
+    }
+
+    let mut report = format_table(&rows);
+    report.push_str(&format!(
+        "\nVoted on {} of {} decided proposals, missed {} ({} with settled rewards).\n",
+        voted,
+        decided,
+        decided - voted,
+        missed_settled
+    ));
+    if truncated {
+        report.push_str(&format!(
+            "Older proposals are not reported: governance only keeps the last {} ballots of a \
+             neuron.\n",
+            MAX_RECENT_BALLOTS
+        ));
+    }
+    report
+}
+
+#[test]
+fn test_render_history() {
+    let ballot = |id, vote| BallotInfo {
+        proposal_id: Some(ProposalId { id }),
+        vote,
+    };
+    let proposal = |id, reward_status, title: &str| ProposalSummary {
+        id: Some(ProposalId { id }),
+        topic: 4,
+        reward_status,
+        proposal_timestamp_seconds: 1_000 + id,
+        proposal: Some(ProposalTitle {
+            title: Some(title.to_string()),
+        }),
+    };
+    let ballots = NeuronBallots {
+        created_timestamp_seconds: 1_002,
+        recent_ballots: vec![ballot(5, 1), ballot(3, 2)],
+    };
+    let proposals = vec![
+        proposal(6, 1, "Pending"),
+        proposal(5, 3, "Approved"),
+        proposal(4, 3, "Forgotten"),
+        proposal(3, 2, "Rejected"),
+        proposal(2, 2, "Settling"),
+        proposal(1, 3, "Before the neuron"),
+    ];
+    let report = render_history(&ballots, &proposals);
+    let votes: Vec<_> = report
+        .lines()
+        .skip(2)
+        .take_while(|line| !line.is_empty())
+        .map(|line| {
+            let cells: Vec<_> = line.split(" | ").map(str::trim).collect();
+            (cells[0], cells[3], cells[4])
+        })
+        .collect();
+    assert_eq!(
+        votes,
+        vec![
+            ("6", "Open", "Pending"),
+            ("5", "Yes", "Approved"),
+            ("4", "Missed", "Forgotten"),
+            ("3", "No", "Rejected"),
+            ("2", "Missed", "Settling"),
+        ]
+    );
+    assert!(report
+        .ends_with("\nVoted on 2 of 4 decided proposals, missed 2 (1 with settled rewards).\n"));
+}
+
+#[test]
+fn test_inactivity_warning() {
+    let day = u64::from(ONE_DAY_SECONDS);
+    let now = 1_000 * day;
+    assert_eq!(inactivity_warning(27, Some(now - day), now), None);
+    assert_eq!(
+        inactivity_warning(27, None, now).unwrap(),
+        "Followee 27 has not voted on any recent proposal."
+    );
+    assert!(inactivity_warning(27, Some(now - 45 * day), now)
+        .unwrap()
+        .starts_with("Followee 27 last voted on a proposal made 45 days ago, on "));
+}
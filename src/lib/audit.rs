@@ -0,0 +1,90 @@
+//! Audit log of the message bundles submitted by `send`.
+
+use crate::lib::{get_identity, AnyhowResult, AuthInfo};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+
+/// One line of the audit log, describing a submitted bundle.
+#[derive(Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// Seconds since the Unix epoch at which the bundle was submitted.
+    pub timestamp_seconds: u64,
+    /// Hex-encoded SHA-256 hash of the bundle file.
+    pub bundle_sha256: String,
+    /// Request ids of the submitted update calls, up to the one which failed if the submission
+    /// was interrupted.
+    pub request_ids: Vec<String>,
+    /// Accounts receiving ICP from ledger transfers of the bundle.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipients: Vec<String>,
+    /// Counter-signature of the operator who submitted the bundle.
+    pub operator: Option<CounterSignature>,
+    /// The error which interrupted the submission, after which the remaining messages of the
+    /// bundle were not submitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A signature of the bundle hash by the operator's own (non-IC) key.
+#[derive(Deserialize, Serialize)]
+pub struct CounterSignature {
+    pub principal: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Returns the SHA-256 hash of the bundle file content.
+pub fn bundle_hash(bundle: &[u8]) -> [u8; 32] {
+    openssl::sha::sha256(bundle)
+}
+
+/// Signs the bundle hash with the operator's key.
+pub fn counter_sign(operator: &AuthInfo, bundle_hash: &[u8]) -> AnyhowResult<CounterSignature> {
+    let identity = get_identity(operator)?;
+    let principal = identity.sender().map_err(|e| anyhow!(e))?;
+    let signature = identity
+        .sign(bundle_hash)
+        .map_err(|e| anyhow!(e))
+        .context("Cannot counter-sign the bundle")?;
+    Ok(CounterSignature {
+        principal: principal.to_text(),
+        public_key: hex::encode(signature.public_key.unwrap_or_default()),
+        signature: hex::encode(signature.signature.unwrap_or_default()),
+    })
+}
+
+/// Appends the entry as a line of JSON to the audit log.
+pub fn append(path: &Path, entry: &AuditEntry) -> AnyhowResult {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Cannot open the audit log {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?).context("Cannot write the audit log")?;
+    Ok(())
+}
+
+/// Returns the accounts paid in the bundles recorded in the audit log.
+pub fn paid_recipients(path: &Path) -> AnyhowResult<BTreeSet<String>> {
+    let log = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read the audit log {}", path.display()))?;
+    let mut recipients = BTreeSet::new();
+    for (line, entry) in log
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+    {
+        let entry: AuditEntry = serde_json::from_str(entry).with_context(|| {
+            format!(
+                "Cannot parse line {} of the audit log {}",
+                line + 1,
+                path.display()
+            )
+        })?;
+        recipients.extend(entry.recipients);
+    }
+    Ok(recipients)
+}
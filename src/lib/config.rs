@@ -0,0 +1,142 @@
+//! The configuration directory of quill and the custom canisters described in it.
+//!
+//! A custom canister is described by a TOML file `canisters/<name>.toml` in the
+//! configuration directory, for example:
+//!
+//! ```toml
+//! canister_id = "ryjl3-tyaaa-aaaaa-aaaba-cai"
+//! candid = "ledger.did"
+//! methods = ["send_dfx"]
+//!
+//! [templates]
+//! send_dfx = "(record { to = \"{to}\"; amount = record { e8s = {e8s} : nat64 }; fee = record { e8s = 10_000 : nat64 }; memo = 0 : nat64; from_subaccount = null; created_at_time = null })"
+//! ```
+//!
+//! A relative `candid` path is resolved against the `canisters` directory.
+//!
+//! The subnets which canisters are expected on, checked by `send --high-assurance`, are pinned
+//! in `subnets.toml`, by canister id:
+//!
+//! ```toml
+//! "rrkah-fqaaa-aaaaa-aaaaq-cai" = "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe"
+//! ```
+
+use crate::lib::AnyhowResult;
+use anyhow::{anyhow, bail, Context};
+use candid::Principal;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Returns the configuration directory: `$QUILL_CONFIG_DIR` if set, otherwise
+/// `quill` in the platform's configuration directory.
+pub fn config_dir() -> AnyhowResult<PathBuf> {
+    if let Some(dir) = std::env::var_os("QUILL_CONFIG_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join("quill")).ok_or_else(|| {
+        anyhow!("Cannot determine the configuration directory, set QUILL_CONFIG_DIR")
+    })
+}
+
+fn canisters_dir() -> AnyhowResult<PathBuf> {
+    Ok(config_dir()?.join("canisters"))
+}
+
+/// A canister described by a TOML file in the configuration directory.
+#[derive(Deserialize)]
+pub struct CustomCanister {
+    pub canister_id: String,
+    pub candid: PathBuf,
+    /// The methods which may be called; all methods if empty.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Argument templates in candid text format, by method name.
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+}
+
+impl CustomCanister {
+    pub fn canister_id(&self) -> AnyhowResult<Principal> {
+        Principal::from_text(&self.canister_id)
+            .with_context(|| format!("Invalid canister id {}", self.canister_id))
+    }
+
+    /// Returns the candid interface of the canister.
+    pub fn candid(&self) -> AnyhowResult<String> {
+        std::fs::read_to_string(&self.candid)
+            .with_context(|| format!("Cannot read {}", self.candid.display()))
+    }
+}
+
+/// Loads the description of the custom canister with the given name.
+pub fn load_custom_canister(name: &str) -> AnyhowResult<CustomCanister> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Invalid custom canister name: {}", name);
+    }
+    let dir = canisters_dir()?;
+    let path = dir.join(format!("{}.toml", name));
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    let mut canister: CustomCanister =
+        toml::from_str(&content).with_context(|| format!("Cannot parse {}", path.display()))?;
+    if canister.candid.is_relative() {
+        canister.candid = dir.join(&canister.candid);
+    }
+    Ok(canister)
+}
+
+/// Returns all custom canisters in the configuration directory. Files which cannot be loaded
+/// are skipped with a warning, so that they do not break commands using other canisters.
+pub fn list_custom_canisters() -> AnyhowResult<Vec<CustomCanister>> {
+    let dir = match canisters_dir() {
+        Ok(dir) if dir.is_dir() => dir,
+        _ => return Ok(Vec::new()),
+    };
+    let mut canisters = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+            match load_custom_canister(name) {
+                Ok(canister) => canisters.push(canister),
+                Err(err) => eprintln!("Warning: ignoring {}: {:#}", path.display(), err),
+            }
+        }
+    }
+    Ok(canisters)
+}
+
+/// Returns the subnets pinned in `subnets.toml` by canister id, none if the file does not exist.
+pub fn load_pinned_subnets() -> AnyhowResult<BTreeMap<Principal, Principal>> {
+    let path = config_dir()?.join("subnets.toml");
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read {}", path.display()))?;
+    let pins: BTreeMap<String, String> =
+        toml::from_str(&content).with_context(|| format!("Cannot parse {}", path.display()))?;
+    pins.into_iter()
+        .map(|(canister_id, subnet_id)| {
+            let parse = |id: &str| {
+                Principal::from_text(id)
+                    .with_context(|| format!("Invalid id {} in {}", id, path.display()))
+            };
+            Ok((parse(&canister_id)?, parse(&subnet_id)?))
+        })
+        .collect()
+}
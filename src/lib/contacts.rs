@@ -0,0 +1,120 @@
+//! The address book of named recipients.
+//!
+//! Contacts are recorded in `contacts.json` in the configuration directory, by name, with either
+//! an account id or a principal. Commands taking a recipient accept `@<name>` instead, so that
+//! addresses need not be pasted during a signing session.
+
+use crate::lib::{
+    config::config_dir, get_account_id, nns_types::account_id::parse_account_id, AnyhowResult,
+};
+use anyhow::{anyhow, bail, Context};
+use candid::Principal;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn contacts_path() -> AnyhowResult<PathBuf> {
+    Ok(config_dir()?.join("contacts.json"))
+}
+
+/// Returns the contacts by name, none if there is no address book yet.
+pub fn load_contacts() -> AnyhowResult<BTreeMap<String, String>> {
+    let path = contacts_path()?;
+    match std::fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .with_context(|| format!("Cannot parse {}", path.display())),
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+/// Replaces the contacts in the address book.
+pub fn store_contacts(contacts: &BTreeMap<String, String>) -> AnyhowResult {
+    let path = contacts_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create the directory {}", dir.display()))?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(contacts)?)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Checks the name and the address of a new contact, and returns the normalized address.
+pub fn validate_contact(name: &str, address: &str) -> AnyhowResult<String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(
+            "Invalid contact name {}: use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    if let Ok(principal) = Principal::from_text(address.trim()) {
+        return Ok(principal.to_text());
+    }
+    parse_account_id(address)
+        .map(|account_id| account_id.to_hex())
+        .map_err(|_| anyhow!("{} is neither an account id nor a principal", address))
+}
+
+// Returns the address of `@<name>`, or None for other recipients.
+fn lookup(recipient: &str) -> AnyhowResult<Option<String>> {
+    let name = match recipient.strip_prefix('@') {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    load_contacts()?
+        .remove(name)
+        .map(Some)
+        .ok_or_else(|| anyhow!("There is no contact named {}", name))
+}
+
+/// Returns the account id of a recipient given as `@<name>`, which is the default account of
+/// the contact if it is a principal. Other recipients are returned unchanged.
+pub fn resolve_account_id(recipient: &str) -> AnyhowResult<String> {
+    match lookup(recipient)? {
+        Some(address) => match Principal::from_text(&address) {
+            Ok(principal) => Ok(get_account_id(principal)?.to_hex()),
+            Err(_) => Ok(address),
+        },
+        None => Ok(recipient.to_string()),
+    }
+}
+
+/// Returns the principal of a recipient given as `@<name>`, or the principal given.
+pub fn resolve_principal(recipient: &str) -> AnyhowResult<Principal> {
+    let address = lookup(recipient)?;
+    let principal = address.as_deref().unwrap_or(recipient);
+    Principal::from_text(principal).with_context(|| match &address {
+        Some(_) => format!("The contact {} is not a principal", recipient),
+        None => format!("Invalid principal {}", recipient),
+    })
+}
+
+/// Returns whether the account id is the address of a contact, or the default account of a
+/// contact which is a principal.
+pub fn is_contact(account_id: &str, contacts: &BTreeMap<String, String>) -> AnyhowResult<bool> {
+    for address in contacts.values() {
+        let contact_account_id = match Principal::from_text(address) {
+            Ok(principal) => get_account_id(principal)?.to_hex(),
+            Err(_) => address.clone(),
+        };
+        if contact_account_id == account_id {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[test]
+fn test_validate_contact() {
+    let account_id = "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752";
+    assert_eq!(
+        validate_contact("treasury", &account_id.to_uppercase()).unwrap(),
+        account_id
+    );
+    assert_eq!(validate_contact("ops-1", "aaaaa-aa").unwrap(), "aaaaa-aa");
+    assert!(validate_contact("treasury", "not an address").is_err());
+    assert!(validate_contact("@treasury", account_id).is_err());
+    assert!(validate_contact("", account_id).is_err());
+}
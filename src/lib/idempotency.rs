@@ -0,0 +1,115 @@
+//! Idempotency keys of transfers.
+//!
+//! The ledger refuses a transfer identical to one it executed in the last 24
+//! hours, including its `created_at_time`. A batch payment system can pass an
+//! idempotency key with each transfer, which quill maps to a memo derived from
+//! the key and to the `created_at_time` at which the key was first used. The
+//! keys are recorded in `idempotency-keys.json` in the configuration
+//! directory, so signing a payment again, e.g. when re-running a partially
+//! signed batch, yields a transfer the ledger rejects as a duplicate.
+
+use crate::lib::{config::config_dir, nns_types::Memo, AnyhowResult};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// The ledger deduplicates transfers created in this window.
+const DEDUPLICATION_WINDOW_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// A transfer signed with an idempotency key.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct KeyedTransfer {
+    pub to: String,
+    pub amount_e8s: u64,
+    pub memo: u64,
+    pub created_at_time_nanos: u64,
+}
+
+/// Returns the memo derived from an idempotency key.
+pub fn memo_for_key(key: &str) -> u64 {
+    Memo::hash_text(key).0
+}
+
+/// Returns the `created_at_time` of a transfer signed with the key, which is the time
+/// recorded for the key if it was used before.
+pub fn created_at_time(key: &str, transfer: KeyedTransfer) -> AnyhowResult<u64> {
+    let path = config_dir()?.join("idempotency-keys.json");
+    let mut keys: BTreeMap<String, KeyedTransfer> = match std::fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .with_context(|| format!("Cannot parse {}", path.display()))?,
+        Err(_) => BTreeMap::new(),
+    };
+    let now = transfer.created_at_time_nanos;
+    // Keys are forgotten once the ledger no longer deduplicates their transfers.
+    keys.retain(|_, recorded| {
+        now.saturating_sub(recorded.created_at_time_nanos) < 2 * DEDUPLICATION_WINDOW_NANOS
+    });
+    if let Some(recorded) = keys.get(key) {
+        return reuse(key, recorded, &transfer);
+    }
+    keys.insert(key.to_string(), transfer);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create the directory {}", dir.display()))?;
+    }
+    std::fs::write(&path, serde_json::to_string_pretty(&keys)?)
+        .with_context(|| format!("Cannot write {}", path.display()))?;
+    Ok(now)
+}
+
+// Checks that a key is used again for the same transfer, while the ledger deduplicates it.
+fn reuse(key: &str, recorded: &KeyedTransfer, transfer: &KeyedTransfer) -> AnyhowResult<u64> {
+    if (&recorded.to, recorded.amount_e8s, recorded.memo)
+        != (&transfer.to, transfer.amount_e8s, transfer.memo)
+    {
+        bail!(
+            "The idempotency key {} was used for a different transfer of {} e8s to {}",
+            key,
+            recorded.amount_e8s,
+            recorded.to
+        );
+    }
+    if transfer.created_at_time_nanos - recorded.created_at_time_nanos >= DEDUPLICATION_WINDOW_NANOS
+    {
+        bail!(
+            "The idempotency key {} was first used more than 24 hours ago, so the ledger would \
+             refuse the transfer as too old. Check whether the first transfer was executed, and \
+             use a new key to transfer again.",
+            key
+        );
+    }
+    Ok(recorded.created_at_time_nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{memo_for_key, reuse, KeyedTransfer, DEDUPLICATION_WINDOW_NANOS};
+
+    fn transfer(amount_e8s: u64, created_at_time_nanos: u64) -> KeyedTransfer {
+        KeyedTransfer {
+            to: "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752".to_string(),
+            amount_e8s,
+            memo: memo_for_key("payment-1"),
+            created_at_time_nanos,
+        }
+    }
+
+    #[test]
+    fn derives_memos() {
+        assert_eq!(memo_for_key("payment-1"), memo_for_key("payment-1"));
+        assert_ne!(memo_for_key("payment-1"), memo_for_key("payment-2"));
+    }
+
+    #[test]
+    fn reuses_the_first_time() {
+        let recorded = transfer(100, 1_000);
+        assert_eq!(reuse("k", &recorded, &transfer(100, 5_000)).unwrap(), 1_000);
+        assert!(reuse("k", &recorded, &transfer(200, 5_000)).is_err());
+        assert!(reuse(
+            "k",
+            &recorded,
+            &transfer(100, 1_000 + DEDUPLICATION_WINDOW_NANOS)
+        )
+        .is_err());
+    }
+}
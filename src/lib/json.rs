@@ -0,0 +1,100 @@
+//! Rendering of candid values as JSON, for tools which read replies without a
+//! candid parser.
+//!
+//! The values are mapped as follows:
+//!
+//! - `null` and `reserved` become `null`, `bool` and `text` become booleans and strings;
+//! - `nat8`, `nat16`, `nat32`, `int8`, `int16`, `int32` and floats become numbers;
+//! - `nat64`, `int64`, `nat` and `int` become decimal strings, since JSON numbers
+//!   cannot represent all of them exactly;
+//! - `opt` becomes `null` or the value, `vec` becomes an array, except for a
+//!   non-empty `vec nat8` (a blob) which becomes a hex string;
+//! - a `record` becomes an object keyed by field name, or by the numeric id of
+//!   unnamed fields, and a `variant` becomes an object with a single key;
+//! - principals become their text representation, and a `func` becomes an
+//!   object with `principal` and `method` keys.
+//!
+//! The arguments of a message or reply become an array.
+
+use candid::parser::value::{IDLArgs, IDLField, IDLValue};
+use serde_json::{json, Map, Value};
+
+pub fn idl_args_to_json(args: &IDLArgs) -> Value {
+    Value::Array(args.args.iter().map(idl_value_to_json).collect())
+}
+
+pub fn idl_value_to_json(value: &IDLValue) -> Value {
+    match value {
+        IDLValue::Null | IDLValue::None | IDLValue::Reserved => Value::Null,
+        IDLValue::Bool(b) => json!(b),
+        IDLValue::Text(s) | IDLValue::Number(s) => json!(s),
+        IDLValue::Float64(f) => json!(f),
+        IDLValue::Float32(f) => json!(f),
+        IDLValue::Opt(value) => idl_value_to_json(value),
+        IDLValue::Vec(values) => {
+            let bytes = values
+                .iter()
+                .map(|value| match value {
+                    IDLValue::Nat8(byte) => Some(*byte),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            match bytes {
+                Some(bytes) if !bytes.is_empty() => json!(hex::encode(bytes)),
+                _ => Value::Array(values.iter().map(idl_value_to_json).collect()),
+            }
+        }
+        IDLValue::Record(fields) => Value::Object(fields.iter().map(field_to_json).collect()),
+        IDLValue::Variant(variant) => {
+            let mut object = Map::new();
+            let (key, value) = field_to_json(&variant.0);
+            object.insert(key, value);
+            Value::Object(object)
+        }
+        IDLValue::Principal(principal) | IDLValue::Service(principal) => {
+            json!(principal.to_text())
+        }
+        IDLValue::Func(principal, method) => {
+            json!({ "principal": principal.to_text(), "method": method })
+        }
+        IDLValue::Int(n) => json!(n.to_string()),
+        IDLValue::Nat(n) => json!(n.to_string()),
+        IDLValue::Nat8(n) => json!(n),
+        IDLValue::Nat16(n) => json!(n),
+        IDLValue::Nat32(n) => json!(n),
+        IDLValue::Nat64(n) => json!(n.to_string()),
+        IDLValue::Int8(n) => json!(n),
+        IDLValue::Int16(n) => json!(n),
+        IDLValue::Int32(n) => json!(n),
+        IDLValue::Int64(n) => json!(n.to_string()),
+    }
+}
+
+fn field_to_json(field: &IDLField) -> (String, Value) {
+    (field.id.to_string(), idl_value_to_json(&field.val))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::idl_args_to_json;
+    use candid::parser::value::IDLArgs;
+    use serde_json::json;
+
+    #[test]
+    fn renders_values() {
+        let args: IDLArgs =
+            "(record { height = 123 : nat64; ok = true; memo = vec { 1; 255 } : vec nat8 }, \
+             variant { Err = opt \"oops\" }, vec { 1 : nat32; 2 : nat32 }, null)"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            idl_args_to_json(&args),
+            json!([
+                { "height": "123", "ok": true, "memo": "01ff" },
+                { "Err": "oops" },
+                [1, 2],
+                null
+            ])
+        );
+    }
+}
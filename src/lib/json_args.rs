@@ -0,0 +1,214 @@
+//! Reading the arguments of a command from a JSON document, e.g. one generated by an
+//! orchestration system, with `--args-from-json <FILE>`.
+//!
+//! The document is an object whose keys are the names of the options and arguments of the
+//! command, as in `{"neuron_id": "123", "disburse": true, "to": "..."}`. It is turned into
+//! command-line arguments, so that clap checks them with the same parsers as typed arguments.
+
+use crate::lib::AnyhowResult;
+use anyhow::{anyhow, bail, Context};
+use clap::{Arg, ArgSettings, Command};
+use serde_json::{Map, Value};
+
+pub const ARGS_FROM_JSON: &str = "--args-from-json";
+
+/// Replaces `--args-from-json <FILE>` in the command line by the arguments in the file, which
+/// apply to the subcommand given on the command line.
+pub fn expand_args(mut args: Vec<String>, command: &Command) -> AnyhowResult<Vec<String>> {
+    let index = match args
+        .iter()
+        .position(|arg| arg == ARGS_FROM_JSON || arg.starts_with("--args-from-json="))
+    {
+        Some(index) => index,
+        None => return Ok(args),
+    };
+    let path = match args.remove(index).split_once('=') {
+        Some((_, path)) => path.to_string(),
+        None if index < args.len() => args.remove(index),
+        None => bail!("{} requires a file", ARGS_FROM_JSON),
+    };
+    let json = crate::read_file(&path, "arguments")?;
+    let document: Value = serde_json::from_str(&json)
+        .with_context(|| format!("Cannot parse the arguments in {}", path))?;
+    let document = document
+        .as_object()
+        .ok_or_else(|| anyhow!("The arguments in {} are not a JSON object", path))?;
+    let subcommand = find_subcommand(command, &args[1..]);
+    args.extend(json_to_args(document, subcommand, command)?);
+    Ok(args)
+}
+
+// Returns the innermost subcommand named on the command line.
+fn find_subcommand<'a, 'help>(command: &'a Command<'help>, args: &[String]) -> &'a Command<'help> {
+    let mut current = command;
+    for arg in args.iter().filter(|arg| !arg.starts_with('-')) {
+        match current.find_subcommand(arg) {
+            Some(subcommand) => current = subcommand,
+            None => break,
+        }
+    }
+    current
+}
+
+// Converts the document to the arguments of the subcommand, with the positional arguments first
+// so that options taking several values cannot take them.
+fn json_to_args(
+    document: &Map<String, Value>,
+    subcommand: &Command,
+    root: &Command,
+) -> AnyhowResult<Vec<String>> {
+    let normalize = |key: &str| key.replace('_', "-");
+    let find = |key: &str| {
+        subcommand
+            .get_arguments()
+            .chain(root.get_arguments())
+            .find(|arg| {
+                arg.get_long() == Some(normalize(key).as_str())
+                    || normalize(arg.get_name()) == normalize(key)
+            })
+    };
+    for key in document.keys() {
+        if find(key).is_none() {
+            bail!(
+                "Unknown argument {} for the command {}",
+                key,
+                subcommand.get_name()
+            );
+        }
+    }
+
+    let mut positionals = Vec::new();
+    for arg in subcommand.get_positionals() {
+        if let Some((_, value)) = document
+            .iter()
+            .find(|(key, _)| normalize(key) == normalize(arg.get_name()))
+        {
+            positionals.extend(values(value)?);
+        }
+    }
+    let mut options = Vec::new();
+    for (key, value) in document {
+        let arg = find(key).expect("known argument");
+        if arg.is_positional() {
+            continue;
+        }
+        options.extend(option_args(arg, value)?);
+    }
+    positionals.extend(options);
+    Ok(positionals)
+}
+
+fn option_args(arg: &Arg, value: &Value) -> AnyhowResult<Vec<String>> {
+    let long = format!("--{}", arg.get_long().unwrap_or_else(|| arg.get_name()));
+    if !arg.is_set(ArgSettings::TakesValue) {
+        return match value {
+            Value::Bool(true) => Ok(vec![long]),
+            Value::Bool(false) | Value::Null => Ok(Vec::new()),
+            _ => bail!("{} is a flag, which is either true or false", long),
+        };
+    }
+    let values = values(value)?;
+    if arg.is_set(ArgSettings::MultipleOccurrences) || values.len() <= 1 {
+        Ok(values
+            .into_iter()
+            .map(|value| format!("{}={}", long, value))
+            .collect())
+    } else {
+        Ok(std::iter::once(long).chain(values).collect())
+    }
+}
+
+// Returns the values of an argument: strings, numbers and booleans, or arrays of them.
+fn values(value: &Value) -> AnyhowResult<Vec<String>> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::String(value) => Ok(vec![value.clone()]),
+        Value::Number(value) => Ok(vec![value.to_string()]),
+        Value::Bool(value) => Ok(vec![value.to_string()]),
+        Value::Array(values) => {
+            let mut all = Vec::new();
+            for value in values {
+                if matches!(value, Value::Array(_) | Value::Object(_) | Value::Null) {
+                    bail!("Arrays of arguments cannot contain {}", value);
+                }
+                all.extend(self::values(value)?);
+            }
+            Ok(all)
+        }
+        Value::Object(_) => bail!("Arguments cannot be objects, found {}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_args;
+    use clap::{CommandFactory, Parser};
+
+    #[derive(Parser)]
+    struct TestOpts {
+        #[clap(long, global(true))]
+        quiet: bool,
+
+        #[clap(subcommand)]
+        command: TestCommand,
+    }
+
+    #[derive(Parser)]
+    enum TestCommand {
+        Manage {
+            neuron_id: u64,
+            #[clap(long)]
+            disburse: bool,
+            #[clap(long)]
+            amount: Option<String>,
+            #[clap(long, multiple_values(true))]
+            register_vote: Vec<String>,
+            #[clap(long, multiple_occurrences(true))]
+            set_following: Vec<String>,
+        },
+    }
+
+    fn expand(cli: &[&str], json: &str) -> anyhow::Result<Vec<String>> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, json.as_bytes()).unwrap();
+        let mut args: Vec<String> = cli.iter().map(|arg| arg.to_string()).collect();
+        args.push("--args-from-json".to_string());
+        args.push(file.path().display().to_string());
+        expand_args(args, &TestOpts::command())
+    }
+
+    #[test]
+    fn expands_arguments() {
+        let args = expand(
+            &["quill", "manage"],
+            r#"{"neuron_id": 12, "disburse": true, "quiet": false, "amount": "1.5",
+                "register-vote": [1, 2], "set_following": ["4=1", "0=2"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "quill",
+                "manage",
+                "12",
+                "--amount=1.5",
+                "--disburse",
+                "--register-vote",
+                "1",
+                "2",
+                "--set-following=4=1",
+                "--set-following=0=2",
+            ]
+        );
+        let opts = TestOpts::try_parse_from(args).unwrap();
+        let TestCommand::Manage { neuron_id, .. } = opts.command;
+        assert_eq!(neuron_id, 12);
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert!(expand(&["quill", "manage"], r#"{"neuron": 12}"#).is_err());
+        assert!(expand(&["quill", "manage"], r#"{"disburse": "yes"}"#).is_err());
+        assert!(expand(&["quill", "manage"], r#"[12]"#).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Metrics of the submission of a batch of messages, in the Prometheus text format, so that long
+//! runs such as payouts can be monitored, e.g. by the textfile collector of the node exporter.
+
+use crate::lib::AnyhowResult;
+use anyhow::Context;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// The upper bounds of the buckets of the histogram of the durations of the calls, in seconds.
+const BUCKETS: [f64; 9] = [0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// The counters of a submission, and the histogram of the durations of the calls from their
+/// submission to their outcome.
+#[derive(Default)]
+pub struct Metrics {
+    state: Mutex<MetricsState>,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    submitted: u64,
+    replied: u64,
+    rejected: u64,
+    retries: u64,
+    // The number of calls in every bucket, not cumulated.
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Metrics {
+    pub fn submitted(&self) {
+        self.state.lock().unwrap().submitted += 1;
+    }
+
+    /// Counts a message submitted again after the boundary node rate-limited it.
+    pub fn retried(&self) {
+        self.state.lock().unwrap().retries += 1;
+    }
+
+    pub fn replied(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.replied += 1;
+        state.observe(duration);
+    }
+
+    pub fn rejected(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.rejected += 1;
+        state.observe(duration);
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap();
+        let mut text = String::new();
+        for (name, help, value) in [
+            ("submitted", "Messages accepted by the IC.", state.submitted),
+            ("replied", "Calls which were replied.", state.replied),
+            ("rejected", "Calls which were rejected.", state.rejected),
+            (
+                "retries",
+                "Messages submitted again after being rate-limited.",
+                state.retries,
+            ),
+        ] {
+            let _ = writeln!(text, "# HELP quill_send_{}_total {}", name, help);
+            let _ = writeln!(text, "# TYPE quill_send_{}_total counter", name);
+            let _ = writeln!(text, "quill_send_{}_total {}", name, value);
+        }
+        let name = "quill_send_call_duration_seconds";
+        let _ = writeln!(
+            text,
+            "# HELP {} Time from the submission of a call to its outcome.",
+            name
+        );
+        let _ = writeln!(text, "# TYPE {} histogram", name);
+        let mut cumulated = 0;
+        for (bound, count) in BUCKETS.iter().zip(state.buckets) {
+            cumulated += count;
+            let _ = writeln!(text, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulated);
+        }
+        let _ = writeln!(text, "{}_bucket{{le=\"+Inf\"}} {}", name, state.count);
+        let _ = writeln!(text, "{}_sum {}", name, state.sum);
+        let _ = writeln!(text, "{}_count {}", name, state.count);
+        text
+    }
+
+    /// Replaces the file with the current metrics, atomically so that a collector never reads
+    /// a partial file.
+    pub fn write(&self, path: &Path) -> AnyhowResult {
+        let tmp = path.with_extension("prom.tmp");
+        std::fs::write(&tmp, self.render())
+            .with_context(|| format!("Cannot write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("Cannot write {}", path.display()))
+    }
+}
+
+impl MetricsState {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(bucket) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[test]
+fn test_render() {
+    let metrics = Metrics::default();
+    metrics.submitted();
+    metrics.submitted();
+    metrics.retried();
+    metrics.replied(Duration::from_millis(1500));
+    metrics.rejected(Duration::from_secs(600));
+    let text = metrics.render();
+    assert!(text.contains("\nquill_send_submitted_total 2\n"));
+    assert!(text.contains("\nquill_send_retries_total 1\n"));
+    assert!(text.contains("\nquill_send_call_duration_seconds_bucket{le=\"1\"} 0\n"));
+    assert!(text.contains("\nquill_send_call_duration_seconds_bucket{le=\"2\"} 1\n"));
+    assert!(text.contains("\nquill_send_call_duration_seconds_bucket{le=\"300\"} 1\n"));
+    assert!(text.contains("\nquill_send_call_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+    assert!(text.contains("\nquill_send_call_duration_seconds_sum 601.5\n"));
+}
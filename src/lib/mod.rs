@@ -4,26 +4,33 @@ use anyhow::{anyhow, bail, Context};
 use bip39::Mnemonic;
 use candid::{
     parser::typing::{check_prog, TypeEnv},
-    types::Function,
-    IDLProg, Principal,
+    types::{Function, Type},
+    IDLArgs, IDLProg, Principal,
 };
+use clap::ArgEnum;
 use ic_agent::{
     identity::{AnonymousIdentity, BasicIdentity, Secp256k1Identity},
     Agent, Identity,
 };
 use ic_base_types::PrincipalId;
+#[cfg(feature = "hsm")]
 use ic_identity_hsm::HardwareIdentity;
 use ic_nns_constants::{
-    GENESIS_TOKEN_CANISTER_ID, GOVERNANCE_CANISTER_ID, LEDGER_CANISTER_ID, REGISTRY_CANISTER_ID,
+    CYCLES_MINTING_CANISTER_ID, GENESIS_TOKEN_CANISTER_ID, GOVERNANCE_CANISTER_ID,
+    LEDGER_CANISTER_ID, REGISTRY_CANISTER_ID,
 };
 use k256::{elliptic_curve::sec1::ToEncodedPoint, SecretKey};
+use once_cell::sync::Lazy;
 use pem::{encode, Pem};
 use serde_cbor::Value;
 use simple_asn1::ASN1Block::{
     BitString, Explicit, Integer, ObjectIdentifier, OctetString, Sequence,
 };
 use simple_asn1::{oid, to_der, ASN1Class, BigInt, BigUint};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::{env::VarError, path::Path};
 
 pub const IC_URL: &str = "https://ic0.app";
@@ -32,7 +39,108 @@ pub fn get_ic_url() -> String {
     std::env::var("IC_URL").unwrap_or_else(|_| IC_URL.to_string())
 }
 
+/// The network of the boundary nodes of the IC, whichever domain is used.
+pub const MAINNET: &str = "mainnet";
+
+/// Returns the network IC_URL points to, which signed messages are tagged with: `mainnet` for
+/// the boundary nodes of the IC, and the URL itself for test and local instances.
+pub fn get_network() -> String {
+    network_of_url(&get_ic_url())
+}
+
+fn network_of_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = host.split(&['/', ':'][..]).next().unwrap_or_default();
+    let mainnet = ["ic0.app", "icp0.io", "icp-api.io"].iter().any(|domain| {
+        host.eq_ignore_ascii_case(domain)
+            || host.to_ascii_lowercase().ends_with(&format!(".{}", domain))
+    });
+    if mainnet {
+        MAINNET.to_string()
+    } else {
+        url.to_string()
+    }
+}
+
+pub mod audit;
+pub mod config;
+pub mod contacts;
+pub mod idempotency;
+pub mod json;
+pub mod json_args;
+#[cfg(feature = "online")]
+pub mod metrics;
+pub mod nns_functions;
+pub use quill::nns_types;
+#[cfg(feature = "online")]
+pub mod notify;
+#[cfg(not(feature = "online"))]
+mod offline;
+#[cfg(feature = "online")]
+pub mod pacing;
+pub mod policy;
+pub mod proposal;
+#[cfg(feature = "online")]
+pub mod queue;
+pub mod request_id;
+pub mod serial;
 pub mod signing;
+pub mod sns;
+pub mod timestamp;
+
+/// The SHA-256 hashes of the candid interfaces in the format of `sha256sum`,
+/// pinned in `candid/SHA256SUMS` whenever an interface is updated.
+pub const CANDID_SHA256SUMS: &str = include_str!("../../candid/SHA256SUMS");
+
+/// The candid interfaces embedded in the binary, by file name. Their expected
+/// hashes are in `CANDID_SHA256SUMS`.
+pub const EMBEDDED_CANDID: &[(&str, &[u8])] = &[
+    (
+        "governance.did",
+        include_bytes!("../../candid/governance.did"),
+    ),
+    ("gtc.did", include_bytes!("../../candid/gtc.did")),
+    ("index.did", include_bytes!("../../candid/index.did")),
+    ("ledger.did", include_bytes!("../../candid/ledger.did")),
+    (
+        "nns_function_payloads.did",
+        include_bytes!("../../candid/nns_function_payloads.did"),
+    ),
+    ("registry.did", include_bytes!("../../candid/registry.did")),
+    (
+        "sns_governance.did",
+        include_bytes!("../../candid/sns_governance.did"),
+    ),
+    (
+        "sns_ledger.did",
+        include_bytes!("../../candid/sns_ledger.did"),
+    ),
+];
+
+fn embedded_candid(name: &str) -> AnyhowResult<String> {
+    let (_, bytes) = EMBEDDED_CANDID
+        .iter()
+        .find(|(file, _)| *file == name)
+        .ok_or_else(|| anyhow!("{} is not embedded", name))?;
+    String::from_utf8(bytes.to_vec()).with_context(|| format!("Cannot load {}", name))
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppresses progress messages and warnings, and makes prompts fail instead
+/// of waiting for input.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Maximum size of a message file accepted by `send`. Message files usually
+/// arrive on removable media, so anything larger is refused before parsing.
+pub const MAX_MESSAGE_FILE_BYTES: u64 = 32 * 1024 * 1024;
 
 pub type AnyhowResult<T = ()> = anyhow::Result<T>;
 
@@ -88,20 +196,68 @@ pub fn registry_canister_id() -> Principal {
     Principal::from_slice(REGISTRY_CANISTER_ID.as_ref())
 }
 
+pub fn cycles_minting_canister_id() -> Principal {
+    Principal::from_slice(CYCLES_MINTING_CANISTER_ID.as_ref())
+}
+
+/// The id of the ICP ledger index canister.
+pub fn index_canister_id() -> Principal {
+    Principal::from_text("qhbym-qaaaa-aaaaa-aaafq-cai").unwrap()
+}
+
+/// Returns the canisters `send` may submit messages to. The list can be
+/// narrowed with the `QUILL_ALLOWED_CANISTERS` environment variable, a comma
+/// separated list of canister ids; by default the NNS canisters known to quill are allowed.
+/// SNS canisters and the custom canisters of the configuration directory must be allowed
+/// explicitly.
+pub fn get_allowed_canister_ids() -> AnyhowResult<Vec<Principal>> {
+    match std::env::var("QUILL_ALLOWED_CANISTERS") {
+        Ok(ids) => ids
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                Principal::from_text(id).with_context(|| {
+                    format!("Invalid canister id in QUILL_ALLOWED_CANISTERS: {}", id)
+                })
+            })
+            .collect(),
+        Err(VarError::NotPresent) => Ok(vec![
+            ledger_canister_id(),
+            governance_canister_id(),
+            genesis_token_canister_id(),
+            registry_canister_id(),
+            index_canister_id(),
+        ]),
+        Err(e) => Err(e).context("Cannot read QUILL_ALLOWED_CANISTERS"),
+    }
+}
+
 // Returns the candid for the specified canister id, if there is one.
 pub fn get_local_candid(canister_id: Principal) -> AnyhowResult<String> {
     if canister_id == governance_canister_id() {
-        String::from_utf8(include_bytes!("../../candid/governance.did").to_vec())
-            .context("Cannot load governance.did")
+        embedded_candid("governance.did")
     } else if canister_id == ledger_canister_id() {
-        String::from_utf8(include_bytes!("../../candid/ledger.did").to_vec())
-            .context("Cannot load ledger.did")
+        embedded_candid("ledger.did")
     } else if canister_id == genesis_token_canister_id() {
-        String::from_utf8(include_bytes!("../../candid/gtc.did").to_vec())
-            .context("Cannot load gtc.did")
+        embedded_candid("gtc.did")
     } else if canister_id == registry_canister_id() {
-        String::from_utf8(include_bytes!("../../candid/registry.did").to_vec())
-            .context("Cannot load registry.did")
+        embedded_candid("registry.did")
+    } else if canister_id == index_canister_id() {
+        embedded_candid("index.did")
+    } else if let Some(sns) = sns::configured_sns_canister_ids()?.filter(|sns| {
+        sns.governance_canister_id == canister_id || sns.ledger_canister_id == canister_id
+    }) {
+        if sns.governance_canister_id == canister_id {
+            embedded_candid("sns_governance.did")
+        } else {
+            embedded_candid("sns_ledger.did")
+        }
+    } else if let Some(canister) = config::list_custom_canisters()?
+        .into_iter()
+        .find(|canister| canister.canister_id().ok() == Some(canister_id))
+    {
+        canister.candid()
     } else {
         bail!(
             "\
@@ -111,74 +267,194 @@ Should be one of:
 - Ledger: {ledger}
 - Governance: {governance}
 - Genesis: {genesis}
-- Registry: {registry}",
+- Registry: {registry}
+- Index: {index}",
             ledger = ledger_canister_id(),
             governance = governance_canister_id(),
             genesis = genesis_token_canister_id(),
-            registry = registry_canister_id()
+            registry = registry_canister_id(),
+            index = index_canister_id()
         );
     }
 }
 
-/// Returns pretty-printed encoding of a candid value.
-pub fn get_idl_string(
+/// The part of a method call to decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgPart {
+    /// The arguments of the call.
+    Args,
+    /// The reply.
+    Rets,
+}
+
+/// The formats in which candid values are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum OutputFormat {
+    /// The hex-encoded bytes.
+    Raw,
+    /// Compact candid text.
+    Idl,
+    /// Pretty-printed candid text.
+    #[clap(name = "pp")]
+    Pretty,
+    /// JSON, see the `json` module for the mapping of candid values.
+    Json,
+}
+
+/// Decodes the arguments or the reply of a method, using the candid interface
+/// of the canister when there is one.
+pub fn get_idl_args(
     blob: &[u8],
     canister_id: Principal,
     method_name: &str,
-    part: &str,
-) -> AnyhowResult<String> {
-    let spec = get_local_candid(canister_id)?;
-    let method_type = get_candid_type(spec, method_name);
+    part: ArgPart,
+) -> AnyhowResult<IDLArgs> {
+    let interface = get_canister_interface(canister_id)?;
+    let method_type = interface.as_deref().and_then(|(env, actor)| {
+        env.get_method(actor, method_name)
+            .ok()
+            .map(|func| (env, func))
+    });
     let result = match method_type {
-        None => candid::IDLArgs::from_bytes(blob),
-        Some((env, func)) => candid::IDLArgs::from_bytes_with_types(
+        None => IDLArgs::from_bytes(blob),
+        Some((env, func)) => IDLArgs::from_bytes_with_types(
             blob,
-            &env,
-            if part == "args" {
-                &func.args
-            } else {
-                &func.rets
+            env,
+            match part {
+                ArgPart::Args => &func.args,
+                ArgPart::Rets => &func.rets,
             },
         ),
     };
-    Ok(format!("{}", result?))
+    let mut args = result?;
+    if canister_id == governance_canister_id() {
+        nns_functions::decode_payloads(&mut args);
+    }
+    Ok(args)
+}
+
+/// Renders the arguments or the reply of a method in the given format.
+pub fn get_idl_string(
+    blob: &[u8],
+    canister_id: Principal,
+    method_name: &str,
+    part: ArgPart,
+    output_format: OutputFormat,
+) -> AnyhowResult<String> {
+    let args = || get_idl_args(blob, canister_id, method_name, part);
+    Ok(match output_format {
+        OutputFormat::Raw => hex::encode(blob),
+        OutputFormat::Idl => format!("{:?}", args()?),
+        OutputFormat::Pretty => format!("{}", args()?),
+        OutputFormat::Json => serde_json::to_string_pretty(&json::idl_args_to_json(&args()?))?,
+    })
 }
 
 /// Returns the candid type of a specifed method and correspondig idl
 /// description.
 pub fn get_candid_type(idl: String, method_name: &str) -> Option<(TypeEnv, Function)> {
-    let ast = candid::pretty_parse::<IDLProg>("/dev/null", &idl).ok()?;
+    let (env, actor) = parse_interface(&idl)?;
+    let method = env.get_method(&actor, method_name).ok()?.clone();
+    Some((env, method))
+}
+
+// Parses a candid interface into its type environment and the type of its service.
+fn parse_interface(idl: &str) -> Option<(TypeEnv, Type)> {
+    let ast = candid::pretty_parse::<IDLProg>("/dev/null", idl).ok()?;
     let mut env = TypeEnv::new();
     let actor = check_prog(&mut env, &ast).ok()?;
-    let method = env.get_method(&actor?, method_name).ok()?.clone();
-    Some((env, method))
+    Some((env, actor?))
+}
+
+// The parsed candid interfaces, by canister. Parsing an interface takes much longer than decoding
+// a reply, and a batch of messages to the same canister only needs it parsed once.
+#[allow(clippy::type_complexity)]
+static CANISTER_INTERFACES: Lazy<Mutex<HashMap<Principal, Option<Arc<(TypeEnv, Type)>>>>> =
+    Lazy::new(Default::default);
+
+// Returns the parsed candid interface of a canister, if it is known and valid.
+fn get_canister_interface(canister_id: Principal) -> AnyhowResult<Option<Arc<(TypeEnv, Type)>>> {
+    if let Some(interface) = CANISTER_INTERFACES.lock().unwrap().get(&canister_id) {
+        return Ok(interface.clone());
+    }
+    let interface = parse_interface(&get_local_candid(canister_id)?).map(Arc::new);
+    CANISTER_INTERFACES
+        .lock()
+        .unwrap()
+        .insert(canister_id, interface.clone());
+    Ok(interface)
 }
 
 /// Reads from the file path or STDIN and returns the content.
 pub fn read_from_file(path: impl AsRef<Path>) -> AnyhowResult<String> {
+    read_from_file_limited(path, u64::MAX)
+}
+
+/// Reads at most `limit` bytes from the file path or STDIN and returns the
+/// content. Inputs larger than `limit` are rejected without being buffered.
+pub fn read_from_file_limited(path: impl AsRef<Path>, limit: u64) -> AnyhowResult<String> {
     use std::io::Read;
     let path = path.as_ref();
-    let mut content = String::new();
-    if path == Path::new("-") {
-        std::io::stdin().read_to_string(&mut content)?;
+    let reader: Box<dyn Read> = if path == Path::new("-") {
+        Box::new(std::io::stdin())
     } else {
-        let mut file = std::fs::File::open(&path).context("Cannot open the message file.")?;
-        file.read_to_string(&mut content)
-            .context("Cannot read the message file.")?;
+        Box::new(std::fs::File::open(&path).context("Cannot open the message file.")?)
+    };
+    let mut content = String::new();
+    reader
+        .take(limit.saturating_add(1))
+        .read_to_string(&mut content)
+        .context("Cannot read the message file.")?;
+    if content.len() as u64 > limit {
+        bail!(
+            "The message file exceeds the maximum size of {} bytes.",
+            limit
+        );
     }
     Ok(content)
 }
 
+#[cfg(all(
+    feature = "online",
+    not(any(feature = "native-tls", feature = "rustls"))
+))]
+compile_error!("The `online` feature requires a TLS implementation: `native-tls` or `rustls`.");
+
+/// Returns an HTTP client. The TLS implementation is rustls with the `rustls`
+/// feature, and the platform's (OpenSSL on Linux) with `native-tls` only.
+#[cfg(feature = "online")]
+pub fn get_http_client() -> AnyhowResult<reqwest::Client> {
+    let builder = reqwest::Client::builder();
+    #[cfg(feature = "rustls")]
+    let builder = builder.use_rustls_tls();
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    let builder = builder.use_native_tls();
+    builder.build().context("Cannot create the HTTP client")
+}
+
+/// Returns the HTTP transport to the IC.
+#[cfg(feature = "online")]
+pub fn get_http_transport(
+) -> AnyhowResult<ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport> {
+    let client = get_http_client()?;
+    Ok(
+        ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport::create_with_client(
+            get_ic_url(),
+            client,
+        )?,
+    )
+}
+
 /// Returns an agent with an identity derived from a private key if it was
 /// provided.
 pub fn get_agent(auth: &AuthInfo) -> AnyhowResult<Agent> {
     let timeout = std::time::Duration::from_secs(60 * 5);
+    #[cfg(feature = "online")]
+    let transport = get_http_transport()?;
+    #[cfg(not(feature = "online"))]
+    let transport = offline::OfflineTransport;
     let builder = Agent::builder()
-        .with_transport(
-            ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport::create({
-                get_ic_url()
-            })?,
-        )
+        .with_transport(transport)
         .with_ingress_expiry(Some(timeout));
 
     let identity = get_identity(auth)?;
@@ -188,13 +464,18 @@ pub fn get_agent(auth: &AuthInfo) -> AnyhowResult<Agent> {
         .map_err(|err| anyhow!(err))
 }
 
+#[cfg(feature = "hsm")]
 fn ask_nitrohsm_pin_via_tty() -> Result<String, String> {
+    if is_quiet() {
+        return Err("The NitroHSM PIN must be set in NITROHSM_PIN in quiet mode".to_string());
+    }
     rpassword::prompt_password("NitroHSM PIN: ")
         .context("Cannot read NitroHSM PIN from tty")
         // TODO: better error string
         .map_err(|e| e.to_string())
 }
 
+#[cfg(feature = "hsm")]
 fn read_nitrohsm_pin_env_var() -> Result<Option<String>, String> {
     match std::env::var("NITROHSM_PIN") {
         Ok(val) => Ok(Some(val)),
@@ -214,6 +495,7 @@ pub fn get_identity(auth: &AuthInfo) -> AnyhowResult<Box<dyn Identity>> {
                 Err(e) => Err(e).context("couldn't load identity from PEM file"),
             },
         },
+        #[cfg(feature = "hsm")]
         AuthInfo::NitroHsm(info) => {
             let pin_fn = || {
                 let user_set_pin = { info.pin.borrow().clone() };
@@ -234,6 +516,10 @@ pub fn get_identity(auth: &AuthInfo) -> AnyhowResult<Box<dyn Identity>> {
                 .context("Unable to use your hardware key")?;
             Ok(Box::new(identity) as _)
         }
+        #[cfg(not(feature = "hsm"))]
+        AuthInfo::NitroHsm(_) => {
+            bail!("This build of quill cannot use an HSM, it lacks the `hsm` feature.")
+        }
     }
 }
 
@@ -241,8 +527,31 @@ pub fn parse_query_response(
     response: Vec<u8>,
     canister_id: Principal,
     method_name: &str,
+    output_format: OutputFormat,
 ) -> AnyhowResult<String> {
-    let cbor: Value = serde_cbor::from_slice(&response)
+    match parse_query_reply(&response)? {
+        QueryReply::Replied(reply) => get_idl_string(
+            &reply,
+            canister_id,
+            method_name,
+            ArgPart::Rets,
+            output_format,
+        ),
+        QueryReply::Rejected(rejection) => Ok(rejection),
+    }
+}
+
+/// The outcome of a query call.
+pub enum QueryReply {
+    /// The candid encoded reply.
+    Replied(Vec<u8>),
+    /// The reject code and message.
+    Rejected(String),
+}
+
+/// Parses the CBOR response to a query call.
+pub fn parse_query_reply(response: &[u8]) -> AnyhowResult<QueryReply> {
+    let cbor: Value = serde_cbor::from_slice(response)
         .context("Invalid cbor data in the content of the message.")?;
     if let Value::Map(m) = cbor {
         // Try to decode a rejected response.
@@ -251,10 +560,10 @@ pub fn parse_query_response(
             m.get(&Value::Text("reject_code".to_string())),
             m.get(&Value::Text("reject_message".to_string())),
         ) {
-            return Ok(format!(
+            return Ok(QueryReply::Rejected(format!(
                 "Rejected (code {}): {}",
                 reject_code, reject_message
-            ));
+            )));
         }
 
         // Try to decode a successful response.
@@ -263,7 +572,7 @@ pub fn parse_query_response(
             m.get(&Value::Text("reply".to_string())),
         ) {
             if let Some(Value::Bytes(reply)) = m.get(&Value::Text("arg".to_string())) {
-                return get_idl_string(reply, canister_id, method_name, "rets");
+                return Ok(QueryReply::Replied(reply.clone()));
             }
         }
     }
@@ -324,3 +633,18 @@ pub fn mnemonic_to_pem(mnemonic: &Mnemonic) -> AnyhowResult<String> {
     let key_pem = encode(&pem);
     Ok(key_pem.replace('\r', "").replace("\n\n", "\n"))
 }
+
+#[test]
+fn test_network_of_url() {
+    assert_eq!(network_of_url("https://ic0.app"), MAINNET);
+    assert_eq!(network_of_url("https://icp-api.io/"), MAINNET);
+    assert_eq!(network_of_url("https://nns.IC0.app:443/api"), MAINNET);
+    assert_eq!(
+        network_of_url("http://localhost:8080/"),
+        "http://localhost:8080"
+    );
+    assert_eq!(
+        network_of_url("https://ic0.app.example.com"),
+        "https://ic0.app.example.com"
+    );
+}
@@ -0,0 +1,153 @@
+//! Decoding of the payloads of `ExecuteNnsFunction` proposals, which are candid-encoded blobs, so
+//! that voters see e.g. the subnet and replica version of an upgrade rather than opaque bytes.
+//!
+//! The payload types of the common NNS functions are in the embedded
+//! `nns_function_payloads.did`. Payloads of other functions, e.g. wasm modules, stay blobs.
+
+use crate::lib::embedded_candid;
+use candid::{
+    idl_hash,
+    parser::{
+        typing::{check_prog, TypeEnv},
+        value::{IDLArgs, IDLField, IDLValue},
+    },
+    types::Type,
+    IDLProg,
+};
+use once_cell::sync::Lazy;
+
+/// The name of the payload type of an NNS function, by the number of the function in the
+/// `NnsFunction` enum of governance.
+pub fn payload_type_name(nns_function: i32) -> Option<&'static str> {
+    Some(match nns_function {
+        2 => "AddNodesToSubnetPayload",
+        5 => "BlessReplicaVersionPayload",
+        7 => "UpdateSubnetPayload",
+        8 => "AddNodeOperatorPayload",
+        10 => "UpdateIcpXdrConversionRatePayload",
+        11 => "UpdateSubnetReplicaVersionPayload",
+        13 => "RemoveNodesFromSubnetPayload",
+        14 => "SetAuthorizedSubnetworkListArgs",
+        16 => "UpdateNodeOperatorConfigPayload",
+        18 => "RemoveNodesPayload",
+        21 => "AddOrRemoveDataCentersProposalPayload",
+        22 => "UpdateUnassignedNodesConfigPayload",
+        23 => "RemoveNodeOperatorsPayload",
+        _ => return None,
+    })
+}
+
+static PAYLOAD_TYPES: Lazy<Option<TypeEnv>> = Lazy::new(|| {
+    let idl = embedded_candid("nns_function_payloads.did").ok()?;
+    let ast = candid::pretty_parse::<IDLProg>("nns_function_payloads.did", &idl).ok()?;
+    let mut env = TypeEnv::new();
+    check_prog(&mut env, &ast).ok()?;
+    Some(env)
+});
+
+/// Decodes the payload of an NNS function, if its type is known.
+pub fn decode_payload(nns_function: i32, payload: &[u8]) -> Option<IDLValue> {
+    let env = PAYLOAD_TYPES.as_ref()?;
+    let name = payload_type_name(nns_function)?;
+    let mut args =
+        IDLArgs::from_bytes_with_types(payload, env, &[Type::Var(name.to_string())]).ok()?;
+    args.args.pop()
+}
+
+/// Replaces the payloads of the `ExecuteNnsFunction` actions in the arguments or the reply of a
+/// governance method by their decoded values, when they are known.
+pub fn decode_payloads(args: &mut IDLArgs) {
+    for value in &mut args.args {
+        decode_value(value);
+    }
+}
+
+fn decode_value(value: &mut IDLValue) {
+    match value {
+        IDLValue::Record(fields) => {
+            if let Some(decoded) = decode_execute_nns_function(fields) {
+                for field in fields.iter_mut() {
+                    if field.id.get_id() == idl_hash("payload") {
+                        field.val = decoded;
+                        return;
+                    }
+                }
+            }
+            for field in fields {
+                decode_value(&mut field.val);
+            }
+        }
+        IDLValue::Variant(variant) => decode_value(&mut (variant.0).val),
+        IDLValue::Opt(value) => decode_value(value),
+        IDLValue::Vec(values) => {
+            // Blobs are vectors of bytes, and need not be searched.
+            if !matches!(values.first(), Some(IDLValue::Nat8(_))) {
+                values.iter_mut().for_each(decode_value);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Decodes the payload of a record `{ nns_function : int32; payload : blob }`.
+fn decode_execute_nns_function(fields: &[IDLField]) -> Option<IDLValue> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|field| field.id.get_id() == idl_hash(name))
+            .map(|field| &field.val)
+    };
+    let nns_function = match field("nns_function")? {
+        IDLValue::Int32(nns_function) => *nns_function,
+        _ => return None,
+    };
+    let payload = match field("payload")? {
+        IDLValue::Vec(bytes) => bytes
+            .iter()
+            .map(|byte| match byte {
+                IDLValue::Nat8(byte) => Some(*byte),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    decode_payload(nns_function, &payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_payloads;
+    use candid::{parser::value::IDLArgs, Encode, Principal};
+
+    #[test]
+    fn decodes_payloads() {
+        #[derive(candid::CandidType)]
+        struct UpdateSubnetReplicaVersionPayload {
+            subnet_id: Principal,
+            replica_version_id: String,
+        }
+        let payload = Encode!(&UpdateSubnetReplicaVersionPayload {
+            subnet_id: Principal::anonymous(),
+            replica_version_id: "abc".to_string(),
+        })
+        .unwrap();
+        let mut args: IDLArgs = format!(
+            "(record {{ action = opt variant {{ ExecuteNnsFunction = record {{ nns_function = 11 \
+             : int32; payload = blob \"{}\" }} }} }})",
+            payload
+                .iter()
+                .map(|byte| format!("\\{:02x}", byte))
+                .collect::<String>()
+        )
+        .parse()
+        .unwrap();
+        decode_payloads(&mut args);
+        let text = args.to_string();
+        assert!(text.contains("replica_version_id = \"abc\""), "{}", text);
+        assert!(
+            text.contains("subnet_id = principal \"2vxsx-fae\""),
+            "{}",
+            text
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Notifications of the outcome of the messages sent, e.g. to tell a treasury system that a
+//! transfer was executed, without polling the output of quill.
+//!
+//! The outcome of every call with a known outcome is rendered as a JSON object, which is given on
+//! the standard input of the `--on-complete` command, and posted to the `--webhook` URL.
+
+use crate::lib::{get_http_client, AnyhowResult};
+use anyhow::{bail, Context};
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The outcome of a call.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Completion {
+    pub request_id: String,
+    pub canister_id: String,
+    pub method_name: String,
+    /// `replied` or `rejected`.
+    pub status: &'static str,
+    /// The reply, in the JSON mapping of candid values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reject_code: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reject_message: Option<String>,
+}
+
+/// Runs the command with a shell, with the JSON of the outcome on its standard input.
+pub fn run_command(command: &str, completion: &Completion) -> AnyhowResult {
+    let json = serde_json::to_vec(completion)?;
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Cannot run {}", command))?;
+    // The command may not read its input, which is not an error.
+    let _ = child.stdin.take().unwrap().write_all(&json);
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} failed: {}", command, status);
+    }
+    Ok(())
+}
+
+/// Posts the JSON of the outcome to the URL.
+pub async fn post_webhook(url: &str, completion: &Completion) -> AnyhowResult {
+    let response = get_http_client()?
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_vec(completion)?)
+        .send()
+        .await
+        .with_context(|| format!("Cannot post to {}", url))?;
+    if !response.status().is_success() {
+        bail!("{} replied {}", url, response.status());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_completion_json() {
+    let completion = Completion {
+        request_id: "0xab".to_string(),
+        canister_id: "ryjl3-tyaaa-aaaaa-aaaba-cai".to_string(),
+        method_name: "send_dfx".to_string(),
+        status: "rejected",
+        reply: None,
+        reject_code: Some(4),
+        reject_message: Some("Insufficient funds".to_string()),
+    };
+    assert_eq!(
+        serde_json::to_string(&completion).unwrap(),
+        r#"{"request_id":"0xab","canister_id":"ryjl3-tyaaa-aaaaa-aaaba-cai","method_name":"send_dfx","status":"rejected","reject_code":4,"reject_message":"Insufficient funds"}"#
+    );
+}
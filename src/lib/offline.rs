@@ -0,0 +1,49 @@
+//! The transport of agents in builds without the `online` feature, which can
+//! sign messages but not send them.
+
+use candid::Principal;
+use ic_agent::{agent::ReplicaV2Transport, AgentError, RequestId};
+use std::future::Future;
+use std::pin::Pin;
+
+/// A transport failing every request.
+pub struct OfflineTransport;
+
+fn offline<T>() -> Result<T, AgentError> {
+    Err(AgentError::MessageError(
+        "This build of quill cannot connect to the IC, it lacks the `online` feature.".to_string(),
+    ))
+}
+
+impl ReplicaV2Transport for OfflineTransport {
+    fn call<'a>(
+        &'a self,
+        _effective_canister_id: Principal,
+        _envelope: Vec<u8>,
+        _request_id: RequestId,
+    ) -> Pin<Box<dyn Future<Output = Result<(), AgentError>> + Send + 'a>> {
+        Box::pin(async { offline() })
+    }
+
+    fn read_state<'a>(
+        &'a self,
+        _effective_canister_id: Principal,
+        _envelope: Vec<u8>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, AgentError>> + Send + 'a>> {
+        Box::pin(async { offline() })
+    }
+
+    fn query<'a>(
+        &'a self,
+        _effective_canister_id: Principal,
+        _envelope: Vec<u8>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, AgentError>> + Send + 'a>> {
+        Box::pin(async { offline() })
+    }
+
+    fn status<'a>(
+        &'a self,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>, AgentError>> + Send + 'a>> {
+        Box::pin(async { offline() })
+    }
+}
@@ -0,0 +1,92 @@
+//! Pacing of the submission of batches of messages. Boundary nodes rate-limit the ingress
+//! messages of a principal and reply to the excess ones with 429 Too Many Requests, so large
+//! batches are submitted at a configurable rate, which slows down on 429 replies.
+
+use ic_agent::AgentError;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The number of times a message refused with 429 is submitted again.
+pub const MAX_RATE_LIMITED_RETRIES: u32 = 8;
+
+// The interval between messages after the first 429 reply, when no rate is configured.
+const FIRST_SLOW_DOWN: Duration = Duration::from_secs(1);
+const MAX_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spaces the submissions of messages.
+#[derive(Default)]
+pub struct Pacer {
+    state: Mutex<PacerState>,
+}
+
+#[derive(Default)]
+struct PacerState {
+    // Zero until the first 429 reply, after which it is at least the configured interval.
+    slowed_down: Duration,
+    last: Option<Instant>,
+}
+
+impl Pacer {
+    /// Waits until the next message can be submitted, given the configured rate.
+    pub async fn wait(&self, messages_per_second: Option<f64>) {
+        let delay = {
+            let mut state = self.state.lock().unwrap();
+            let interval = state.slowed_down.max(min_interval(messages_per_second));
+            let now = Instant::now();
+            let next = state.last.map_or(now, |last| (last + interval).max(now));
+            state.last = Some(next);
+            next - now
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Doubles the interval between messages after a 429 reply, and returns it.
+    pub fn slow_down(&self, messages_per_second: Option<f64>) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let interval = state.slowed_down.max(min_interval(messages_per_second));
+        state.slowed_down = if interval.is_zero() {
+            FIRST_SLOW_DOWN
+        } else {
+            (interval * 2).min(MAX_INTERVAL)
+        };
+        state.slowed_down
+    }
+
+    /// Speeds up gradually after a message was accepted, back to the configured rate.
+    pub fn accepted(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.slowed_down = state.slowed_down * 9 / 10;
+    }
+}
+
+fn min_interval(messages_per_second: Option<f64>) -> Duration {
+    match messages_per_second {
+        Some(rate) if rate > 0.0 => Duration::from_secs_f64(1.0 / rate),
+        _ => Duration::ZERO,
+    }
+}
+
+/// Returns whether the boundary node refused a message because of its rate limit.
+pub fn is_rate_limited(err: &AgentError) -> bool {
+    matches!(err, AgentError::HttpError(payload) if payload.status == 429)
+}
+
+#[test]
+fn test_slow_down() {
+    let pacer = Pacer::default();
+    assert_eq!(pacer.slow_down(None), Duration::from_secs(1));
+    assert_eq!(pacer.slow_down(None), Duration::from_secs(2));
+    pacer.accepted();
+    assert_eq!(pacer.slow_down(None), Duration::from_millis(3600));
+
+    let pacer = Pacer::default();
+    assert_eq!(pacer.slow_down(Some(10.0)), Duration::from_millis(200));
+    for _ in 0..100 {
+        pacer.accepted();
+    }
+    // The configured rate still applies after speeding up.
+    assert_eq!(pacer.slow_down(Some(10.0)), Duration::from_millis(200));
+    assert_eq!(min_interval(Some(0.0)), Duration::ZERO);
+}
@@ -0,0 +1,509 @@
+//! The key usage policy enforced before signing.
+//!
+//! An organization can restrict what keys may be used for with a policy file
+//! `policy.toml` in the configuration directory, for example:
+//!
+//! ```toml
+//! # The keys the policy applies to; all keys if empty.
+//! principals = ["fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae"]
+//! # The methods which may be signed; all methods if empty.
+//! allowed_operations = ["send_dfx", "manage_neuron"]
+//! # The accounts ICP may be transferred to; all accounts if empty.
+//! allowed_destinations = ["345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752"]
+//! # The maximum amount of ICP transferred per (UTC) day, including fees.
+//! max_icp_per_day = "100"
+//! ```
+//!
+//! The policy must be signed with `quill sign-policy`, which writes the
+//! signature to `policy.toml.sig`, and the principal of the signing key must be
+//! set in `QUILL_POLICY_SIGNER`. The amounts transferred on the current day are
+//! recorded in `policy-spending.json`.
+//!
+//! The destinations and the daily maximum apply to ledger transfers and to the
+//! neuron commands moving ICP out of a neuron. When either is set, other calls
+//! which may move funds, e.g. SNS ledger transfers, are refused, as the policy
+//! cannot check them.
+
+use crate::lib::{
+    audit::CounterSignature,
+    config::config_dir,
+    get_account_id, governance_canister_id, ledger_canister_id,
+    nns_types::icpts::{E8s, Icp},
+    AnyhowResult,
+};
+use anyhow::{anyhow, bail, ensure, Context};
+use candid::{CandidType, Decode, Principal};
+use ic_nns_governance::pb::v1::{manage_neuron::Command, ManageNeuron};
+use ledger_canister::Tokens;
+use openssl::{
+    bn::BigNum,
+    ecdsa::EcdsaSig,
+    hash::MessageDigest,
+    pkey::{Id, PKey},
+    sign::Verifier,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// The methods which may move funds on canisters other than the ones the policy
+// checks, e.g. SNS ledgers and governance canisters.
+const TRANSFER_METHODS: &[&str] = &[
+    "send_dfx",
+    "transfer",
+    "icrc1_transfer",
+    "icrc2_approve",
+    "icrc2_transfer_from",
+    "manage_neuron",
+];
+
+/// The restrictions of a policy file.
+#[derive(Debug, Default, Deserialize)]
+pub struct Policy {
+    #[serde(default)]
+    pub principals: Vec<String>,
+    #[serde(default)]
+    pub allowed_operations: Vec<String>,
+    #[serde(default)]
+    pub allowed_destinations: Vec<String>,
+    pub max_icp_per_day: Option<String>,
+}
+
+// The amount transferred on one day.
+#[derive(Default, Deserialize, Serialize)]
+struct Spending {
+    day: u64,
+    spent_e8s: u64,
+}
+
+// Only the fields of a ledger transfer the policy restricts are decoded.
+#[derive(CandidType, Deserialize)]
+struct Transfer {
+    to: String,
+    amount: Tokens,
+    fee: Tokens,
+}
+
+/// Returns the path of the policy file, which may not exist.
+pub fn policy_path() -> AnyhowResult<PathBuf> {
+    Ok(config_dir()?.join("policy.toml"))
+}
+
+/// Returns the path of the signature of a policy file.
+pub fn signature_path(policy: &Path) -> PathBuf {
+    let mut path = policy.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+fn spending_path() -> AnyhowResult<PathBuf> {
+    Ok(config_dir()?.join("policy-spending.json"))
+}
+
+/// Loads the policy from the configuration directory, if there is one, after
+/// verifying its signature.
+pub fn load_policy() -> AnyhowResult<Option<Policy>> {
+    let path = policy_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        std::fs::read(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let signer = std::env::var("QUILL_POLICY_SIGNER").map_err(|_| {
+        anyhow!(
+            "{} exists but QUILL_POLICY_SIGNER is not set to the principal which signed it",
+            path.display()
+        )
+    })?;
+    let signer = Principal::from_text(&signer)
+        .with_context(|| format!("Invalid principal in QUILL_POLICY_SIGNER: {}", signer))?;
+    let sig_path = signature_path(&path);
+    let signature: CounterSignature = serde_json::from_slice(
+        &std::fs::read(&sig_path)
+            .with_context(|| format!("Cannot read the policy signature {}", sig_path.display()))?,
+    )
+    .with_context(|| format!("Cannot parse {}", sig_path.display()))?;
+    verify_signature(&signature, signer, &openssl::sha::sha256(&content))
+        .with_context(|| format!("The signature of {} is not valid", path.display()))?;
+    let policy = toml::from_str(std::str::from_utf8(&content)?)
+        .with_context(|| format!("Cannot parse {}", path.display()))?;
+    Ok(Some(policy))
+}
+
+// Verifies a signature made by `audit::counter_sign` with a secp256k1 or
+// Ed25519 key of the given principal.
+fn verify_signature(signature: &CounterSignature, signer: Principal, data: &[u8]) -> AnyhowResult {
+    let public_key = hex::decode(&signature.public_key)?;
+    ensure!(
+        Principal::self_authenticating(&public_key) == signer,
+        "it was made by {} instead of {}",
+        Principal::self_authenticating(&public_key),
+        signer
+    );
+    let key = PKey::public_key_from_der(&public_key)?;
+    let raw = hex::decode(&signature.signature)?;
+    let valid = if key.id() == Id::ED25519 {
+        Verifier::new_without_digest(&key)?.verify_oneshot(&raw, data)?
+    } else {
+        ensure!(raw.len() == 64, "unexpected signature length {}", raw.len());
+        let der = EcdsaSig::from_private_components(
+            BigNum::from_slice(&raw[..32])?,
+            BigNum::from_slice(&raw[32..])?,
+        )?
+        .to_der()?;
+        Verifier::new(MessageDigest::sha256(), &key)?.verify_oneshot(&der, data)?
+    };
+    ensure!(valid, "the signature does not match");
+    Ok(())
+}
+
+impl Policy {
+    /// Checks that the key `sender` may sign a call, and returns the amount it
+    /// transfers, which counts towards the daily maximum.
+    pub fn check(
+        &self,
+        sender: Principal,
+        canister_id: Principal,
+        method_name: &str,
+        args: &[u8],
+    ) -> AnyhowResult<E8s> {
+        if !self.principals.is_empty() && !self.principals.contains(&sender.to_text()) {
+            return Ok(E8s::default());
+        }
+        if !self.allowed_operations.is_empty()
+            && !self.allowed_operations.iter().any(|op| op == method_name)
+        {
+            bail!("The policy does not allow signing {} calls", method_name);
+        }
+        if canister_id == ledger_canister_id() && method_name == "send_dfx" {
+            let transfer = Decode!(args, Transfer).context("Cannot decode the transfer")?;
+            self.check_destination(&transfer.to)?;
+            return E8s::from(transfer.amount)
+                .checked_add(E8s::from(transfer.fee))
+                .ok_or_else(|| anyhow!("The transferred amount overflows"));
+        }
+        let restricted = !self.allowed_destinations.is_empty() || self.max_icp_per_day.is_some();
+        if !restricted {
+            return Ok(E8s::default());
+        }
+        if canister_id == governance_canister_id() && method_name == "manage_neuron" {
+            return self.check_manage_neuron(sender, args);
+        }
+        if TRANSFER_METHODS.contains(&method_name) {
+            bail!(
+                "The policy restricts transfers and cannot check {} calls to {}",
+                method_name,
+                canister_id
+            );
+        }
+        Ok(E8s::default())
+    }
+
+    // Checks the commands of `manage_neuron` moving ICP out of a neuron, and
+    // returns the amount they transfer.
+    fn check_manage_neuron(&self, sender: Principal, args: &[u8]) -> AnyhowResult<E8s> {
+        let manage_neuron =
+            Decode!(args, ManageNeuron).context("Cannot decode the neuron command")?;
+        let check_controller = |controller: Option<Principal>, command: &str| match controller
+            .filter(|controller| *controller != sender)
+        {
+            Some(controller) => bail!(
+                "The policy restricts transfers and cannot check {} to {}",
+                command,
+                controller
+            ),
+            None => Ok(E8s::default()),
+        };
+        match manage_neuron.command {
+            Some(Command::Disburse(disburse)) => {
+                let to = match &disburse.to_account {
+                    Some(account) => hex::encode(&account.hash),
+                    None => get_account_id(sender)?.to_hex(),
+                };
+                self.check_destination(&to)?;
+                match disburse.amount {
+                    Some(amount) => Ok(E8s::new(amount.e8s)),
+                    None if self.max_icp_per_day.is_some() => bail!(
+                        "The policy limits the ICP transferred per day and cannot check \
+                         disbursing a whole neuron; give the amount with --disburse-amount"
+                    ),
+                    None => Ok(E8s::default()),
+                }
+            }
+            Some(Command::DisburseToNeuron(disburse)) => check_controller(
+                disburse.new_controller.map(|controller| controller.0),
+                "disbursing to a neuron",
+            ),
+            Some(Command::Spawn(spawn)) => check_controller(
+                spawn.new_controller.map(|controller| controller.0),
+                "spawning a neuron",
+            ),
+            _ => Ok(E8s::default()),
+        }
+    }
+
+    fn check_destination(&self, account: &str) -> AnyhowResult {
+        if !self.allowed_destinations.is_empty()
+            && !self
+                .allowed_destinations
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(account))
+        {
+            bail!(
+                "The policy does not allow transfers to the account {}",
+                account
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks that `amount` can be spent on top of `spent` on one day.
+    fn check_daily_limit(&self, spent: E8s, amount: E8s) -> AnyhowResult<E8s> {
+        let total = spent
+            .checked_add(amount)
+            .ok_or_else(|| anyhow!("The transferred amount overflows"))?;
+        if let Some(max) = &self.max_icp_per_day {
+            let max = E8s::from(max.parse::<Icp>().context("Invalid max_icp_per_day")?);
+            if total > max {
+                bail!(
+                    "The policy allows transferring {} ICP per day, {} ICP were already signed today",
+                    Icp::from(max),
+                    Icp::from(spent)
+                );
+            }
+        }
+        Ok(total)
+    }
+}
+
+/// Enforces the policy of the configuration directory, if there is one, on a
+/// call about to be signed by `sender`, and records the transferred amount.
+pub fn enforce(
+    sender: Principal,
+    canister_id: Principal,
+    method_name: &str,
+    args: &[u8],
+) -> AnyhowResult {
+    let policy = match load_policy()? {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    let amount = policy.check(sender, canister_id, method_name, args)?;
+    if amount == E8s::default() && policy.max_icp_per_day.is_none() {
+        return Ok(());
+    }
+    let today = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs()
+        / SECONDS_PER_DAY;
+    let path = spending_path()?;
+    let spending = match std::fs::read(&path) {
+        Ok(content) => serde_json::from_slice::<Spending>(&content)
+            .with_context(|| format!("Cannot parse {}", path.display()))?,
+        Err(_) => Spending::default(),
+    };
+    let spent = if spending.day == today {
+        E8s::new(spending.spent_e8s)
+    } else {
+        E8s::default()
+    };
+    let total = policy.check_daily_limit(spent, amount)?;
+    let spending = Spending {
+        day: today,
+        spent_e8s: total.get(),
+    };
+    std::fs::write(&path, serde_json::to_string(&spending)?)
+        .with_context(|| format!("Cannot write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Policy, Transfer};
+    use crate::lib::{
+        get_account_id, governance_canister_id, ledger_canister_id, nns_types::icpts::E8s,
+    };
+    use candid::{Encode, Principal};
+    use ic_base_types::PrincipalId;
+    use ic_nns_common::pb::v1::NeuronId;
+    use ic_nns_governance::pb::v1::{
+        manage_neuron::{disburse::Amount, Command, Disburse, DisburseToNeuron, Spawn},
+        ManageNeuron,
+    };
+    use ledger_canister::{AccountIdentifier, Tokens};
+
+    const ACCOUNT_ID: &str = "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752";
+
+    fn transfer(to: &str, e8s: u64) -> Vec<u8> {
+        Encode!(&Transfer {
+            to: to.to_string(),
+            amount: Tokens::from_e8s(e8s),
+            fee: Tokens::from_e8s(10_000),
+        })
+        .unwrap()
+    }
+
+    fn manage_neuron(command: Command) -> Vec<u8> {
+        Encode!(&ManageNeuron {
+            id: Some(NeuronId { id: 123 }),
+            command: Some(command),
+            neuron_id_or_subaccount: None,
+        })
+        .unwrap()
+    }
+
+    fn disburse(to: Option<&str>, e8s: Option<u64>) -> Vec<u8> {
+        manage_neuron(Command::Disburse(Disburse {
+            to_account: to.map(|to| ledger_canister::protobuf::AccountIdentifier {
+                hash: AccountIdentifier::from_hex(to).unwrap().to_vec(),
+            }),
+            amount: e8s.map(|e8s| Amount { e8s }),
+        }))
+    }
+
+    #[test]
+    fn checks_operations_and_destinations() {
+        let sender = Principal::anonymous();
+        let policy = Policy {
+            allowed_operations: vec!["send_dfx".to_string()],
+            allowed_destinations: vec![ACCOUNT_ID.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            policy
+                .check(
+                    sender,
+                    ledger_canister_id(),
+                    "send_dfx",
+                    &transfer(ACCOUNT_ID, 5)
+                )
+                .unwrap(),
+            E8s::new(10_005)
+        );
+        assert!(policy
+            .check(
+                sender,
+                ledger_canister_id(),
+                "send_dfx",
+                &transfer(&ACCOUNT_ID.replace('3', "4"), 5)
+            )
+            .is_err());
+        assert!(policy
+            .check(sender, governance_canister_id(), "manage_neuron", &[])
+            .is_err());
+
+        let scoped = Policy {
+            principals: vec![governance_canister_id().to_text()],
+            ..policy
+        };
+        assert!(scoped
+            .check(sender, governance_canister_id(), "manage_neuron", &[])
+            .is_ok());
+    }
+
+    #[test]
+    fn checks_neuron_commands() {
+        let sender = Principal::anonymous();
+        let other = governance_canister_id();
+        let policy = Policy {
+            allowed_destinations: vec![ACCOUNT_ID.to_string()],
+            max_icp_per_day: Some("1".to_string()),
+            ..Default::default()
+        };
+        let check =
+            |args: &[u8]| policy.check(sender, governance_canister_id(), "manage_neuron", args);
+        assert_eq!(
+            check(&disburse(Some(ACCOUNT_ID), Some(5))).unwrap(),
+            E8s::new(5)
+        );
+        let other_account = get_account_id(governance_canister_id()).unwrap().to_hex();
+        assert!(check(&disburse(Some(&other_account), Some(5))).is_err());
+        // The account of the key is not an allowed destination.
+        assert!(check(&disburse(None, Some(5))).is_err());
+        // The stake of the neuron, and so the amount, is unknown.
+        assert!(check(&disburse(Some(ACCOUNT_ID), None)).is_err());
+
+        let to_neuron = |controller: Principal| {
+            manage_neuron(Command::DisburseToNeuron(DisburseToNeuron {
+                new_controller: Some(PrincipalId(controller)),
+                amount_e8s: 100_000_000,
+                dissolve_delay_seconds: 0,
+                kyc_verified: true,
+                nonce: 0,
+            }))
+        };
+        assert!(check(&to_neuron(other)).is_err());
+        assert_eq!(check(&to_neuron(sender)).unwrap(), E8s::default());
+
+        let spawn = |controller: Option<Principal>| {
+            manage_neuron(Command::Spawn(Spawn {
+                new_controller: controller.map(PrincipalId),
+                ..Default::default()
+            }))
+        };
+        assert!(check(&spawn(Some(other))).is_err());
+        assert_eq!(check(&spawn(None)).unwrap(), E8s::default());
+
+        let unrestricted = Policy::default();
+        assert!(unrestricted
+            .check(
+                sender,
+                governance_canister_id(),
+                "manage_neuron",
+                &disburse(None, None)
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn refuses_unchecked_transfers() {
+        let sender = Principal::anonymous();
+        // The ledger of an SNS.
+        let sns_ledger = Principal::from_slice(&[0, 0, 0, 0, 2, 0, 0, 1, 1, 1]);
+        let policy = Policy {
+            allowed_destinations: vec![ACCOUNT_ID.to_string()],
+            ..Default::default()
+        };
+        assert!(policy.check(sender, sns_ledger, "transfer", &[]).is_err());
+        assert!(policy
+            .check(sender, sns_ledger, "icrc1_transfer", &[])
+            .is_err());
+        assert!(policy
+            .check(sender, ledger_canister_id(), "transfer", &[])
+            .is_err());
+        assert!(policy
+            .check(sender, sns_ledger, "manage_neuron", &[])
+            .is_err());
+        assert!(policy
+            .check(sender, sns_ledger, "icrc1_balance_of", &[])
+            .is_ok());
+
+        let limited = Policy {
+            max_icp_per_day: Some("1".to_string()),
+            ..Default::default()
+        };
+        assert!(limited
+            .check(sender, sns_ledger, "icrc1_transfer", &[])
+            .is_err());
+        assert!(Policy::default()
+            .check(sender, sns_ledger, "icrc1_transfer", &[])
+            .is_ok());
+    }
+
+    #[test]
+    fn checks_daily_limit() {
+        let policy = Policy {
+            max_icp_per_day: Some("1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy
+                .check_daily_limit(E8s::new(60_000_000), E8s::new(40_000_000))
+                .unwrap(),
+            E8s::new(100_000_000)
+        );
+        assert!(policy
+            .check_daily_limit(E8s::new(60_000_000), E8s::new(40_000_001))
+            .is_err());
+    }
+}
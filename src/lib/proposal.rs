@@ -0,0 +1,93 @@
+//! Checks of the text of proposals against the limits of the governance canisters, made before
+//! signing so that a proposal is not rejected for a long summary after the signing ceremony. The
+//! NNS and SNS governance canisters enforce the same limits.
+
+use crate::lib::{is_quiet, read_from_file, AnyhowResult};
+use anyhow::{bail, ensure};
+use std::path::Path;
+
+const TITLE_BYTES_MIN: usize = 5;
+const TITLE_BYTES_MAX: usize = 256;
+const SUMMARY_BYTES_MAX: usize = 30_000;
+const URL_BYTES_MIN: usize = 10;
+const URL_BYTES_MAX: usize = 2048;
+const MOTION_TEXT_BYTES_MAX: usize = 10_000;
+
+/// Returns the summary given as an option or read from a file, e.g. a Markdown document.
+pub fn read_summary(summary: Option<String>, summary_file: Option<&Path>) -> AnyhowResult<String> {
+    match (summary, summary_file) {
+        (Some(summary), _) => Ok(summary),
+        (None, Some(path)) => Ok(read_from_file(path)?.trim_end().to_string()),
+        (None, None) => bail!("Either --summary or --summary-file is required"),
+    }
+}
+
+/// Fails if the title, summary or URL of a proposal exceeds the limits of governance.
+pub fn check_proposal_text(title: &str, summary: &str, url: &str) -> AnyhowResult {
+    ensure!(
+        (TITLE_BYTES_MIN..=TITLE_BYTES_MAX).contains(&title.len()),
+        "The title of the proposal must have between {} and {} bytes, it has {}",
+        TITLE_BYTES_MIN,
+        TITLE_BYTES_MAX,
+        title.len()
+    );
+    ensure!(
+        summary.len() <= SUMMARY_BYTES_MAX,
+        "The summary of the proposal must have at most {} bytes, it has {}",
+        SUMMARY_BYTES_MAX,
+        summary.len()
+    );
+    if !url.is_empty() {
+        ensure!(
+            (URL_BYTES_MIN..=URL_BYTES_MAX).contains(&url.len()),
+            "The URL of the proposal must have between {} and {} bytes, it has {}",
+            URL_BYTES_MIN,
+            URL_BYTES_MAX,
+            url.len()
+        );
+        ensure!(
+            url.starts_with("https://"),
+            "The URL of the proposal must start with https://"
+        );
+    }
+    Ok(())
+}
+
+/// Fails if the text of a motion exceeds the limit of governance.
+pub fn check_motion_text(motion_text: &str) -> AnyhowResult {
+    ensure!(
+        motion_text.len() <= MOTION_TEXT_BYTES_MAX,
+        "The motion text must have at most {} bytes, it has {}",
+        MOTION_TEXT_BYTES_MAX,
+        motion_text.len()
+    );
+    Ok(())
+}
+
+/// Prints the text of a proposal on standard error, to be read before it is signed.
+pub fn print_preview(title: &str, summary: &str, url: &str) {
+    if is_quiet() {
+        return;
+    }
+    eprintln!("Title:   {}", title);
+    if !url.is_empty() {
+        eprintln!("URL:     {}", url);
+    }
+    eprintln!("Summary ({} bytes):\n\n{}\n", summary.len(), summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_motion_text, check_proposal_text};
+
+    #[test]
+    fn checks_limits() {
+        assert!(check_proposal_text("Adopt the roadmap", "", "").is_ok());
+        assert!(check_proposal_text("Hi", "", "").is_err());
+        assert!(check_proposal_text(&"x".repeat(257), "", "").is_err());
+        assert!(check_proposal_text("Adopt the roadmap", &"x".repeat(30_001), "").is_err());
+        assert!(check_proposal_text("Adopt the roadmap", "", "https://forum.dfinity.org").is_ok());
+        assert!(check_proposal_text("Adopt the roadmap", "", "http://forum.dfinity.org").is_err());
+        assert!(check_motion_text(&"x".repeat(10_001)).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! A persistent record of the submission of a bundle, from which `send --resume-from` resumes a
+//! submission interrupted by a crash or a reboot, without the original bundle.
+//!
+//! The state directory holds a copy of the bundle, `bundle.json`, and the state of every update
+//! call by request id, `state.json`, which is replaced atomically on every change.
+
+use crate::lib::AnyhowResult;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const BUNDLE_FILE: &str = "bundle.json";
+const STATE_FILE: &str = "state.json";
+
+/// The state of the submission of an update call.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageState {
+    /// Not submitted yet.
+    Pending,
+    /// Accepted by the IC, without a known outcome. Submitting it again is harmless: the IC
+    /// executes a request id at most once.
+    Submitted,
+    /// Executed, with a reply.
+    Replied,
+    /// Rejected by the IC or the canister.
+    Rejected,
+}
+
+impl MessageState {
+    /// Returns whether the outcome of the call is known, so that it is not submitted again.
+    pub fn is_done(self) -> bool {
+        matches!(self, MessageState::Replied | MessageState::Rejected)
+    }
+}
+
+/// The states of the update calls of a bundle being submitted.
+pub struct Queue {
+    dir: PathBuf,
+    states: Mutex<BTreeMap<String, MessageState>>,
+}
+
+impl Queue {
+    /// Records a new submission of the bundle in the directory, with all its update calls
+    /// pending.
+    pub fn create(
+        dir: &Path,
+        bundle: &str,
+        request_ids: impl IntoIterator<Item = String>,
+    ) -> AnyhowResult<Self> {
+        let bundle_path = dir.join(BUNDLE_FILE);
+        if bundle_path.exists() {
+            bail!(
+                "{} already records a submission. Resume it with --resume-from {}, or use another \
+                 directory.",
+                dir.display(),
+                dir.display()
+            );
+        }
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create the directory {}", dir.display()))?;
+        let states = request_ids
+            .into_iter()
+            .map(|request_id| (request_id, MessageState::Pending))
+            .collect();
+        save_states(dir, &states)?;
+        write_atomically(&bundle_path, bundle.as_bytes())?;
+        Ok(Queue {
+            dir: dir.to_path_buf(),
+            states: Mutex::new(states),
+        })
+    }
+
+    /// Opens the submission recorded in the directory, and returns it with its bundle.
+    pub fn open(dir: &Path) -> AnyhowResult<(Self, String)> {
+        let bundle_path = dir.join(BUNDLE_FILE);
+        let bundle = std::fs::read_to_string(&bundle_path)
+            .with_context(|| format!("Cannot read {}", bundle_path.display()))?;
+        let state_path = dir.join(STATE_FILE);
+        let states = serde_json::from_slice(
+            &std::fs::read(&state_path)
+                .with_context(|| format!("Cannot read {}", state_path.display()))?,
+        )
+        .with_context(|| format!("Cannot parse {}", state_path.display()))?;
+        let queue = Queue {
+            dir: dir.to_path_buf(),
+            states: Mutex::new(states),
+        };
+        Ok((queue, bundle))
+    }
+
+    pub fn state(&self, request_id: &str) -> Option<MessageState> {
+        self.states.lock().unwrap().get(request_id).copied()
+    }
+
+    /// Records the new state of a call before returning.
+    pub fn set(&self, request_id: &str, state: MessageState) -> AnyhowResult {
+        let mut states = self.states.lock().unwrap();
+        states.insert(request_id.to_string(), state);
+        save_states(&self.dir, &states)
+    }
+
+    /// Returns the states of all the calls, by request id.
+    pub fn states(&self) -> BTreeMap<String, MessageState> {
+        self.states.lock().unwrap().clone()
+    }
+
+    /// Counts the calls in every state.
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for state in self.states.lock().unwrap().values() {
+            *counts
+                .entry(format!("{:?}", state).to_lowercase())
+                .or_default() += 1;
+        }
+        counts
+    }
+}
+
+fn save_states(dir: &Path, states: &BTreeMap<String, MessageState>) -> AnyhowResult {
+    write_atomically(&dir.join(STATE_FILE), &serde_json::to_vec_pretty(states)?)
+}
+
+// Writes a temporary file and renames it, so that a crash leaves either the old or the new file.
+// The file is synced before the rename, so that the new name never refers to unwritten content,
+// and the directory after it, so that the rename survives a power loss.
+fn write_atomically(path: &Path, content: &[u8]) -> AnyhowResult {
+    let tmp = path.with_extension("json.tmp");
+    let mut file =
+        std::fs::File::create(&tmp).with_context(|| format!("Cannot write {}", tmp.display()))?;
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("Cannot write {}", tmp.display()))?;
+    drop(file);
+    std::fs::rename(&tmp, path).with_context(|| format!("Cannot write {}", path.display()))?;
+    // Directories cannot be opened as files on every platform, e.g. Windows.
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageState, Queue};
+
+    #[test]
+    fn resumes_submissions() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Queue::create(
+            dir.path(),
+            "[bundle]",
+            vec!["0xaa".to_string(), "0xbb".to_string()],
+        )
+        .unwrap();
+        queue.set("0xaa", MessageState::Submitted).unwrap();
+        queue.set("0xaa", MessageState::Replied).unwrap();
+        queue.set("0xbb", MessageState::Submitted).unwrap();
+        drop(queue);
+
+        let (queue, bundle) = Queue::open(dir.path()).unwrap();
+        assert_eq!(bundle, "[bundle]");
+        assert_eq!(queue.state("0xaa"), Some(MessageState::Replied));
+        assert!(queue.state("0xaa").unwrap().is_done());
+        assert_eq!(queue.state("0xbb"), Some(MessageState::Submitted));
+        assert!(!queue.state("0xbb").unwrap().is_done());
+        assert_eq!(queue.state("0xcc"), None);
+        assert_eq!(queue.counts().get("replied"), Some(&1));
+        assert_eq!(queue.states().len(), 2);
+
+        assert!(Queue::create(dir.path(), "[bundle]", Vec::new()).is_err());
+    }
+}
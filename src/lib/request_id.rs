@@ -0,0 +1,117 @@
+//! The representation-independent hash of the IC interface specification, which is the request
+//! id of a call and determines the bytes that are signed.
+//!
+//! This is deliberately implemented on top of plain CBOR values rather than with the agent, so
+//! that the request ids of signed messages can be checked independently of how they were
+//! produced.
+
+use crate::lib::AnyhowResult;
+use anyhow::bail;
+use openssl::sha::sha256;
+use serde_cbor::Value;
+
+/// The domain separator prepended to request ids before signing.
+pub const IC_REQUEST_DOMAIN_SEPARATOR: &[u8] = b"\x0Aic-request";
+
+/// Returns the representation-independent hash of a value.
+pub fn hash_value(value: &Value) -> AnyhowResult<[u8; 32]> {
+    Ok(match value {
+        Value::Text(text) => sha256(text.as_bytes()),
+        Value::Bytes(bytes) => sha256(bytes),
+        Value::Integer(n) if *n >= 0 => sha256(&leb128(*n as u128)),
+        Value::Array(values) => {
+            let mut hashes = Vec::with_capacity(values.len() * 32);
+            for value in values {
+                hashes.extend_from_slice(&hash_value(value)?);
+            }
+            sha256(&hashes)
+        }
+        Value::Map(map) => {
+            let mut fields = Vec::with_capacity(map.len());
+            for (key, value) in map {
+                let key = match key {
+                    Value::Text(key) => key,
+                    _ => bail!("Map keys must be text, found {:?}", key),
+                };
+                let mut field = sha256(key.as_bytes()).to_vec();
+                field.extend_from_slice(&hash_value(value)?);
+                fields.push(field);
+            }
+            fields.sort();
+            sha256(&fields.concat())
+        }
+        // The self-describing tag of the envelope does not change the value.
+        Value::Tag(_, value) => hash_value(value)?,
+        _ => bail!("Cannot hash {:?}", value),
+    })
+}
+
+/// Returns the bytes signed for a request with the given id.
+pub fn bytes_to_sign(request_id: &[u8; 32]) -> Vec<u8> {
+    [IC_REQUEST_DOMAIN_SEPARATOR, request_id].concat()
+}
+
+fn leb128(mut n: u128) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_value, leb128};
+    use serde_cbor::Value;
+    use std::collections::BTreeMap;
+
+    fn text(s: &str) -> Value {
+        Value::Text(s.to_string())
+    }
+
+    #[test]
+    fn matches_the_specification_examples() {
+        let mut content = BTreeMap::new();
+        content.insert(text("request_type"), text("call"));
+        content.insert(
+            text("canister_id"),
+            Value::Bytes(vec![0, 0, 0, 0, 0, 0, 0x04, 0xD2]),
+        );
+        content.insert(text("method_name"), text("hello"));
+        content.insert(text("arg"), Value::Bytes(b"DIDL\x00\xFD*".to_vec()));
+        assert_eq!(
+            hex::encode(hash_value(&Value::Map(content.clone())).unwrap()),
+            "8781291c347db32a9d8c10eb62b710fce5a93be676474c42babc74c51858f94b"
+        );
+
+        content.insert(text("sender"), Value::Bytes(vec![0x04]));
+        content.insert(
+            text("ingress_expiry"),
+            Value::Integer(1_685_570_400_000_000_000),
+        );
+        assert_eq!(
+            hex::encode(hash_value(&Value::Map(content)).unwrap()),
+            "1d1091364d6bb8a6c16b203ee75467d59ead468f523eb058880ae8ec80e2b101"
+        );
+    }
+
+    #[test]
+    fn rejects_values_without_a_hash() {
+        assert!(hash_value(&Value::Integer(-1)).is_err());
+        assert!(hash_value(&Value::Null).is_err());
+        let mut map = BTreeMap::new();
+        map.insert(Value::Integer(1), text("a"));
+        assert!(hash_value(&Value::Map(map)).is_err());
+    }
+
+    #[test]
+    fn encodes_leb128() {
+        assert_eq!(leb128(0), vec![0]);
+        assert_eq!(leb128(624_485), vec![0xE5, 0x8E, 0x26]);
+    }
+}
@@ -0,0 +1,110 @@
+//! Framing of bundles sent over a one-way serial link.
+//!
+//! The receiver cannot ask for a retransmission, so every bundle is sent as a
+//! single frame which the receiver can validate on its own:
+//!
+//! ```text
+//! "QUIL" | payload length (u32, big endian) | payload | SHA-256 of the payload
+//! ```
+//!
+//! The serial port must be configured (e.g. with `stty`) on both sides before
+//! quill opens it as a file.
+
+use crate::lib::AnyhowResult;
+use anyhow::{bail, Context};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"QUIL";
+
+/// Returns the frame carrying the payload.
+pub fn encode_frame(payload: &[u8]) -> AnyhowResult<Vec<u8>> {
+    let len = u32::try_from(payload.len()).context("The bundle is too large for a frame")?;
+    let mut frame = Vec::with_capacity(payload.len() + 40);
+    frame.extend_from_slice(MAGIC);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame.extend_from_slice(&openssl::sha::sha256(payload));
+    Ok(frame)
+}
+
+/// Reads the next frame, skipping any noise before it, and returns its payload
+/// once its checksum is verified. Payloads larger than `limit` are rejected.
+pub fn read_frame(reader: &mut impl Read, limit: u64) -> AnyhowResult<Vec<u8>> {
+    let mut window = [0u8; 4];
+    let mut byte = [0u8; 1];
+    while &window != MAGIC {
+        reader
+            .read_exact(&mut byte)
+            .context("The link closed before a frame started")?;
+        window.rotate_left(1);
+        window[3] = byte[0];
+    }
+    let mut len = [0u8; 4];
+    reader
+        .read_exact(&mut len)
+        .context("The frame is truncated")?;
+    let len = u32::from_be_bytes(len);
+    if u64::from(len) > limit {
+        bail!(
+            "The frame announces {} bytes, the maximum is {} bytes",
+            len,
+            limit
+        );
+    }
+    let mut payload = vec![0; len as usize];
+    let mut checksum = [0u8; 32];
+    reader
+        .read_exact(&mut payload)
+        .and_then(|_| reader.read_exact(&mut checksum))
+        .context("The frame is truncated")?;
+    if openssl::sha::sha256(&payload) != checksum {
+        bail!("The checksum of the frame does not match, the transfer was corrupted");
+    }
+    Ok(payload)
+}
+
+/// Writes the payload as a frame to the serial device.
+pub fn send(device: &Path, payload: &[u8]) -> AnyhowResult {
+    let mut port = std::fs::OpenOptions::new()
+        .write(true)
+        .open(device)
+        .with_context(|| format!("Cannot open {}", device.display()))?;
+    port.write_all(&encode_frame(payload)?)
+        .and_then(|_| port.flush())
+        .with_context(|| format!("Cannot write to {}", device.display()))
+}
+
+/// Waits for a frame on the serial device and returns its payload.
+pub fn receive(device: &Path, limit: u64) -> AnyhowResult<Vec<u8>> {
+    let mut port =
+        std::fs::File::open(device).with_context(|| format!("Cannot open {}", device.display()))?;
+    read_frame(&mut port, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_frame, read_frame};
+
+    #[test]
+    fn round_trips_frames() {
+        let payload = br#"[{"ingress": {}}]"#;
+        let mut stream = b"\x00noise".to_vec();
+        stream.extend(encode_frame(payload).unwrap());
+        assert_eq!(read_frame(&mut stream.as_slice(), 1024).unwrap(), payload);
+    }
+
+    #[test]
+    fn rejects_bad_frames() {
+        let frame = encode_frame(b"bundle").unwrap();
+
+        let mut corrupted = frame.clone();
+        corrupted[9] ^= 1;
+        assert!(read_frame(&mut corrupted.as_slice(), 1024).is_err());
+
+        assert!(read_frame(&mut &frame[..frame.len() - 1], 1024).is_err());
+        assert!(read_frame(&mut frame.as_slice(), 5).is_err());
+        assert!(read_frame(&mut &b"no frame"[..], 1024).is_err());
+    }
+}
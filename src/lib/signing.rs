@@ -1,15 +1,62 @@
-use crate::lib::get_idl_string;
+use crate::lib::{get_idl_string, ArgPart, OutputFormat};
 use crate::lib::{AnyhowResult, AuthInfo};
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use candid::Principal;
-use ic_agent::agent::UpdateBuilder;
-use ic_agent::RequestId;
+use clap::crate_version;
+use ic_agent::agent::{QueryBuilder, UpdateBuilder};
+use ic_agent::{Agent, RequestId};
 use serde::{Deserialize, Serialize};
 use serde_cbor::Value;
 use std::convert::TryFrom;
 use std::time::Duration;
 
-use super::get_agent;
+use super::{
+    cycles_minting_canister_id, genesis_token_canister_id, get_agent, get_network,
+    governance_canister_id, ledger_canister_id, policy, registry_canister_id,
+};
+
+/// Maximum length of the hex-encoded content of a single signed message. The
+/// IC refuses ingress messages of more than a few MiB, so anything longer
+/// cannot be legitimate.
+pub const MAX_CONTENT_HEX_LEN: usize = 2 * 4 * 1024 * 1024;
+
+/// Maximum size of a signed ingress message on an application subnet.
+pub const MAX_INGRESS_BYTES: usize = 2 * 1024 * 1024;
+
+/// Maximum size of a signed ingress message on the NNS subnet.
+pub const MAX_NNS_INGRESS_BYTES: usize = 3584 * 1024;
+
+/// Returns the maximum size of a signed ingress message to the canister.
+pub fn max_ingress_bytes(canister_id: Principal) -> usize {
+    let nns_canisters = [
+        ledger_canister_id(),
+        governance_canister_id(),
+        genesis_token_canister_id(),
+        registry_canister_id(),
+        cycles_minting_canister_id(),
+    ];
+    if nns_canisters.contains(&canister_id) {
+        MAX_NNS_INGRESS_BYTES
+    } else {
+        MAX_INGRESS_BYTES
+    }
+}
+
+/// Fails if a signed message is too large for the IC to accept.
+pub fn check_ingress_size(canister_id: Principal, method_name: &str, size: usize) -> AnyhowResult {
+    let max = max_ingress_bytes(canister_id);
+    if size > max {
+        bail!(
+            "The signed {} message is {} bytes, more than the {} bytes the IC accepts. Large \
+             payloads must be split, e.g. by uploading a wasm module in chunks or by making \
+             several proposals.",
+            method_name,
+            size,
+            max
+        );
+    }
+    Ok(())
+}
 
 #[derive(Debug)]
 pub struct MessageError(String);
@@ -28,29 +75,264 @@ pub struct SignedMessageWithRequestId {
     pub request_id: Option<RequestId>,
 }
 
+/// The latest version of the format of the signed messages, which this version of quill reads
+/// and writes for messages needing it. Messages without a version were written before versions
+/// were introduced, and have version 0.
+///
+/// The version must be incremented whenever messages change in a way older versions of quill
+/// would misread, and such messages should also record the first version of quill reading them
+/// in a `requires_quill` field.
+///
+/// - 1: the first versioned format.
+/// - 2: messages record the `network` they were signed for.
+/// - 3: messages may carry `preconditions`.
+pub const SCHEMA_VERSION: u32 = 3;
+
+// The version of messages without preconditions, which quill reading version 2 still reads.
+const BASE_SCHEMA_VERSION: u32 = 2;
+
+// The version of quill recorded in `requires_quill`: the one signing the message.
+fn requires_quill() -> Option<String> {
+    Some(crate_version!().to_string())
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct RequestStatus {
+    #[serde(default)]
+    pub schema_version: u32,
+    /// The first version of quill reading the query, as for `Ingress`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_quill: Option<String>,
+    /// The network the query was signed for, as for `Ingress`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
     pub canister_id: String,
     pub request_id: String,
     pub content: String,
 }
 
+const REQUEST_STATUS_FIELDS: &[&str] = &[
+    "schema_version",
+    "requires_quill",
+    "network",
+    "canister_id",
+    "request_id",
+    "content",
+];
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Ingress {
+    #[serde(default)]
+    pub schema_version: u32,
+    /// The first version of quill reading the message, shown by older versions which cannot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_quill: Option<String>,
+    /// The network the message was signed for, see `get_network`. Messages signed before
+    /// messages were tagged have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
     pub call_type: String,
     pub request_id: Option<String>,
     pub content: String,
 }
 
+const INGRESS_FIELDS: &[&str] = &[
+    "schema_version",
+    "requires_quill",
+    "network",
+    "call_type",
+    "request_id",
+    "content",
+];
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct IngressWithRequestId {
     pub ingress: Ingress,
     pub request_status: RequestStatus,
+    /// Conditions which `send` checks right before submitting the message, skipping it if one
+    /// fails. They are not signed: they only protect against sending a message which is no
+    /// longer useful.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preconditions: Vec<Precondition>,
+}
+
+const INGRESS_WITH_REQUEST_ID_FIELDS: &[&str] = &["ingress", "request_status", "preconditions"];
+
+impl IngressWithRequestId {
+    /// Attaches the preconditions to the message. Older versions of quill would refuse the
+    /// field without telling why, so a message with preconditions is written in the current
+    /// format, recording the version of quill it requires.
+    pub fn with_preconditions(mut self, preconditions: Vec<Precondition>) -> Self {
+        if !preconditions.is_empty() {
+            self.ingress.schema_version = SCHEMA_VERSION;
+            self.ingress.requires_quill = requires_quill();
+        }
+        self.preconditions = preconditions;
+        self
+    }
+}
+
+/// A condition on the state of the IC for a message to be submitted.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Precondition {
+    /// The proposal is open, e.g. for a vote on it.
+    ProposalOpen { proposal_id: u64 },
+    /// The neuron is dissolved, e.g. for its disbursement.
+    NeuronDissolved { neuron_id: u64 },
+}
+
+impl std::fmt::Display for Precondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Precondition::ProposalOpen { proposal_id } => {
+                write!(f, "proposal {} is open", proposal_id)
+            }
+            Precondition::NeuronDissolved { neuron_id } => {
+                write!(f, "neuron {} is dissolved", neuron_id)
+            }
+        }
+    }
+}
+
+/// The signed messages of a bundle, in the shapes written by the signing commands.
+pub enum Bundle {
+    /// A single message, e.g. from `--qr` output.
+    Ingress(Ingress),
+    /// Messages without request status queries, e.g. queries.
+    Messages(Vec<Ingress>),
+    /// Update calls, each with the query for its status.
+    WithRequestStatus(Vec<IngressWithRequestId>),
+}
+
+impl Bundle {
+    /// Returns the calls of the bundle, without the request status queries.
+    pub fn ingress_messages(&self) -> Vec<&Ingress> {
+        match self {
+            Bundle::Ingress(msg) => vec![msg],
+            Bundle::Messages(msgs) => msgs.iter().collect(),
+            Bundle::WithRequestStatus(msgs) => msgs.iter().map(|msg| &msg.ingress).collect(),
+        }
+    }
+}
+
+/// Parses a bundle of signed messages.
+///
+/// Bundles written by a newer version of quill, in a format this version does not know, are
+/// rejected with the version of quill they require. Fields which are not part of the format are
+/// rejected unless `allow_unknown_fields` is set, in which case they are ignored.
+pub fn parse_bundle(json: &str, allow_unknown_fields: bool) -> AnyhowResult<Bundle> {
+    let mut bundle: serde_json::Value =
+        serde_json::from_str(json).context("The bundle is not valid JSON")?;
+    check_schema_version(&bundle)?;
+    let with_request_status = match &bundle {
+        serde_json::Value::Array(messages) => messages
+            .first()
+            .map_or(false, |message| message.get("ingress").is_some()),
+        _ => false,
+    };
+    if allow_unknown_fields {
+        match &mut bundle {
+            serde_json::Value::Array(messages) => {
+                for message in messages {
+                    if with_request_status {
+                        retain_fields(message, INGRESS_WITH_REQUEST_ID_FIELDS);
+                        if let Some(ingress) = message.get_mut("ingress") {
+                            retain_fields(ingress, INGRESS_FIELDS);
+                        }
+                        if let Some(request_status) = message.get_mut("request_status") {
+                            retain_fields(request_status, REQUEST_STATUS_FIELDS);
+                        }
+                    } else {
+                        retain_fields(message, INGRESS_FIELDS);
+                    }
+                }
+            }
+            message => retain_fields(message, INGRESS_FIELDS),
+        }
+    }
+    let bundle = match bundle {
+        serde_json::Value::Array(_) if with_request_status => {
+            serde_json::from_value(bundle).map(Bundle::WithRequestStatus)
+        }
+        serde_json::Value::Array(_) => serde_json::from_value(bundle).map(Bundle::Messages),
+        _ => serde_json::from_value(bundle).map(Bundle::Ingress),
+    };
+    bundle.context("Invalid signed message")
+}
+
+// Fails if any message of the bundle has a newer version than this version of quill reads.
+fn check_schema_version(value: &serde_json::Value) -> AnyhowResult {
+    match value {
+        serde_json::Value::Array(values) => values.iter().try_for_each(check_schema_version),
+        serde_json::Value::Object(fields) => {
+            let version = fields.get("schema_version").and_then(|v| v.as_u64());
+            if let Some(version) = version.filter(|&v| v > u64::from(SCHEMA_VERSION)) {
+                match fields.get("requires_quill").and_then(|v| v.as_str()) {
+                    Some(required) => bail!(
+                        "The bundle requires quill >= {} (it has schema version {}, quill {} reads \
+                         versions up to {}).",
+                        required,
+                        version,
+                        crate_version!(),
+                        SCHEMA_VERSION
+                    ),
+                    None => bail!(
+                        "The bundle requires a newer quill (it has schema version {}, quill {} \
+                         reads versions up to {}).",
+                        version,
+                        crate_version!(),
+                        SCHEMA_VERSION
+                    ),
+                }
+            }
+            fields.values().try_for_each(check_schema_version)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn retain_fields(value: &mut serde_json::Value, fields: &[&str]) {
+    if let serde_json::Value::Object(map) = value {
+        map.retain(|name, _| fields.contains(&name.as_str()));
+    }
+}
+
+/// Decodes the hex-encoded content of a message, refusing oversized blobs
+/// before allocating for them.
+pub fn decode_content(content: &str) -> AnyhowResult<Vec<u8>> {
+    if content.len() > MAX_CONTENT_HEX_LEN {
+        bail!(
+            "The message content is {} characters long, the maximum is {}.",
+            content.len(),
+            MAX_CONTENT_HEX_LEN
+        );
+    }
+    hex::decode(content).context("The message content is not valid hex.")
 }
 
 impl Ingress {
     pub fn parse(&self) -> AnyhowResult<(Principal, Principal, String, String)> {
-        let cbor: Value = serde_cbor::from_slice(&hex::decode(&self.content)?)
+        let (sender, canister_id, method_name, arg) = self.parse_raw()?;
+        let args = get_idl_string(
+            &arg,
+            canister_id,
+            &method_name,
+            ArgPart::Args,
+            OutputFormat::Pretty,
+        )?;
+        Ok((sender, canister_id, method_name, args))
+    }
+
+    /// Returns the sender, canister id, method name and the candid-encoded argument.
+    pub fn parse_raw(&self) -> AnyhowResult<(Principal, Principal, String, Vec<u8>)> {
+        if self.call_type != "query" && self.call_type != "update" {
+            bail!("Unsupported call type: {}", self.call_type);
+        }
+        let cbor: Value = serde_cbor::from_slice(&decode_content(&self.content)?)
             .context("Invalid cbor data in the content of the message.")?;
         if let Value::Map(m) = cbor {
             let cbor_content = m
@@ -70,17 +352,29 @@ impl Ingress {
                 ) {
                     let sender = Principal::try_from(sender)?;
                     let canister_id = Principal::try_from(canister_id)?;
-                    return Ok((
-                        sender,
-                        canister_id,
-                        method_name.to_string(),
-                        get_idl_string(arg, canister_id, method_name, "args")?,
-                    ));
+                    return Ok((sender, canister_id, method_name.to_string(), arg.clone()));
                 }
             }
         }
         Err(anyhow!("Invalid cbor content"))
     }
+
+    /// Returns the time after which the IC refuses the message, in seconds since the Unix
+    /// epoch.
+    pub fn expiry_seconds(&self) -> AnyhowResult<u64> {
+        let cbor: Value = serde_cbor::from_slice(&decode_content(&self.content)?)
+            .context("Invalid cbor data in the content of the message.")?;
+        if let Value::Map(m) = cbor {
+            if let Some(Value::Map(m)) = m.get(&Value::Text("content".to_string())) {
+                if let Some(Value::Integer(nanos)) =
+                    m.get(&Value::Text("ingress_expiry".to_string()))
+                {
+                    return Ok((*nanos / 1_000_000_000) as u64);
+                }
+            }
+        }
+        Err(anyhow!("The message has no expiry"))
+    }
 }
 
 pub fn request_status_sign(
@@ -91,6 +385,9 @@ pub fn request_status_sign(
     let agent = get_agent(auth)?;
     let val = agent.sign_request_status(canister_id, request_id)?;
     Ok(RequestStatus {
+        schema_version: BASE_SCHEMA_VERSION,
+        requires_quill: requires_quill(),
+        network: Some(get_network()),
         canister_id: canister_id.to_string(),
         request_id: request_id.into(),
         content: hex::encode(val.signed_request_status),
@@ -102,19 +399,48 @@ pub fn sign(
     canister_id: Principal,
     method_name: &str,
     args: Vec<u8>,
+) -> AnyhowResult<SignedMessageWithRequestId> {
+    let agent = get_agent(auth)?;
+    if !matches!(auth, AuthInfo::NoAuth) {
+        let sender = agent.get_principal().map_err(|e| anyhow!(e))?;
+        policy::enforce(sender, canister_id, method_name, &args)?;
+    }
+    sign_with_agent(&agent, canister_id, method_name, args)
+}
+
+/// Signs an update call like `sign`, without enforcing the key usage policy, e.g. for the test
+/// vectors of `self-test`.
+pub fn sign_without_policy(
+    auth: &AuthInfo,
+    canister_id: Principal,
+    method_name: &str,
+    args: Vec<u8>,
+) -> AnyhowResult<SignedMessageWithRequestId> {
+    sign_with_agent(&get_agent(auth)?, canister_id, method_name, args)
+}
+
+fn sign_with_agent(
+    agent: &Agent,
+    canister_id: Principal,
+    method_name: &str,
+    args: Vec<u8>,
 ) -> AnyhowResult<SignedMessageWithRequestId> {
     let ingress_expiry = Duration::from_secs(5 * 60);
 
-    let signed_update = UpdateBuilder::new(&get_agent(auth)?, canister_id, method_name.to_string())
+    let signed_update = UpdateBuilder::new(agent, canister_id, method_name.to_string())
         .with_arg(args)
         .expire_after(ingress_expiry)
         .sign()?;
+    check_ingress_size(canister_id, method_name, signed_update.signed_update.len())?;
 
     let content = hex::encode(signed_update.signed_update);
     let request_id = signed_update.request_id;
 
     Ok(SignedMessageWithRequestId {
         message: Ingress {
+            schema_version: BASE_SCHEMA_VERSION,
+            requires_quill: requires_quill(),
+            network: Some(get_network()),
             call_type: "update".to_string(),
             request_id: Some(request_id.into()),
             content,
@@ -123,6 +449,32 @@ pub fn sign(
     })
 }
 
+/// Signs a query call. Replies to query calls are not certified, so this is
+/// only meant for read-only data.
+pub fn sign_query(
+    auth: &AuthInfo,
+    canister_id: Principal,
+    method_name: &str,
+    args: Vec<u8>,
+) -> AnyhowResult<Ingress> {
+    let ingress_expiry = Duration::from_secs(5 * 60);
+
+    let signed_query = QueryBuilder::new(&get_agent(auth)?, canister_id, method_name.to_string())
+        .with_arg(args)
+        .expire_after(ingress_expiry)
+        .sign()?;
+    check_ingress_size(canister_id, method_name, signed_query.signed_query.len())?;
+
+    Ok(Ingress {
+        schema_version: BASE_SCHEMA_VERSION,
+        requires_quill: requires_quill(),
+        network: Some(get_network()),
+        call_type: "query".to_string(),
+        request_id: None,
+        content: hex::encode(signed_query.signed_query),
+    })
+}
+
 /// Generates a bundle of signed messages (ingress + request status query).
 pub fn sign_ingress_with_request_status_query(
     auth: &AuthInfo,
@@ -138,6 +490,138 @@ pub fn sign_ingress_with_request_status_query(
     let message = IngressWithRequestId {
         ingress: msg_with_req_id.message,
         request_status,
+        preconditions: Vec::new(),
     };
     Ok(message)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_ingress_size, decode_content, parse_bundle, requires_quill, Bundle, Ingress,
+        IngressWithRequestId, Precondition, MAX_CONTENT_HEX_LEN, MAX_INGRESS_BYTES,
+        MAX_NNS_INGRESS_BYTES, SCHEMA_VERSION,
+    };
+    use crate::lib::governance_canister_id;
+    use candid::Principal;
+
+    fn ingress(call_type: &str, content: &str) -> Ingress {
+        Ingress {
+            schema_version: 0,
+            requires_quill: None,
+            network: None,
+            call_type: call_type.to_string(),
+            request_id: None,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_decode_content_rejects_oversized() {
+        let content = "0".repeat(MAX_CONTENT_HEX_LEN + 2);
+        assert!(decode_content(&content).is_err());
+        assert!(decode_content("zz").is_err());
+        assert_eq!(decode_content("0a0b").unwrap(), vec![10, 11]);
+    }
+
+    #[test]
+    fn test_check_ingress_size() {
+        let other = Principal::anonymous();
+        assert!(check_ingress_size(
+            governance_canister_id(),
+            "manage_neuron",
+            MAX_NNS_INGRESS_BYTES
+        )
+        .is_ok());
+        assert!(check_ingress_size(
+            governance_canister_id(),
+            "manage_neuron",
+            MAX_NNS_INGRESS_BYTES + 1
+        )
+        .is_err());
+        assert!(check_ingress_size(other, "upload", MAX_INGRESS_BYTES).is_ok());
+        assert!(check_ingress_size(other, "upload", MAX_INGRESS_BYTES + 1).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_messages() {
+        // Unknown call type.
+        assert!(ingress("delete", "a0").parse().is_err());
+        // Truncated CBOR map.
+        assert!(ingress("update", "d9d9f7a3").parse().is_err());
+        // Valid CBOR which is not a map.
+        assert!(ingress("update", "01").parse().is_err());
+        // Empty content.
+        assert!(ingress("query", "").parse().is_err());
+    }
+
+    #[test]
+    fn test_parse_bundle_versions() {
+        // Bundles from before schema versions are still read.
+        let legacy = r#"[{"ingress": {"call_type": "update", "request_id": "ab", "content": "a0"},
+            "request_status": {"canister_id": "aaaaa-aa", "request_id": "ab", "content": "a0"}}]"#;
+        match parse_bundle(legacy, false).unwrap() {
+            Bundle::WithRequestStatus(messages) => {
+                assert_eq!(messages[0].ingress.schema_version, 0)
+            }
+            _ => panic!("Wrong bundle shape"),
+        }
+
+        let current = r#"[{"schema_version": 1, "call_type": "query", "request_id": null,
+            "content": "a0"}]"#;
+        assert!(matches!(
+            parse_bundle(current, false),
+            Ok(Bundle::Messages(_))
+        ));
+
+        let tagged = r#"{"schema_version": 2, "requires_quill": "0.4.0", "network": "mainnet",
+            "call_type": "query", "request_id": null, "content": "a0"}"#;
+        assert!(matches!(
+            parse_bundle(tagged, false),
+            Ok(Bundle::Ingress(_))
+        ));
+
+        let unknown = r#"{"schema_version": 1, "call_type": "query", "request_id": null,
+            "content": "a0", "note": "x"}"#;
+        assert!(parse_bundle(unknown, false).is_err());
+        assert!(matches!(
+            parse_bundle(unknown, true),
+            Ok(Bundle::Ingress(_))
+        ));
+
+        let newer = r#"{"schema_version": 99, "requires_quill": "9.0.0", "call_type": "query",
+            "content": "a0", "new_field": 1}"#;
+        let err = parse_bundle(newer, true).err().unwrap().to_string();
+        assert!(
+            err.starts_with("The bundle requires quill >= 9.0.0"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_preconditions() {
+        let bundle = r#"[{"ingress": {"call_type": "update", "request_id": "ab", "content": "a0"},
+            "request_status": {"canister_id": "aaaaa-aa", "request_id": "ab", "content": "a0"},
+            "preconditions": [{"kind": "proposal_open", "proposal_id": 123}]}]"#;
+        match parse_bundle(bundle, false).unwrap() {
+            Bundle::WithRequestStatus(messages) => assert_eq!(
+                messages[0].preconditions,
+                vec![Precondition::ProposalOpen { proposal_id: 123 }]
+            ),
+            _ => panic!("Wrong bundle shape"),
+        }
+    }
+
+    #[test]
+    fn test_with_preconditions() {
+        let message = IngressWithRequestId::default().with_preconditions(Vec::new());
+        assert_eq!(message.ingress.schema_version, 0);
+        assert!(message.ingress.requires_quill.is_none());
+
+        let message = IngressWithRequestId::default()
+            .with_preconditions(vec![Precondition::ProposalOpen { proposal_id: 123 }]);
+        assert_eq!(message.ingress.schema_version, SCHEMA_VERSION);
+        assert_eq!(message.ingress.requires_quill, requires_quill());
+    }
+}
@@ -0,0 +1,55 @@
+//! The canister ids of a Service Nervous System (SNS).
+//!
+//! Unlike the NNS canisters, the canisters of an SNS are not known in advance;
+//! they are read from a JSON file such as
+//!
+//! ```json
+//! {
+//!   "governance_canister_id": "...",
+//!   "ledger_canister_id": "...",
+//!   "root_canister_id": "...",
+//!   "swap_canister_id": "..."
+//! }
+//! ```
+//!
+//! `sns_canister_ids.json` in the configuration directory is used by default,
+//! and is what `send` consults to recognize messages to SNS canisters.
+
+use crate::lib::{config::config_dir, AnyhowResult};
+use anyhow::Context;
+use candid::Principal;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize)]
+pub struct SnsCanisterIds {
+    pub governance_canister_id: Principal,
+    pub ledger_canister_id: Principal,
+    pub root_canister_id: Principal,
+    pub swap_canister_id: Principal,
+}
+
+fn default_sns_canister_ids_file() -> AnyhowResult<PathBuf> {
+    Ok(config_dir()?.join("sns_canister_ids.json"))
+}
+
+/// Loads the SNS canister ids from the given file, or from the configuration
+/// directory if none is given.
+pub fn load_sns_canister_ids(path: Option<&Path>) -> AnyhowResult<SnsCanisterIds> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => default_sns_canister_ids_file()?,
+    };
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read the SNS canister ids file {}", path.display()))?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("Cannot parse the SNS canister ids file {}", path.display()))
+}
+
+/// Returns the SNS canister ids of the configuration directory, if there are any.
+pub fn configured_sns_canister_ids() -> AnyhowResult<Option<SnsCanisterIds>> {
+    match default_sns_canister_ids_file() {
+        Ok(path) if path.is_file() => load_sns_canister_ids(Some(&path)).map(Some),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,129 @@
+//! Formatting and parsing of the timestamps shown to and entered by users.
+//!
+//! Timestamps are displayed in local time unless `--utc` is given.
+
+use crate::lib::AnyhowResult;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DISPLAY_UTC: AtomicBool = AtomicBool::new(false);
+
+/// Makes all displayed timestamps use UTC instead of local time, and naive
+/// timestamps entered by the user be read as UTC.
+pub fn set_display_utc(utc: bool) {
+    DISPLAY_UTC.store(utc, Ordering::Relaxed);
+}
+
+/// Returns whether timestamps are displayed in UTC, i.e. whether `--utc` was given.
+pub fn display_utc() -> bool {
+    DISPLAY_UTC.load(Ordering::Relaxed)
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+pub fn now_seconds() -> AnyhowResult<u64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs())
+}
+
+/// Formats seconds since the Unix epoch as a date and time, e.g.
+/// `2022-12-20 15:03:20 +01:00`, or `2022-12-20 14:03:20 UTC` with `--utc`.
+pub fn format_timestamp_seconds(seconds: u64) -> String {
+    format_timestamp(seconds, display_utc())
+}
+
+/// Like `format_timestamp_seconds`, but in UTC or local time as given.
+pub fn format_timestamp(seconds: u64, utc: bool) -> String {
+    match Utc.timestamp_opt(seconds as i64, 0).single() {
+        Some(time) if utc => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        Some(time) => time
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+        None => seconds.to_string(),
+    }
+}
+
+/// Parses an instant entered by the user and returns it in seconds since the
+/// Unix epoch. Accepts seconds since the epoch, RFC 3339 (e.g.
+/// `2023-01-13T12:00:00+01:00`), `YYYY-MM-DD HH:MM[:SS]` and `YYYY-MM-DD`;
+/// the latter are read in local time, or in UTC with `--utc`.
+pub fn parse_timestamp(instant: &str) -> AnyhowResult<u64> {
+    parse_timestamp_in(instant, display_utc())
+}
+
+fn parse_timestamp_in(instant: &str, utc: bool) -> AnyhowResult<u64> {
+    let instant = instant.trim();
+    if !instant.is_empty() && instant.chars().all(|c| c.is_ascii_digit()) {
+        return instant.parse().context("Failed to parse the timestamp");
+    }
+    let seconds = if let Ok(time) = DateTime::parse_from_rfc3339(instant) {
+        time.timestamp()
+    } else {
+        let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(instant, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(instant, "%Y-%m-%d")
+                    .ok()
+                    .map(|date| date.and_hms(0, 0, 0))
+            })
+            .ok_or_else(|| anyhow!("Cannot parse the timestamp {}", instant))?;
+        if utc {
+            naive.timestamp()
+        } else {
+            Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .ok_or_else(|| anyhow!("{} does not exist in the local time zone", instant))?
+                .timestamp()
+        }
+    };
+    u64::try_from(seconds).context("Timestamps before 1970 are not supported")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_timestamp, parse_timestamp_in};
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0, true), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_timestamp(951_782_400, true),
+            "2000-02-29 00:00:00 UTC"
+        );
+        assert_eq!(
+            format_timestamp(1_671_545_000, true),
+            "2022-12-20 14:03:20 UTC"
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp_in("1671545000", true).ok(),
+            Some(1_671_545_000)
+        );
+        assert_eq!(
+            parse_timestamp_in("2022-12-20T15:03:20+01:00", false).ok(),
+            Some(1_671_545_000)
+        );
+        assert_eq!(
+            parse_timestamp_in("2022-12-20 14:03:20", true).ok(),
+            Some(1_671_545_000)
+        );
+        assert_eq!(
+            parse_timestamp_in("2022-12-20 14:03", true).ok(),
+            Some(1_671_544_980)
+        );
+        assert_eq!(
+            parse_timestamp_in("2022-12-20", true).ok(),
+            Some(1_671_494_400)
+        );
+        assert!(parse_timestamp_in("yesterday", true).is_err());
+        assert!(parse_timestamp_in("1969-12-31", true).is_err());
+    }
+}
@@ -1,10 +1,15 @@
 #![warn(unused_extern_crates)]
+// Builds without some of the default features leave helpers of the missing commands unused.
+#![cfg_attr(
+    not(all(feature = "online", feature = "qr", feature = "hsm")),
+    allow(dead_code, unused_imports)
+)]
 use std::path::{Path, PathBuf};
 
 use crate::lib::AnyhowResult;
 use anyhow::Context;
 use bip39::Mnemonic;
-use clap::{crate_version, Args, Parser};
+use clap::{crate_version, Args, CommandFactory, Parser};
 use lib::AuthInfo;
 
 mod commands;
@@ -14,6 +19,20 @@ mod lib;
 #[derive(Parser)]
 #[clap(name("quill"), version = crate_version!())]
 pub struct CliOpts {
+    /// Display timestamps in UTC instead of local time, and read entered dates as UTC.
+    #[clap(long, global(true))]
+    utc: bool,
+
+    /// Print only the results of commands and errors: progress messages and warnings are
+    /// suppressed, and prompts fail instead of waiting for input.
+    #[clap(long, global(true))]
+    quiet: bool,
+
+    /// Reads the arguments of the command from a JSON object, e.g. `{"neuron_id": "123",
+    /// "disburse": true}`, keyed by the names of the options and arguments.
+    #[clap(long, global(true), value_name("FILE"))]
+    args_from_json: Option<PathBuf>,
+
     #[clap(subcommand)]
     command: commands::Command,
 }
@@ -24,6 +43,8 @@ struct GlobalOpts {
     #[clap(long)]
     pem_file: Option<PathBuf>,
 
+    /// Use a NitroHSM for signing. The PIN is read from NITROHSM_PIN if set, otherwise it is
+    /// prompted for, which fails with --quiet.
     #[clap(long)]
     hsm: bool,
 
@@ -40,10 +61,32 @@ struct GlobalOpts {
     #[clap(long)]
     seed_file: Option<PathBuf>,
 
-    /// Output the result(s) as UTF-8 QR codes.
+    /// Output the result(s) as UTF-8 QR codes. Between QR codes, quill waits for Enter to be
+    /// pressed, so with --quiet only a single QR code can be output.
     #[clap(long)]
     qr: bool,
 
+    /// Writes the output of signing commands to a new file in this directory, e.g. the mount
+    /// point of a USB stick, instead of STDOUT. The file is synced to the device and verified by
+    /// reading it back, bypassing the page cache where the platform allows it.
+    #[clap(long, conflicts_with("qr"))]
+    output_device: Option<PathBuf>,
+
+    /// Writes the output of signing commands as a checksummed frame to this serial device, for
+    /// `send --from-serial` on the other end of a one-way link.
+    #[clap(long, conflicts_with_all(&["qr", "output-device"]))]
+    to_serial: Option<PathBuf>,
+
+    /// Writes a Markdown summary of the signed messages to this file, with their amounts,
+    /// recipients, expiry and hashes, to be reviewed and signed off before the messages are
+    /// sent. `send --review-summary` checks that a summary is the one of the bundle.
+    #[clap(long, conflicts_with("qr"))]
+    review_summary: Option<PathBuf>,
+
+    /// Unmounts the output device after writing to it.
+    #[clap(long, requires("output-device"))]
+    eject: bool,
+
     /// Fetches the root key before making requests so that interfacing with local instances is possible.
     /// DO NOT USE WITH ANY REAL INFORMATION
     #[clap(long = "insecure-local-dev-mode", name = "insecure-local-dev-mode")]
@@ -59,8 +102,7 @@ pub struct BaseOpts<T: Args> {
 }
 
 fn main() {
-    let opts = CliOpts::parse();
-    if let Err(err) = commands::dispatch(opts.command) {
+    if let Err(err) = run() {
         for (level, cause) in err.chain().enumerate() {
             if level == 0 {
                 eprintln!("Error: {}", err);
@@ -75,6 +117,22 @@ fn main() {
     }
 }
 
+fn run() -> AnyhowResult {
+    // The arguments read with --args-from-json are checked by clap like typed arguments.
+    let opts = if std::env::args_os().any(|arg| {
+        arg.to_str()
+            .map_or(false, |arg| arg.starts_with(lib::json_args::ARGS_FROM_JSON))
+    }) {
+        let args = lib::json_args::expand_args(std::env::args().collect(), &CliOpts::command())?;
+        CliOpts::parse_from(args)
+    } else {
+        CliOpts::parse()
+    };
+    lib::timestamp::set_display_utc(opts.utc);
+    lib::set_quiet(opts.quiet);
+    commands::dispatch(opts.command)
+}
+
 fn get_auth(opts: GlobalOpts) -> AnyhowResult<AuthInfo> {
     // Get PEM from the file if provided, or try to convert from the seed file
     if opts.hsm {
@@ -0,0 +1,69 @@
+//! Parsing of ledger account identifiers as they are copied from other places.
+
+use crate::AnyhowResult;
+use anyhow::anyhow;
+use ledger_canister::AccountIdentifier;
+
+/// Parses a hex-encoded account identifier and validates its checksum,
+/// accepting the formats in which account identifiers are usually copied:
+///
+/// - surrounded by quotes, or in upper case;
+/// - embedded in a dashboard URL, e.g.
+///   `https://dashboard.internetcomputer.org/account/<account id>`;
+/// - as an `icp:` URI, e.g. `icp:<account id>?amount=1.5`, as encoded in QR
+///   codes.
+pub fn parse_account_id(account_id: &str) -> AnyhowResult<AccountIdentifier> {
+    let trimmed = account_id
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .trim();
+    let without_scheme = trimmed
+        .strip_prefix("icp:")
+        .or_else(|| trimmed.strip_prefix("ICP:"))
+        .unwrap_or(trimmed);
+    let hex = without_scheme
+        .split(|c| c == '?' || c == '#')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .last()
+        .unwrap_or_default()
+        .to_lowercase();
+    AccountIdentifier::from_hex(&hex)
+        .map_err(|err| anyhow!("Invalid account id {}: {}", account_id, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_account_id;
+
+    const ACCOUNT_ID: &str = "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752";
+
+    #[test]
+    fn parses_account_ids() {
+        let id = |s: &str| parse_account_id(s).ok().map(|id| id.to_hex());
+        let expected = Some(ACCOUNT_ID.to_string());
+        assert_eq!(id(ACCOUNT_ID), expected);
+        assert_eq!(id(&format!("\"{}\"", ACCOUNT_ID)), expected);
+        assert_eq!(id(&ACCOUNT_ID.to_uppercase()), expected);
+        assert_eq!(
+            id(&format!(
+                "https://dashboard.internetcomputer.org/account/{}",
+                ACCOUNT_ID
+            )),
+            expected
+        );
+        assert_eq!(id(&format!("icp:{}?amount=1.5", ACCOUNT_ID)), expected);
+
+        // Wrong checksum.
+        assert_eq!(
+            id("445f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752"),
+            None
+        );
+        // Wrong length.
+        assert_eq!(id(&ACCOUNT_ID[2..]), None);
+        assert_eq!(id(""), None);
+        assert_eq!(id("https://dashboard.internetcomputer.org/account/"), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Amounts of ICP.
+//!
+//! Amounts entered and displayed in ICP (e.g. `1.5`) are `Icp`, amounts in the
+//! smallest unit used by the canisters are `E8s`. They only convert into each
+//! other explicitly, so that a raw number is never read in the wrong unit.
+//!
+//! With serde, `E8s` is a number of e8s and `Icp` the string it is displayed as, e.g.
+//! `"1.50000000"`, which also reads the formats parsed from the command line.
+
+use crate::AnyhowResult;
+use anyhow::{anyhow, bail, Context};
+use candid::Nat;
+use ledger_canister::Tokens;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+pub const E8S_PER_ICP: u64 = 100_000_000;
+
+/// An amount in e8s, i.e. 10^-8 ICP.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct E8s(u64);
+
+impl E8s {
+    pub const fn new(e8s: u64) -> Self {
+        E8s(e8s)
+    }
+
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+
+    pub fn checked_add(self, other: E8s) -> Option<E8s> {
+        self.0.checked_add(other.0).map(E8s)
+    }
+
+    pub fn checked_sub(self, other: E8s) -> Option<E8s> {
+        self.0.checked_sub(other.0).map(E8s)
+    }
+}
+
+impl fmt::Display for E8s {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} e8s", self.0)
+    }
+}
+
+impl From<Icp> for E8s {
+    fn from(icp: Icp) -> Self {
+        icp.0
+    }
+}
+
+impl From<Tokens> for E8s {
+    fn from(tokens: Tokens) -> Self {
+        E8s(tokens.get_e8s())
+    }
+}
+
+impl From<E8s> for Tokens {
+    fn from(e8s: E8s) -> Self {
+        Tokens::from_e8s(e8s.0)
+    }
+}
+
+impl From<E8s> for Nat {
+    fn from(e8s: E8s) -> Self {
+        Nat::from(e8s.0)
+    }
+}
+
+/// An amount in ICP, with up to 8 decimal digits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Icp(E8s);
+
+impl Icp {
+    /// Returns the amount of the given number of whole ICP.
+    pub fn whole(icp: u64) -> AnyhowResult<Self> {
+        icp.checked_mul(E8S_PER_ICP)
+            .map(|e8s| Icp(E8s(e8s)))
+            .ok_or_else(|| anyhow!("{} ICP is too large an amount", icp))
+    }
+}
+
+impl From<E8s> for Icp {
+    fn from(e8s: E8s) -> Self {
+        Icp(e8s)
+    }
+}
+
+impl fmt::Display for Icp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let e8s = (self.0).0;
+        write!(f, "{}.{:08}", e8s / E8S_PER_ICP, e8s % E8S_PER_ICP)
+    }
+}
+
+impl FromStr for Icp {
+    type Err = anyhow::Error;
+
+    /// Parses amounts such as `12`, `1_000` or `0.0001`, optionally followed by the unit `ICP`,
+    /// or amounts in e8s such as `150_000_000e8s`.
+    fn from_str(amount: &str) -> AnyhowResult<Self> {
+        let parse = |s: &str| {
+            s.trim()
+                .replace('_', "")
+                .parse::<u64>()
+                .with_context(|| format!("Cannot parse amount {}", amount))
+        };
+        let lowercase = amount.trim().to_ascii_lowercase();
+        if let Some(e8s) = lowercase.strip_suffix("e8s") {
+            return Ok(Icp(E8s(parse(e8s)?)));
+        }
+        let icp = lowercase.strip_suffix("icp").unwrap_or(&lowercase).trim();
+        let (icp, e8s) = match icp.split('.').collect::<Vec<_>>().as_slice() {
+            [icp] => (parse(icp)?, 0),
+            // Digits beyond the 8th decimal are below one e8 and are dropped.
+            [icp, fraction] => (parse(icp)?, parse(&format!("{:0<8.8}", fraction))?),
+            _ => bail!("Cannot parse amount {}", amount),
+        };
+        Icp::whole(icp)?
+            .0
+            .checked_add(E8s(e8s))
+            .map(Icp)
+            .ok_or_else(|| anyhow!("{} ICP is too large an amount", amount))
+    }
+}
+
+impl Serialize for Icp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Icp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{E8s, Icp};
+
+    #[test]
+    fn parses_icp() {
+        let e8s = |amount: &str| amount.parse::<Icp>().map(|icp| E8s::from(icp).get());
+        assert_eq!(e8s("12").unwrap(), 1_200_000_000);
+        assert_eq!(e8s("1_000").unwrap(), 100_000_000_000);
+        assert_eq!(e8s("0.0001").unwrap(), 10_000);
+        assert_eq!(e8s("1.5").unwrap(), 150_000_000);
+        assert_eq!(e8s("0.00000001").unwrap(), 1);
+        assert_eq!(e8s("0.0000000999999").unwrap(), 9);
+        assert_eq!(e8s("1.").unwrap(), 100_000_000);
+        assert!(e8s("1.2.3").is_err());
+        assert!(e8s("-1").is_err());
+        assert!(e8s("184467440738").is_err());
+
+        assert_eq!(e8s("1.5 ICP").unwrap(), 150_000_000);
+        assert_eq!(e8s("150_000_000e8s").unwrap(), 150_000_000);
+        assert_eq!(e8s("42 e8s").unwrap(), 42);
+        assert!(e8s("1.5e8s").is_err());
+    }
+
+    #[test]
+    fn converts_explicitly() {
+        let icp = Icp::whole(3).unwrap();
+        assert_eq!(E8s::from(icp), E8s::new(300_000_000));
+        assert_eq!(Icp::from(E8s::new(150_000_001)).to_string(), "1.50000001");
+        assert_eq!(E8s::new(42).to_string(), "42 e8s");
+    }
+
+    #[test]
+    fn serializes() {
+        let icp = Icp::from(E8s::new(150_000_000));
+        assert_eq!(serde_json::to_string(&icp).unwrap(), r#""1.50000000""#);
+        assert_eq!(serde_json::from_str::<Icp>(r#""1.5 ICP""#).unwrap(), icp);
+        assert_eq!(serde_json::to_string(&E8s::from(icp)).unwrap(), "150000000");
+        assert!(serde_json::from_str::<Icp>("1.5").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Memos of ICP transfers, which tell the recipient what a transfer is for.
+//!
+//! A memo is entered as a decimal number (e.g. `42` or `1_000`), as hex digits prefixed with `0x`,
+//! or as text. Text of up to 8 ASCII characters is stored as its bytes in little-endian order, as
+//! the protocol memos such as `TPUP` are; longer text is hashed to the first 8 bytes of its
+//! SHA-256, in big-endian order.
+
+use crate::AnyhowResult;
+use anyhow::{bail, Context};
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+/// The memo of an ICP transfer.
+#[derive(
+    Serialize,
+    Deserialize,
+    CandidType,
+    Clone,
+    Copy,
+    Hash,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+)]
+#[serde(transparent)]
+pub struct Memo(pub u64);
+
+impl Memo {
+    /// The memo of a transfer to the cycles minting canister topping up a canister, `TPUP`.
+    pub const TOP_UP_CANISTER: Memo = Memo(0x5055_5054);
+
+    /// The memo of a transfer to the cycles minting canister creating a canister, `CREA`.
+    pub const CREATE_CANISTER: Memo = Memo(0x4145_5243);
+
+    /// Returns the memo of a text: its bytes if it is ASCII of up to 8 characters, its hash
+    /// otherwise.
+    pub fn from_text(text: &str) -> Self {
+        if text.is_ascii() && text.len() <= 8 {
+            let mut bytes = [0; 8];
+            bytes[..text.len()].copy_from_slice(text.as_bytes());
+            Memo(u64::from_le_bytes(bytes))
+        } else {
+            Memo::hash_text(text)
+        }
+    }
+
+    /// Returns the first 8 bytes of the SHA-256 of the text, in big-endian order.
+    pub fn hash_text(text: &str) -> Self {
+        let hash = openssl::sha::sha256(text.as_bytes());
+        Memo(u64::from_be_bytes(hash[..8].try_into().unwrap()))
+    }
+
+    /// Returns the name of a protocol memo.
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Memo::TOP_UP_CANISTER => Some("TPUP"),
+            Memo::CREATE_CANISTER => Some("CREA"),
+            _ => None,
+        }
+    }
+}
+
+impl From<u64> for Memo {
+    fn from(memo: u64) -> Self {
+        Memo(memo)
+    }
+}
+
+impl From<Memo> for ledger_canister::Memo {
+    fn from(memo: Memo) -> Self {
+        ledger_canister::Memo(memo.0)
+    }
+}
+
+impl fmt::Display for Memo {
+    /// Displays the number of the memo, followed by the name of a protocol memo, e.g.
+    /// `1347768404 (TPUP)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", self.0, name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl FromStr for Memo {
+    type Err = anyhow::Error;
+
+    fn from_str(memo: &str) -> AnyhowResult<Self> {
+        let trimmed = memo.trim();
+        if trimmed.is_empty() {
+            bail!("The memo is empty");
+        }
+        if let Some(hex) = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+        {
+            return u64::from_str_radix(&hex.replace('_', ""), 16)
+                .map(Memo)
+                .with_context(|| format!("Cannot parse the hex memo {}", memo));
+        }
+        if trimmed.chars().all(|c| c.is_ascii_digit() || c == '_') {
+            return trimmed
+                .replace('_', "")
+                .parse()
+                .map(Memo)
+                .with_context(|| format!("The memo {} is too large", memo));
+        }
+        Ok(Memo::from_text(trimmed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Memo;
+
+    #[test]
+    fn parses_memos() {
+        let memo = |s: &str| s.parse::<Memo>().map(|memo| memo.0);
+        assert_eq!(memo("42").unwrap(), 42);
+        assert_eq!(memo("1_000").unwrap(), 1000);
+        assert_eq!(memo("0x2a").unwrap(), 42);
+        assert_eq!(memo("TPUP").unwrap(), Memo::TOP_UP_CANISTER.0);
+        assert_eq!(memo("CREA").unwrap(), Memo::CREATE_CANISTER.0);
+        assert_eq!(
+            memo("invoice 2023-0042").unwrap(),
+            Memo::hash_text("invoice 2023-0042").0
+        );
+        assert!(memo("").is_err());
+        assert!(memo("0xg").is_err());
+        assert!(memo("18446744073709551616").is_err());
+    }
+
+    #[test]
+    fn names_protocol_memos() {
+        assert_eq!(Memo::TOP_UP_CANISTER.to_string(), "1347768404 (TPUP)");
+        assert_eq!(Memo(7).to_string(), "7");
+    }
+}
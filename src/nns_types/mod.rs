@@ -0,0 +1,17 @@
+//! Types shared by the commands building NNS messages, and by other tools through the `quill`
+//! library.
+
+pub mod account_id;
+pub mod icpts;
+pub mod memo;
+pub mod neuron_id;
+pub mod payment_request;
+pub mod subaccount;
+pub mod topic;
+
+pub use memo::Memo;
+pub use subaccount::Subaccount;
+
+/// The ledger type in the signatures of this module, so that users do not depend on a second
+/// version of the ledger.
+pub use ledger_canister::AccountIdentifier;
@@ -0,0 +1,94 @@
+//! Parsing of neuron ids as they are copied from other places.
+
+use crate::AnyhowResult;
+use anyhow::{anyhow, bail, Context};
+
+/// Parses a neuron id, accepting the formats in which neuron ids are usually
+/// copied:
+///
+/// - with digit separators, e.g. `4_123_567`, `4,123,567` or `4 123 567`; commas
+///   and spaces must separate groups of three digits;
+/// - surrounded by quotes;
+/// - embedded in an NNS dapp URL, e.g.
+///   `https://nns.ic0.app/neuron/?u=qoctq-giaaa-aaaaa-aaaea-cai&neuron=4123567`
+///   or `https://nns.ic0.app/#/neuron/4123567`.
+pub fn parse_neuron_id(id: &str) -> AnyhowResult<u64> {
+    let trimmed = id
+        .trim()
+        .trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .trim();
+    let number = if let Some(start) = trimmed.find("neuron=") {
+        trimmed[start + "neuron=".len()..]
+            .split(|c| c == '&' || c == '#')
+            .next()
+            .unwrap_or_default()
+    } else if trimmed.contains('/') {
+        trimmed
+            .split(|c| c == '?' || c == '&')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .last()
+            .ok_or_else(|| anyhow!("Cannot find a neuron id in {}", id))?
+    } else {
+        trimmed
+    };
+    let number = number.replace('_', "");
+    let separators = [',', ' ']
+        .iter()
+        .filter(|separator| number.contains(**separator))
+        .collect::<Vec<_>>();
+    let digits = match separators.as_slice() {
+        [] => number,
+        [separator] => {
+            let groups = number.split(**separator).collect::<Vec<_>>();
+            if !(1..=3).contains(&groups[0].len()) || groups[1..].iter().any(|g| g.len() != 3) {
+                bail!(
+                    "Failed to parse the neuron id {}: '{}' must separate groups of three digits",
+                    id,
+                    separator
+                );
+            }
+            groups.concat()
+        }
+        _ => bail!(
+            "Failed to parse the neuron id {}: mixed digit separators",
+            id
+        ),
+    };
+    digits
+        .parse()
+        .with_context(|| format!("Failed to parse the neuron id {}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_neuron_id;
+
+    #[test]
+    fn parses_neuron_ids() {
+        let id = |s: &str| parse_neuron_id(s).ok();
+        assert_eq!(id("4123567"), Some(4123567));
+        assert_eq!(id("4_123_567"), Some(4123567));
+        assert_eq!(id("4,123,567"), Some(4123567));
+        assert_eq!(id(" 4 123 567 "), Some(4123567));
+        assert_eq!(id("\"4123567\""), Some(4123567));
+        assert_eq!(id("'4_123_567'"), Some(4123567));
+        assert_eq!(
+            id("https://nns.ic0.app/neuron/?u=qoctq-giaaa-aaaaa-aaaea-cai&neuron=4123567"),
+            Some(4123567)
+        );
+        assert_eq!(id("https://nns.ic0.app/#/neuron/4123567"), Some(4123567));
+        assert_eq!(id("nns.ic0.app/#/neuron/4123567/"), Some(4123567));
+
+        assert_eq!(id(""), None);
+        assert_eq!(id("12a"), None);
+        assert_eq!(id("-1"), None);
+        assert_eq!(id("1,2"), None);
+        assert_eq!(id("12,3456"), None);
+        assert_eq!(id("1234,567"), None);
+        assert_eq!(id("4,123 567"), None);
+        assert_eq!(id("https://nns.ic0.app/neurons/"), None);
+    }
+}
@@ -0,0 +1,113 @@
+//! Payment requests, encoded as `icp:` URIs such as
+//! `icp:<account id>?amount=1.5&memo=42`, which mobile wallets emit and read
+//! from QR codes.
+
+use crate::{
+    nns_types::{account_id::parse_account_id, icpts::Icp},
+    AnyhowResult,
+};
+use anyhow::{anyhow, Context};
+use ledger_canister::AccountIdentifier;
+
+const SCHEME: &str = "icp:";
+
+/// A request to transfer ICP to an account.
+pub struct PaymentRequest {
+    pub to: AccountIdentifier,
+    pub amount: Option<Icp>,
+    pub memo: Option<u64>,
+}
+
+impl PaymentRequest {
+    /// Returns whether the text is a payment request URI rather than a plain account id.
+    pub fn is_uri(text: &str) -> bool {
+        text.trim()
+            .get(..SCHEME.len())
+            .map_or(false, |scheme| scheme.eq_ignore_ascii_case(SCHEME))
+    }
+
+    /// Parses a payment request URI. Unknown parameters are ignored.
+    pub fn from_uri(uri: &str) -> AnyhowResult<Self> {
+        if !Self::is_uri(uri) {
+            return Err(anyhow!("{} is not an {} URI", uri, SCHEME));
+        }
+        let uri = uri.trim();
+        let (account, query) = match uri.find('?') {
+            Some(i) => (&uri[..i], &uri[i + 1..]),
+            None => (uri, ""),
+        };
+        let mut request = PaymentRequest {
+            to: parse_account_id(account)?,
+            amount: None,
+            memo: None,
+        };
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_at(param.find('=').unwrap_or(param.len()));
+            let value = value.trim_start_matches('=');
+            match key {
+                "amount" => request.amount = Some(value.parse()?),
+                "memo" => {
+                    request.memo = Some(value.parse().with_context(|| {
+                        format!("Failed to parse the memo {} of the payment request", value)
+                    })?)
+                }
+                _ => {}
+            }
+        }
+        Ok(request)
+    }
+
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(amount) = self.amount {
+            let amount = amount.to_string();
+            params.push(format!(
+                "amount={}",
+                amount.trim_end_matches('0').trim_end_matches('.')
+            ));
+        }
+        if let Some(memo) = self.memo {
+            params.push(format!("memo={}", memo));
+        }
+        let mut uri = format!("{}{}", SCHEME, self.to.to_hex());
+        if !params.is_empty() {
+            uri.push('?');
+            uri.push_str(&params.join("&"));
+        }
+        uri
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PaymentRequest;
+    use crate::nns_types::icpts::{E8s, Icp};
+
+    const ACCOUNT_ID: &str = "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752";
+
+    #[test]
+    fn round_trips() {
+        let uri = format!("icp:{}?amount=10.5&memo=42", ACCOUNT_ID);
+        let request = PaymentRequest::from_uri(&uri).unwrap();
+        assert_eq!(request.to.to_hex(), ACCOUNT_ID);
+        assert_eq!(
+            request.amount.map(|icp| E8s::from(icp).get()),
+            Some(1_050_000_000)
+        );
+        assert_eq!(request.memo, Some(42));
+        assert_eq!(request.to_uri(), uri);
+
+        let request = PaymentRequest::from_uri(&format!("ICP:{}?label=shop", ACCOUNT_ID)).unwrap();
+        assert!(request.amount.is_none() && request.memo.is_none());
+        assert_eq!(request.to_uri(), format!("icp:{}", ACCOUNT_ID));
+
+        let request = PaymentRequest {
+            amount: Some(Icp::whole(10).unwrap()),
+            ..request
+        };
+        assert_eq!(request.to_uri(), format!("icp:{}?amount=10", ACCOUNT_ID));
+
+        assert!(PaymentRequest::from_uri(ACCOUNT_ID).is_err());
+        assert!(PaymentRequest::from_uri(&format!("icp:{}?amount=x", ACCOUNT_ID)).is_err());
+    }
+}
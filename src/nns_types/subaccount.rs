@@ -0,0 +1,127 @@
+//! Subaccounts, which distinguish the ledger accounts of the same principal.
+//!
+//! Subaccounts are entered and displayed as 64 hex digits, and serialize the same way.
+
+use crate::AnyhowResult;
+use anyhow::{anyhow, Context};
+use candid::Principal;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// A 32-byte subaccount.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Subaccount(pub [u8; 32]);
+
+impl Subaccount {
+    /// Returns the subaccount of a principal, e.g. the one to which the cycles minting canister
+    /// expects the ICP topping up a canister: the length of the principal followed by its bytes.
+    pub fn from_principal(principal: &Principal) -> Self {
+        let bytes = principal.as_slice();
+        let mut subaccount = [0; 32];
+        subaccount[0] = bytes.len() as u8;
+        subaccount[1..=bytes.len()].copy_from_slice(bytes);
+        Subaccount(subaccount)
+    }
+
+    /// Returns the subaccount of the governance canister staking the neuron of a controller with
+    /// the given nonce, i.e. the memo of the staking transfer. This _must_ correspond to how the
+    /// governance canister computes it; SNS governance canisters compute it the same way.
+    pub fn from_nonce(controller: &Principal, nonce: u64) -> Self {
+        use openssl::sha::Sha256;
+        let mut data = Sha256::new();
+        data.update(&[0x0c]);
+        data.update(b"neuron-stake");
+        data.update(controller.as_slice());
+        data.update(&nonce.to_be_bytes());
+        Subaccount(data.finish())
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+}
+
+impl fmt::Display for Subaccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl FromStr for Subaccount {
+    type Err = anyhow::Error;
+
+    /// Parses a subaccount of 64 hex digits, optionally prefixed with `0x`.
+    fn from_str(subaccount: &str) -> AnyhowResult<Self> {
+        let trimmed = subaccount.trim();
+        let digits = trimmed.strip_prefix("0x").unwrap_or(trimmed);
+        let bytes = hex::decode(digits)
+            .with_context(|| format!("The subaccount {} must be hex-encoded", subaccount))?;
+        <[u8; 32]>::try_from(bytes.as_slice())
+            .map(Subaccount)
+            .map_err(|_| anyhow!("The subaccount {} must be 32 bytes long", subaccount))
+    }
+}
+
+impl From<Subaccount> for ledger_canister::Subaccount {
+    fn from(subaccount: Subaccount) -> Self {
+        ledger_canister::Subaccount(subaccount.0)
+    }
+}
+
+impl From<ledger_canister::Subaccount> for Subaccount {
+    fn from(subaccount: ledger_canister::Subaccount) -> Self {
+        Subaccount(subaccount.0)
+    }
+}
+
+impl Serialize for Subaccount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Subaccount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Subaccount;
+    use candid::Principal;
+
+    #[test]
+    fn derives_subaccounts() {
+        let principal = Principal::from_text("rrkah-fqaaa-aaaaa-aaaaq-cai").unwrap();
+        assert_eq!(
+            Subaccount::from_principal(&principal).to_hex(),
+            "0a00000000000000010101000000000000000000000000000000000000000000"
+        );
+        assert_ne!(
+            Subaccount::from_nonce(&principal, 1),
+            Subaccount::from_nonce(&principal, 2)
+        );
+    }
+
+    #[test]
+    fn parses_hex() {
+        let hex = "0a00000000000000010101000000000000000000000000000000000000000000";
+        let subaccount: Subaccount = hex.parse().unwrap();
+        assert_eq!(subaccount.to_string(), hex);
+        assert_eq!(
+            format!("0x{}", hex).parse::<Subaccount>().unwrap(),
+            subaccount
+        );
+        assert!(hex[2..].parse::<Subaccount>().is_err());
+        assert!("xyz".parse::<Subaccount>().is_err());
+        assert_eq!(
+            serde_json::to_string(&subaccount).unwrap(),
+            format!("\"{}\"", hex)
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Names of the topics of NNS proposals, which neurons follow separately.
+
+use crate::AnyhowResult;
+use anyhow::anyhow;
+use std::convert::TryFrom;
+
+/// The topics by their number in the governance canister.
+const TOPICS: &[&str] = &[
+    "Unspecified",
+    "NeuronManagement",
+    "ExchangeRate",
+    "NetworkEconomics",
+    "Governance",
+    "NodeAdmin",
+    "ParticipantManagement",
+    "SubnetManagement",
+    "NetworkCanisterManagement",
+    "Kyc",
+    "NodeProviderRewards",
+    "SnsDecentralizationSale",
+    "SubnetReplicaVersionManagement",
+    "ReplicaVersionManagement",
+    "SnsAndCommunityFund",
+];
+
+/// Returns the name of a topic, or its number if it is unknown to this version of quill.
+pub fn topic_name(topic: i32) -> String {
+    usize::try_from(topic)
+        .ok()
+        .and_then(|index| TOPICS.get(index))
+        .map_or_else(|| format!("Topic {}", topic), |name| name.to_string())
+}
+
+/// Parses a topic given by its number or its name, ignoring case, `-` and `_`, e.g. `4`,
+/// `Governance` or `sns-and-community-fund`.
+pub fn parse_topic(topic: &str) -> AnyhowResult<i32> {
+    let topic = topic.trim();
+    if let Ok(number) = topic.parse::<i32>() {
+        return Ok(number);
+    }
+    let normalize = |name: &str| {
+        name.chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_lowercase()
+    };
+    TOPICS
+        .iter()
+        .position(|name| normalize(name) == normalize(topic))
+        .map(|index| index as i32)
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown topic {}, expected one of {}",
+                topic,
+                TOPICS.join(", ")
+            )
+        })
+}
+
+#[test]
+fn test_topic_name() {
+    assert_eq!(topic_name(0), "Unspecified");
+    assert_eq!(topic_name(4), "Governance");
+    assert_eq!(topic_name(99), "Topic 99");
+    assert_eq!(topic_name(-1), "Topic -1");
+}
+
+#[test]
+fn test_parse_topic() {
+    assert_eq!(parse_topic("4").unwrap(), 4);
+    assert_eq!(parse_topic("Governance").unwrap(), 4);
+    assert_eq!(parse_topic("SnsAndCommunityFund").unwrap(), 14);
+    assert_eq!(parse_topic("neuron-management").unwrap(), 1);
+    assert_eq!(parse_topic("99").unwrap(), 99);
+    assert!(parse_topic("Gouvernance").is_err());
+}
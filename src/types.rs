@@ -0,0 +1,20 @@
+//! The types with which quill builds messages for the NNS canisters, for other tools for the
+//! Internet Computer which parse and display the same amounts, accounts and neuron ids.
+//!
+//! The items of [`nns_types`] follow semantic versioning: they are only changed incompatibly,
+//! including the formats they parse and display, in a new major version of quill (or a new minor
+//! version before 1.0). The rest of quill, i.e. the commands, is not a library.
+//!
+//! ```
+//! use quill::nns_types::{account_id::parse_account_id, icpts::{E8s, Icp}};
+//!
+//! let amount: Icp = "1.5 ICP".parse()?;
+//! assert_eq!(E8s::from(amount).get(), 150_000_000);
+//! let to = parse_account_id("icp:345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752")?;
+//! assert_eq!(to.to_hex(), "345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752");
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod nns_types;
+
+type AnyhowResult<T = ()> = anyhow::Result<T>;
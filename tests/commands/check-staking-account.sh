@@ -0,0 +1 @@
+"$QUILL" check-staking-account a0ea9002c2bc3d442050f4431f3732c91dbec13eff79f414b15255d60c4a324c --memo 777 --pem-file -
@@ -0,0 +1 @@
+"$QUILL" neuron claim --controller 2vxsx-fae --name myNeuron --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" claim-neuron --memo 777 --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+QUILL_CONFIG_DIR=config "$QUILL" custom ledger send_dfx --set to=345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752 --set e8s=12300 --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" get-full-neuron 2313380519530470538 --pem-file - | "$QUILL" send --dry-run -
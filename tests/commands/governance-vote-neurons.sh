@@ -0,0 +1 @@
+"$QUILL" governance vote 2313380519530470538 123 --also-vote-with 456,789 --pem-file - | "$QUILL" send --dry-run -
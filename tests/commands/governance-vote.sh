@@ -0,0 +1 @@
+"$QUILL" governance vote 2313380519530470538 123 456 --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" governance neuron-ids --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" list-proposals --limit 10 --before 12345 --dry-run
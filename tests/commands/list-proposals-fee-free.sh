@@ -0,0 +1 @@
+"$QUILL" list-proposals --fee-free --dry-run
@@ -0,0 +1 @@
+"$QUILL" list-proposals --pending --dry-run
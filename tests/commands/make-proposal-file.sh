@@ -0,0 +1 @@
+"$QUILL" make-proposal 2313380519530470538 --proposal-file <(echo 'record { title = opt "Adopt the roadmap"; summary = "Adopts the roadmap of the community."; url = ""; action = opt variant { Motion = record { motion_text = "Adopt the 2023 roadmap" } } }') --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" make-proposal 2313380519530470538 --title "Adopt the roadmap" --summary "Adopts the roadmap of the community." --motion-text "Adopt the 2023 roadmap" --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --pem-file - --add-hot-key fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae --add-hot-key rrkah-fqaaa-aaaaa-aaaaq-cai | "$QUILL" send --dry-run -
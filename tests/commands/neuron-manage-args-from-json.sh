@@ -0,0 +1 @@
+"$QUILL" neuron-manage --args-from-json <(echo '{"neuron_id": "2313380519530470538", "register_vote": [123, 456]}') --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --auto-stake-maturity true --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --disburse-to-neuron 1.5 --new-neuron-dissolve-delay 180d --new-neuron-nonce 42 --skip-refresh-voting-power --pem-file - | "$QUILL" send --dry-run -
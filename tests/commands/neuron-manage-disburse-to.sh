@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --disburse --disburse-amount 1.5 --disburse-to 345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752 --skip-refresh-voting-power --pem-file - | "$QUILL" send --dry-run -
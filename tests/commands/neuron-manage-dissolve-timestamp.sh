@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --dissolve-timestamp 2100-01-01T00:00:00Z --skip-refresh-voting-power --pem-file - | "$QUILL" send --dry-run -
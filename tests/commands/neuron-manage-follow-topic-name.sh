@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --follow-topic Unspecified --follow-neurons 380519530470538,380519530470539 --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --leave-neurons-fund --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --refresh-voting-power --pem-file - | "$QUILL" send --dry-run -
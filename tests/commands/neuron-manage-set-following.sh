@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --set-following 4=380519530470538,380519530470539 --set-following 14= --pem-file - | "$QUILL" send --dry-run -
@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --spawn --spawn-to fdsgv-62ihb-nbiqv-xgic5-iefsv-3cscz-tmbzv-63qd5-vh43v-dqfrt-pae --pem-file - | "$QUILL" send --dry-run -
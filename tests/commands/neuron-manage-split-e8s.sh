@@ -0,0 +1 @@
+"$QUILL" neuron-manage 2313380519530470538 --split 10_000_000_000e8s --pem-file - | "$QUILL" send --dry-run -
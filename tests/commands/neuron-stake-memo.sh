@@ -0,0 +1 @@
+"$QUILL" neuron-stake --amount 12 --memo 777 --pem-file - | "$QUILL" send --dry-run -
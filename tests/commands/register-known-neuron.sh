@@ -0,0 +1 @@
+"$QUILL" register-known-neuron 2313380519530470538 --name Alice --description "Votes on governance proposals" --pem-file - | "$QUILL" send --dry-run -
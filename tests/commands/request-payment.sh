@@ -0,0 +1,2 @@
+"$QUILL" request-payment --to 345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752 --amount 10.5 --memo 777
+"$QUILL" transfer "icp:345f723e9e619934daac6ae0f4be13a7b0ba57d6a608e511a00fd0ded5866752?amount=0.000123" --pem-file - | "$QUILL" send --dry-run -
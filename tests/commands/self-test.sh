@@ -0,0 +1 @@
+"$QUILL" self-test